@@ -0,0 +1,387 @@
+//! Step debugger built on [`Exec::exec_next`], used by the interactive mode
+//! of `btcexec`.
+//!
+//! The execution stops on the [`Breakpoint`]s and before each OP_RESERVED
+//! (`DEBUG`) marker. With [`Options::debug_markers`](crate::Options::debug_markers)
+//! the markers are skipped when the execution is resumed; otherwise, they
+//! fail it with [`ExecError::Debug`](crate::ExecError::Debug).
+
+use core::fmt;
+use core::mem;
+use core::str::FromStr;
+
+use bitcoin::opcodes::{all::OP_RESERVED, Opcode};
+use bitcoin::script::Instruction;
+
+use crate::{Exec, ExecutionResult};
+
+/// Comparison of the stack depth with the limit of [`Breakpoint::Depth`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthCmp {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl DepthCmp {
+    fn holds(self, depth: usize, limit: usize) -> bool {
+        match self {
+            DepthCmp::Less => depth < limit,
+            DepthCmp::LessOrEqual => depth <= limit,
+            DepthCmp::Equal => depth == limit,
+            DepthCmp::GreaterOrEqual => depth >= limit,
+            DepthCmp::Greater => depth > limit,
+        }
+    }
+}
+
+impl fmt::Display for DepthCmp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DepthCmp::Less => "<",
+            DepthCmp::LessOrEqual => "<=",
+            DepthCmp::Equal => "==",
+            DepthCmp::GreaterOrEqual => ">=",
+            DepthCmp::Greater => ">",
+        })
+    }
+}
+
+impl FromStr for DepthCmp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "<" => Ok(DepthCmp::Less),
+            "<=" => Ok(DepthCmp::LessOrEqual),
+            "==" | "=" => Ok(DepthCmp::Equal),
+            ">=" => Ok(DepthCmp::GreaterOrEqual),
+            ">" => Ok(DepthCmp::Greater),
+            _ => Err(format!("unknown comparison: {}", s)),
+        }
+    }
+}
+
+/// Condition to stop the execution at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before the instruction at the given byte offset of the script
+    Offset(usize),
+    /// Before each instance of the opcode
+    Opcode(Opcode),
+    /// When the number of the main stack elements starts satisfying the
+    /// condition, so that it does not stop on every step while it holds
+    Depth(DepthCmp, usize),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Offset(offset) => write!(f, "offset {}", offset),
+            Breakpoint::Opcode(op) => write!(f, "op {}", op),
+            Breakpoint::Depth(cmp, limit) => write!(f, "depth {} {}", cmp, limit),
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    /// Parses `offset <n>`, `op <opcode>` or `depth <cmp> <n>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let number = |word: &str| {
+            word.parse::<usize>()
+                .map_err(|_| format!("invalid number: {}", word))
+        };
+
+        match words.as_slice() {
+            ["offset", offset] => Ok(Breakpoint::Offset(number(offset)?)),
+            ["op", name] => parse_opcode(name).map(Breakpoint::Opcode),
+            ["depth", cmp, limit] => Ok(Breakpoint::Depth(cmp.parse()?, number(limit)?)),
+            _ => Err(format!("invalid breakpoint: {}", s)),
+        }
+    }
+}
+
+/// Parses the opcode by its name, with or without the `OP_` prefix.
+/// `DEBUG` stands for OP_RESERVED, as in the `script!` macro.
+fn parse_opcode(name: &str) -> Result<Opcode, String> {
+    if name == "DEBUG" {
+        return Ok(OP_RESERVED);
+    }
+
+    let name = name.strip_prefix("OP_").unwrap_or(name);
+    (0..=u8::MAX)
+        .map(Opcode::from)
+        .find(|op| op.to_string().strip_prefix("OP_") == Some(name))
+        .ok_or_else(|| format!("unknown opcode: {}", name))
+}
+
+/// Why the execution stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    /// The breakpoint with the given index was hit
+    Breakpoint(usize),
+    /// The next instruction is an OP_RESERVED (`DEBUG`) marker
+    Marker,
+    /// The requested number of steps was executed
+    Step,
+    /// The execution ended, successfully or not
+    Finished(ExecutionResult),
+}
+
+/// Execution with breakpoints.
+pub struct Debugger {
+    exec: Exec,
+    /// Breakpoints, identified by their index
+    pub breakpoints: Vec<Breakpoint>,
+    /// Whether the execution was stepped or resumed already
+    started: bool,
+}
+
+impl Debugger {
+    pub fn new(exec: Exec) -> Self {
+        Self {
+            exec,
+            breakpoints: Vec::new(),
+            started: false,
+        }
+    }
+
+    pub fn exec(&self) -> &Exec {
+        &self.exec
+    }
+
+    /// The instruction to be executed next, if any.
+    pub fn next_instruction(&self) -> Option<Instruction> {
+        self.exec.remaining_script().instructions().next()?.ok()
+    }
+
+    /// Executes at most `steps` instructions, ignoring the breakpoints.
+    pub fn step(&mut self, steps: usize) -> Stop {
+        for _ in 0..steps {
+            if let Some(stop) = self.exec_next() {
+                return stop;
+            }
+        }
+
+        Stop::Step
+    }
+
+    /// Executes until a breakpoint or a marker is hit, or the execution
+    /// ends. At least one instruction is executed, so that resuming does not
+    /// stop at the same place again, except for the first resume, which
+    /// stops before the first instruction if it is hit.
+    pub fn resume(&mut self) -> Stop {
+        if !mem::replace(&mut self.started, true) {
+            if let Some(stop) = self.hit(self.exec.stack().len()) {
+                return stop;
+            }
+        }
+
+        loop {
+            let depth = self.exec.stack().len();
+            if let Some(stop) = self.exec_next() {
+                return stop;
+            }
+            if let Some(stop) = self.hit(depth) {
+                return stop;
+            }
+        }
+    }
+
+    /// Executes until the execution ends or fails, ignoring the breakpoints
+    /// and the markers.
+    pub fn run(&mut self) -> ExecutionResult {
+        loop {
+            if let Some(Stop::Finished(result)) = self.exec_next() {
+                return result;
+            }
+        }
+    }
+
+    fn exec_next(&mut self) -> Option<Stop> {
+        self.started = true;
+        self.exec
+            .exec_next()
+            .err()
+            .map(|result| Stop::Finished(result.clone()))
+    }
+
+    /// Checks whether the execution should stop before the next instruction,
+    /// given the stack depth before the previous one.
+    fn hit(&self, previous_depth: usize) -> Option<Stop> {
+        let position = self.exec.script_position();
+        let next_op = match self.next_instruction() {
+            Some(Instruction::Op(op)) => Some(op),
+            _ => None,
+        };
+        let depth = self.exec.stack().len();
+
+        let breakpoint = self
+            .breakpoints
+            .iter()
+            .position(|breakpoint| match *breakpoint {
+                Breakpoint::Offset(offset) => offset == position,
+                Breakpoint::Opcode(op) => next_op == Some(op),
+                Breakpoint::Depth(cmp, limit) => {
+                    cmp.holds(depth, limit) && !cmp.holds(previous_depth, limit)
+                }
+            });
+        if let Some(index) = breakpoint {
+            return Some(Stop::Breakpoint(index));
+        }
+
+        (next_op == Some(OP_RESERVED)).then_some(Stop::Marker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::hashes::Hash;
+    use bitcoin::opcodes::all::*;
+    use bitcoin::script::Builder;
+    use bitcoin::taproot::TapLeafHash;
+    use bitcoin::{absolute::LockTime, transaction, Transaction};
+
+    use super::*;
+    use crate::{ExecCtx, ExecError, Options, TxTemplate};
+
+    fn new_debugger(script: Builder, debug_markers: bool) -> Debugger {
+        let exec = Exec::new(
+            ExecCtx::Tapscript,
+            Options {
+                debug_markers,
+                ..Default::default()
+            },
+            TxTemplate {
+                tx: Transaction {
+                    version: transaction::Version::TWO,
+                    lock_time: LockTime::ZERO,
+                    input: vec![],
+                    output: vec![],
+                },
+                prevouts: vec![],
+                input_idx: 0,
+                taproot_annex_scriptleaf: Some((TapLeafHash::all_zeros(), None)),
+            },
+            script.into_script(),
+            vec![],
+        )
+        .expect("error when creating the execution body");
+
+        Debugger::new(exec)
+    }
+
+    #[test]
+    fn test_parse_breakpoint() {
+        assert_eq!("offset 12".parse(), Ok(Breakpoint::Offset(12)));
+        assert_eq!("op OP_ADD".parse(), Ok(Breakpoint::Opcode(OP_ADD)));
+        assert_eq!("op ADD".parse(), Ok(Breakpoint::Opcode(OP_ADD)));
+        assert_eq!("op DEBUG".parse(), Ok(Breakpoint::Opcode(OP_RESERVED)));
+        assert_eq!(
+            "depth >= 100".parse(),
+            Ok(Breakpoint::Depth(DepthCmp::GreaterOrEqual, 100))
+        );
+        assert!("op OP_UNKNOWN".parse::<Breakpoint>().is_err());
+        assert!("depth 100".parse::<Breakpoint>().is_err());
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let script = Builder::new()
+            .push_int(1)
+            .push_int(2)
+            .push_opcode(OP_ADD)
+            .push_int(3)
+            .push_int(4)
+            .push_opcode(OP_2DROP)
+            .push_int(3)
+            .push_opcode(OP_EQUAL);
+        let mut debugger = new_debugger(script, false);
+        debugger.breakpoints = vec![
+            Breakpoint::Opcode(OP_ADD),
+            Breakpoint::Depth(DepthCmp::Greater, 2),
+            Breakpoint::Offset(6),
+        ];
+
+        assert_eq!(debugger.resume(), Stop::Breakpoint(0));
+        assert_eq!(debugger.exec().stack().len(), 2);
+        assert_eq!(debugger.resume(), Stop::Breakpoint(1));
+        assert_eq!(debugger.exec().stack().len(), 3);
+        assert_eq!(debugger.resume(), Stop::Breakpoint(2));
+        assert_eq!(
+            debugger.next_instruction(),
+            Some(Instruction::Op(OP_PUSHNUM_3))
+        );
+
+        match debugger.resume() {
+            Stop::Finished(result) => assert!(result.success),
+            stop => panic!("unexpected stop: {:?}", stop),
+        }
+    }
+
+    #[test]
+    fn test_step() {
+        let script = Builder::new().push_int(1).push_int(2).push_opcode(OP_ADD);
+        let mut debugger = new_debugger(script, false);
+
+        assert_eq!(debugger.step(2), Stop::Step);
+        assert_eq!(debugger.exec().stack().len(), 2);
+        assert!(matches!(debugger.step(2), Stop::Finished(_)));
+    }
+
+    #[test]
+    fn test_markers() {
+        let script = Builder::new()
+            .push_int(1)
+            .push_opcode(OP_RESERVED)
+            .push_int(1)
+            .push_opcode(OP_VERIFY);
+
+        let mut debugger = new_debugger(script.clone(), true);
+        assert_eq!(debugger.resume(), Stop::Marker);
+        match debugger.resume() {
+            Stop::Finished(result) => assert!(result.success),
+            stop => panic!("unexpected stop: {:?}", stop),
+        }
+
+        let mut debugger = new_debugger(script, false);
+        assert_eq!(debugger.resume(), Stop::Marker);
+        assert_eq!(debugger.run().error, Some(ExecError::Debug));
+    }
+
+    #[test]
+    fn test_stops_before_first_instruction() {
+        let script = Builder::new()
+            .push_opcode(OP_RESERVED)
+            .push_int(1)
+            .push_int(1)
+            .push_opcode(OP_EQUAL);
+
+        for breakpoint in [Breakpoint::Offset(0), Breakpoint::Opcode(OP_RESERVED)] {
+            let mut debugger = new_debugger(script.clone(), true);
+            debugger.breakpoints = vec![breakpoint];
+            assert_eq!(debugger.resume(), Stop::Breakpoint(0));
+            assert_eq!(debugger.exec().script_position(), 0);
+            match debugger.resume() {
+                Stop::Finished(result) => assert!(result.success),
+                stop => panic!("unexpected stop: {:?}", stop),
+            }
+        }
+
+        let mut debugger = new_debugger(script.clone(), true);
+        assert_eq!(debugger.resume(), Stop::Marker);
+        assert_eq!(debugger.exec().script_position(), 0);
+        assert!(matches!(debugger.resume(), Stop::Finished(_)));
+
+        // Stepping first does not stop before the first instruction again
+        let mut debugger = new_debugger(script, true);
+        debugger.breakpoints = vec![Breakpoint::Offset(0)];
+        assert_eq!(debugger.step(1), Stop::Step);
+        assert!(matches!(debugger.resume(), Stop::Finished(_)));
+    }
+}
@@ -21,6 +21,7 @@ pub enum ExecError {
     EqualVerify,
     NumEqualVerify,
     CheckSigVerify,
+    CheckMultiSigVerify,
     TapscriptValidationWeight,
     PubkeyType,
     SchnorrSigSize,
@@ -28,6 +29,8 @@ pub enum ExecError {
     SchnorrSig,
     TapscriptCheckMultiSig,
     PubkeyCount,
+    SigCount,
+    SigNullDummy,
     StackSize,
     WitnessPubkeyType,
 
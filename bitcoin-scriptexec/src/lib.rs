@@ -1,13 +1,13 @@
 extern crate alloc;
 extern crate core;
 
-use alloc::borrow::Cow;
+use alloc::rc::Rc;
 use core::cmp;
 
 use bitcoin::consensus::Encodable;
 use bitcoin::hashes::{hash160, ripemd160, sha1, sha256, sha256d, Hash};
 use bitcoin::opcodes::{all::*, Opcode};
-use bitcoin::script::{self, Instruction, Instructions, Script, ScriptBuf};
+use bitcoin::script::{self, Instruction, Script, ScriptBuf};
 use bitcoin::sighash::SighashCache;
 use bitcoin::taproot::{self, TapLeafHash};
 use bitcoin::transaction::{self, Transaction, TxOut};
@@ -31,6 +31,9 @@ mod wasm;
 mod data_structures;
 pub use data_structures::Stack;
 
+pub mod debugger;
+pub mod profile;
+
 /// Maximum number of non-push operations per script
 const MAX_OPS_PER_SCRIPT: usize = 201;
 
@@ -53,8 +56,8 @@ const VALIDATION_WEIGHT_OFFSET: i64 = 50;
 /// Validation weight per passing signature (Tapscript only, see BIP 342).
 const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
 
-// Maximum number of public keys per multisig
-const _MAX_PUBKEYS_PER_MULTISIG: i64 = 20;
+/// Maximum number of public keys per multisig
+const MAX_PUBKEYS_PER_MULTISIG: i64 = 20;
 
 /// Used to enable experimental script features.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -76,6 +79,11 @@ pub struct Options {
     pub verify_minimal_if: bool,
     /// Enfore a strict limit of 1000 total stack items.
     pub enforce_stack_limit: bool,
+    /// Require the extra element consumed by OP_CHECKMULTISIG to be empty (BIP 147).
+    pub verify_nulldummy: bool,
+    /// Treat OP_RESERVED (`DEBUG` in the `script!` macro) as a no-op marker
+    /// for debuggers instead of failing with [`ExecError::Debug`].
+    pub debug_markers: bool,
 
     pub experimental: Experimental,
 }
@@ -88,6 +96,8 @@ impl Default for Options {
             verify_csv: true,
             verify_minimal_if: true,
             enforce_stack_limit: true,
+            verify_nulldummy: true,
+            debug_markers: false,
             experimental: Experimental { op_cat: true },
         }
     }
@@ -100,6 +110,7 @@ pub enum ExecCtx {
     Tapscript,
 }
 
+#[derive(Clone)]
 pub struct TxTemplate {
     pub tx: Transaction,
     pub prevouts: Vec<TxOut>,
@@ -166,16 +177,17 @@ pub struct Exec {
     result: Option<ExecutionResult>,
 
     sighashcache: SighashCache<Transaction>,
-    script: &'static Script,
-    instructions: Instructions<'static>,
+    script: Rc<ScriptBuf>,
+    // Position of the next instruction.
+    pc: usize,
     current_position: usize,
     cond_stack: ConditionStack,
     stack: Stack,
     altstack: Stack,
     last_codeseparator_pos: Option<u32>,
-    // Initially set to the whole script, but updated when
-    // OP_CODESEPARATOR is encountered.
-    script_code: &'static Script,
+    // Position the script code starts at. Initially the whole script,
+    // but updated when OP_CODESEPARATOR is encountered.
+    script_code_pos: usize,
 
     opcode_count: usize,
     validation_weight: i64,
@@ -184,12 +196,68 @@ pub struct Exec {
     stats: ExecStats,
 }
 
-impl std::ops::Drop for Exec {
-    fn drop(&mut self) {
-        // we need to safely drop the script we allocated
-        unsafe {
-            let script = core::mem::replace(&mut self.script, Script::from_bytes(&[]));
-            let _ = Box::from_raw(script as *const Script as *mut Script);
+/// State of an [`Exec`] between two instructions, to restore the execution
+/// at that point with [`Exec::restore`].
+///
+/// The stack elements are shared with the execution, so taking a snapshot
+/// does not copy them.
+#[derive(Debug, Clone)]
+pub struct ExecSnapshot {
+    pc: usize,
+    current_position: usize,
+    cond_stack: ConditionStack,
+    stack: Stack,
+    altstack: Stack,
+    last_codeseparator_pos: Option<u32>,
+    script_code_pos: usize,
+    opcode_count: usize,
+    validation_weight: i64,
+    stats: ExecStats,
+    result: Option<ExecutionResult>,
+}
+
+impl ExecSnapshot {
+    /// The position of the next instruction in the script.
+    pub fn script_position(&self) -> usize {
+        self.pc
+    }
+
+    pub fn stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    pub fn altstack(&self) -> &Stack {
+        &self.altstack
+    }
+
+    pub fn result(&self) -> Option<&ExecutionResult> {
+        self.result.as_ref()
+    }
+}
+
+impl Clone for Exec {
+    fn clone(&self) -> Self {
+        Exec {
+            ctx: self.ctx,
+            opt: self.opt.clone(),
+            tx: self.tx.clone(),
+            result: self.result.clone(),
+
+            // The cache is only an optimization, so it is not worth copying.
+            sighashcache: SighashCache::new(self.tx.tx.clone()),
+            script: self.script.clone(),
+            pc: self.pc,
+            current_position: self.current_position,
+            cond_stack: self.cond_stack.clone(),
+            stack: self.stack.clone(),
+            altstack: self.altstack.clone(),
+            last_codeseparator_pos: self.last_codeseparator_pos,
+            script_code_pos: self.script_code_pos,
+
+            opcode_count: self.opcode_count,
+            validation_weight: self.validation_weight,
+
+            stats: self.stats.clone(),
         }
     }
 }
@@ -225,20 +293,6 @@ impl Exec {
             return Err(Error::InvalidScript(err));
         }
 
-        // *****
-        // Make sure there is no more possible exit path after this point!
-        // Otherwise we are leaking memory.
-        // *****
-
-        // We box alocate the script to get a static Instructions iterator.
-        // We will manually drop this allocation in the ops::Drop impl.
-        let script = Box::leak(script.into_boxed_script()) as &'static Script;
-        let instructions = if opt.require_minimal {
-            script.instructions_minimal()
-        } else {
-            script.instructions()
-        };
-
         //TODO(stevenroose) make this more efficient
         let witness_size =
             Encodable::consensus_encode(&script_witness, &mut bitcoin::io::sink()).unwrap();
@@ -249,8 +303,8 @@ impl Exec {
             result: None,
 
             sighashcache: SighashCache::new(tx.tx.clone()),
-            script,
-            instructions,
+            script: Rc::new(script),
+            pc: 0,
             current_position: 0,
             cond_stack: ConditionStack::new(),
             //TODO(stevenroose) does this need to be reversed?
@@ -259,7 +313,7 @@ impl Exec {
             opcode_count: 0,
             validation_weight: start_validation_weight,
             last_codeseparator_pos: None,
-            script_code: script,
+            script_code_pos: 0,
 
             opt,
             tx,
@@ -298,13 +352,16 @@ impl Exec {
         self.result.as_ref()
     }
 
+    pub fn script(&self) -> &Script {
+        &self.script
+    }
+
     pub fn script_position(&self) -> usize {
-        self.script.len() - self.instructions.as_script().len()
+        self.pc
     }
 
     pub fn remaining_script(&self) -> &Script {
-        let pos = self.script_position();
-        &self.script[pos..]
+        &self.script[self.pc..]
     }
 
     pub fn stack(&self) -> &Stack {
@@ -319,6 +376,54 @@ impl Exec {
         &self.stats
     }
 
+    fn script_code(&self) -> &Script {
+        &self.script[self.script_code_pos..]
+    }
+
+    ///////////////
+    // SNAPSHOTS //
+    ///////////////
+
+    /// Takes a snapshot of the current state of the execution.
+    pub fn snapshot(&self) -> ExecSnapshot {
+        ExecSnapshot {
+            pc: self.pc,
+            current_position: self.current_position,
+            cond_stack: self.cond_stack.clone(),
+            stack: self.stack.clone(),
+            altstack: self.altstack.clone(),
+            last_codeseparator_pos: self.last_codeseparator_pos,
+            script_code_pos: self.script_code_pos,
+            opcode_count: self.opcode_count,
+            validation_weight: self.validation_weight,
+            stats: self.stats.clone(),
+            result: self.result.clone(),
+        }
+    }
+
+    /// Rewinds (or fast-forwards) the execution to the `snapshot`.
+    ///
+    /// The snapshot must be taken from an execution of the same script with
+    /// the same context and options, e.g. from this one or its clone.
+    pub fn restore(&mut self, snapshot: &ExecSnapshot) {
+        assert!(
+            snapshot.pc <= self.script.len(),
+            "snapshot of another script"
+        );
+
+        self.pc = snapshot.pc;
+        self.current_position = snapshot.current_position;
+        self.cond_stack = snapshot.cond_stack.clone();
+        self.stack = snapshot.stack.clone();
+        self.altstack = snapshot.altstack.clone();
+        self.last_codeseparator_pos = snapshot.last_codeseparator_pos;
+        self.script_code_pos = snapshot.script_code_pos;
+        self.opcode_count = snapshot.opcode_count;
+        self.validation_weight = snapshot.validation_weight;
+        self.stats = snapshot.stats.clone();
+        self.result = snapshot.result.clone();
+    }
+
     ///////////////
     // UTILITIES //
     ///////////////
@@ -402,17 +507,10 @@ impl Exec {
         //TODO(stevenroose) somehow sigops limit should be checked somewhere
 
         // Drop the signature in pre-segwit scripts but not segwit scripts
-        let mut scriptcode = Cow::Borrowed(self.script_code.as_bytes());
-        if self.ctx == ExecCtx::Legacy {
-            let mut i = 0;
-            while i < scriptcode.len() - sig.len() {
-                if &scriptcode[i..i + sig.len()] == sig {
-                    scriptcode.to_mut().drain(i..i + sig.len());
-                } else {
-                    i += 1;
-                }
-            }
-        }
+        let scriptcode = match self.ctx {
+            ExecCtx::Legacy => find_and_delete(self.script_code(), sig),
+            _ => self.script_code().to_owned(),
+        };
 
         //TODO(stevenroose) the signature and pk encoding checks we use here
         // might not be exactly identical to Core's
@@ -421,7 +519,7 @@ impl Exec {
             return Err(ExecError::WitnessPubkeyType);
         }
 
-        Ok(self.check_sig_ecdsa(sig, pk, &scriptcode))
+        Ok(self.check_sig_ecdsa(sig, pk, scriptcode.as_bytes()))
     }
 
     fn check_sig_tap(&mut self, sig: &[u8], pk: &[u8]) -> Result<bool, ExecError> {
@@ -463,9 +561,20 @@ impl Exec {
             return Err(res);
         }
 
-        self.current_position = self.script.len() - self.instructions.as_script().len();
-        let instruction = match self.instructions.next() {
-            Some(Ok(i)) => i,
+        // The instruction borrows the script, which outlives the mutable
+        // borrows of the execution below thanks to this extra reference.
+        let script = Rc::clone(&self.script);
+        let mut instructions = if self.opt.require_minimal {
+            script[self.pc..].instructions_minimal()
+        } else {
+            script[self.pc..].instructions()
+        };
+        self.current_position = self.pc;
+        let instruction = match instructions.next() {
+            Some(Ok(i)) => {
+                self.pc = script.len() - instructions.as_script().len();
+                i
+            }
             None => {
                 let res = ExecutionResult::from_final_stack(self.ctx, self.stack.clone());
                 self.result = Some(res);
@@ -505,7 +614,7 @@ impl Exec {
                     | OP_2MUL | OP_2DIV | OP_MUL | OP_DIV | OP_MOD | OP_LSHIFT | OP_RSHIFT => {
                         return self.failop(ExecError::DisabledOpcode, op);
                     }
-                    OP_RESERVED => {
+                    OP_RESERVED if !self.opt.debug_markers => {
                         return self.failop(ExecError::Debug, op);
                     }
 
@@ -543,6 +652,9 @@ impl Exec {
             // Control
             OP_NOP => {}
 
+            // Only reached when used as a debug marker
+            OP_RESERVED => {}
+
             OP_CLTV if self.opt.verify_cltv => {
                 let top = self.stack.topstr(-1)?;
 
@@ -773,7 +885,8 @@ impl Exec {
                 // (xn ... x2 x1 x0 n - xn ... x2 x1 x0 xn)
                 // (xn ... x2 x1 x0 n - ... x2 x1 x0 xn)
                 let x = self.stack.topnum(-1, self.opt.require_minimal)?;
-                if x < 0 || x >= self.stack.len() as i64 {
+                // The stack still contains n itself.
+                if x < 0 || x >= self.stack.len() as i64 - 1 {
                     return Err(ExecError::InvalidStackOperation);
                 }
                 self.stack.pop().unwrap();
@@ -960,7 +1073,7 @@ impl Exec {
             OP_CODESEPARATOR => {
                 // Store this CODESEPARATOR position and update the scriptcode.
                 self.last_codeseparator_pos = Some(self.current_position as u32);
-                self.script_code = &self.script[self.current_position..];
+                self.script_code_pos = self.current_position;
             }
 
             OP_CHECKSIG | OP_CHECKSIGVERIFY => {
@@ -993,7 +1106,82 @@ impl Exec {
             }
 
             OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
-                unimplemented!();
+                if self.ctx == ExecCtx::Tapscript {
+                    return Err(ExecError::TapscriptCheckMultiSig);
+                }
+
+                // The stack is { dummy sig_m .. sig_1 m pk_n .. pk_1 n }, where
+                // i is the position of the current item from the top.
+                let mut i = 1;
+                let nb_keys = self.stack.topnum(-i, self.opt.require_minimal)?;
+                if !(0..=MAX_PUBKEYS_PER_MULTISIG).contains(&nb_keys) {
+                    return Err(ExecError::PubkeyCount);
+                }
+                self.opcode_count += nb_keys as usize;
+                if self.opcode_count > MAX_OPS_PER_SCRIPT {
+                    return Err(ExecError::OpCount);
+                }
+                i += 1;
+                let ikey = i;
+                i += nb_keys as isize;
+
+                let nb_sigs = self.stack.topnum(-i, self.opt.require_minimal)?;
+                if nb_sigs < 0 || nb_sigs > nb_keys {
+                    return Err(ExecError::SigCount);
+                }
+                i += 1;
+                let isig = i;
+                i += nb_sigs as isize;
+                // Including the dummy element
+                self.stack.needn(i as usize)?;
+
+                let pks = (0..nb_keys as isize)
+                    .map(|k| self.stack.topstr(-ikey - k))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let sigs = (0..nb_sigs as isize)
+                    .map(|k| self.stack.topstr(-isig - k))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // Drop the signatures in pre-segwit scripts but not segwit scripts
+                let mut script_code = self.script_code().to_owned();
+                if self.ctx == ExecCtx::Legacy {
+                    for sig in sigs.iter() {
+                        script_code = find_and_delete(&script_code, sig);
+                    }
+                }
+
+                // Each signature is matched against the remaining keys in order,
+                // failing as soon as there are more signatures left than keys.
+                let mut success = true;
+                let (mut sig_idx, mut key_idx) = (0, 0);
+                while success && sig_idx < sigs.len() {
+                    let pk = &pks[key_idx];
+                    if self.ctx == ExecCtx::SegwitV0 && pk.len() == 65 {
+                        return Err(ExecError::WitnessPubkeyType);
+                    }
+                    if self.check_sig_ecdsa(&sigs[sig_idx], pk, script_code.as_bytes()) {
+                        sig_idx += 1;
+                    }
+                    key_idx += 1;
+
+                    if sigs.len() - sig_idx > pks.len() - key_idx {
+                        success = false;
+                    }
+                }
+
+                // A bug makes CHECKMULTISIG consume one extra element
+                if self.opt.verify_nulldummy && !self.stack.topstr(-i)?.is_empty() {
+                    return Err(ExecError::SigNullDummy);
+                }
+                self.stack.popn(i as usize).unwrap();
+
+                if op == OP_CHECKMULTISIGVERIFY {
+                    if !success {
+                        return Err(ExecError::CheckMultiSigVerify);
+                    }
+                } else {
+                    self.stack.pushnum(if success { 1 } else { 0 });
+                }
             }
 
             // remainder
@@ -1020,6 +1208,39 @@ impl Exec {
     }
 }
 
+/// Removes each occurrence of the push of `data` from the `script`, as
+/// Bitcoin Core's `FindAndDelete` does. Matches are only looked for at the
+/// instruction boundaries.
+fn find_and_delete(script: &Script, data: &[u8]) -> ScriptBuf {
+    let Ok(data) = <&script::PushBytes>::try_from(data) else {
+        return script.to_owned();
+    };
+    let pattern = ScriptBuf::builder().push_slice(data).into_script();
+    let pattern = pattern.as_bytes();
+
+    let bytes = script.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut pc = 0;
+    while pc < bytes.len() {
+        while bytes[pc..].starts_with(pattern) {
+            pc += pattern.len();
+        }
+        if pc == bytes.len() {
+            break;
+        }
+
+        let mut instructions = Script::from_bytes(&bytes[pc..]).instructions();
+        let len = match instructions.next() {
+            Some(Ok(_)) => bytes.len() - pc - instructions.as_script().len(),
+            _ => bytes.len() - pc,
+        };
+        result.extend_from_slice(&bytes[pc..pc + len]);
+        pc += len;
+    }
+
+    ScriptBuf::from_bytes(result)
+}
+
 fn read_scriptint(item: &[u8], size: usize, minimal: bool) -> Result<i64, ExecError> {
     script::read_scriptint_size(item, size, minimal).map_err(|e| match e {
         script::ScriptIntError::NonMinimalPush => ExecError::MinimalData,
@@ -1027,3 +1248,299 @@ fn read_scriptint(item: &[u8], size: usize, minimal: bool) -> Result<i64, ExecEr
         script::ScriptIntError::NumericOverflow => ExecError::ScriptIntNumericOverflow,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::secp256k1::{self, Secp256k1, SecretKey};
+    use bitcoin::{absolute::LockTime, TxIn};
+
+    use super::*;
+
+    fn tx_template() -> TxTemplate {
+        TxTemplate {
+            tx: Transaction {
+                version: transaction::Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![TxIn::default()],
+                output: vec![],
+            },
+            prevouts: vec![],
+            input_idx: 0,
+            taproot_annex_scriptleaf: Some((TapLeafHash::all_zeros(), None)),
+        }
+    }
+
+    fn execute(
+        ctx: ExecCtx,
+        opt: Options,
+        script: ScriptBuf,
+        witness: Vec<Vec<u8>>,
+    ) -> ExecutionResult {
+        let mut exec = Exec::new(ctx, opt, tx_template(), script, witness)
+            .expect("error when creating the execution body");
+
+        loop {
+            if let Err(res) = exec.exec_next() {
+                return res.clone();
+            }
+        }
+    }
+
+    fn execute_legacy(script: ScriptBuf) -> ExecutionResult {
+        execute(ExecCtx::Legacy, Options::default(), script, vec![])
+    }
+
+    fn multisig_script(nb_sigs: i64, pks: &[&[u8]], op: Opcode) -> script::Builder {
+        let mut builder = script::Builder::new().push_int(nb_sigs);
+        for pk in pks {
+            builder = builder.push_slice(<&script::PushBytes>::try_from(*pk).unwrap());
+        }
+        builder.push_int(pks.len() as i64).push_opcode(op)
+    }
+
+    // The following cases follow the CHECKMULTISIG vectors of Bitcoin
+    // Core's script_tests.json
+
+    #[test]
+    fn test_checkmultisig_without_keys_and_sigs() {
+        let script = script::Builder::new()
+            .push_int(0)
+            .push_int(0)
+            .push_int(0)
+            .push_opcode(OP_CHECKMULTISIG)
+            .push_opcode(OP_VERIFY)
+            .push_opcode(OP_DEPTH)
+            .push_int(0)
+            .push_opcode(OP_EQUAL)
+            .into_script();
+
+        let result = execute_legacy(script);
+        assert!(result.success, "{:?}", result.error);
+    }
+
+    #[test]
+    fn test_checkmultisig_keys_are_not_checked_without_sigs() {
+        let script = script::Builder::new()
+            .push_int(0)
+            .append(multisig_script(0, &[b"a", b"b"], OP_CHECKMULTISIG))
+            .push_opcode(OP_VERIFY)
+            .push_opcode(OP_DEPTH)
+            .push_int(0)
+            .push_opcode(OP_EQUAL)
+            .into_script();
+
+        let result = execute_legacy(script);
+        assert!(result.success, "{:?}", result.error);
+    }
+
+    #[test]
+    fn test_checkmultisig_counts() {
+        let keys = [b"k".as_slice(); 21];
+
+        // More than 20 keys
+        let script = script::Builder::new()
+            .push_int(0)
+            .append(multisig_script(0, &keys, OP_CHECKMULTISIG))
+            .into_script();
+        assert_eq!(execute_legacy(script).error, Some(ExecError::PubkeyCount));
+
+        // Negative number of keys
+        let script = script::Builder::new()
+            .push_int(0)
+            .push_int(0)
+            .push_int(-1)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script();
+        assert_eq!(execute_legacy(script).error, Some(ExecError::PubkeyCount));
+
+        // More signatures than keys
+        let script = script::Builder::new()
+            .push_int(0)
+            .push_slice([1; 71])
+            .push_slice([1; 71])
+            .append(multisig_script(2, &keys[..1], OP_CHECKMULTISIG))
+            .into_script();
+        assert_eq!(execute_legacy(script).error, Some(ExecError::SigCount));
+
+        // Missing dummy element
+        let script = multisig_script(0, &keys[..2], OP_CHECKMULTISIG).into_script();
+        assert_eq!(
+            execute_legacy(script).error,
+            Some(ExecError::InvalidStackOperation)
+        );
+    }
+
+    #[test]
+    fn test_checkmultisig_keys_count_towards_op_limit() {
+        let keys = [b"k".as_slice(); 20];
+        let script = |nb_nops: usize| {
+            let mut builder = script::Builder::new();
+            for _ in 0..nb_nops {
+                builder = builder.push_opcode(OP_NOP);
+            }
+            builder
+                .push_int(0)
+                .append(multisig_script(0, &keys, OP_CHECKMULTISIG))
+                .into_script()
+        };
+
+        // 180 + 1 + 20 operations are within the limit
+        assert!(execute_legacy(script(180)).success);
+        assert_eq!(execute_legacy(script(181)).error, Some(ExecError::OpCount));
+    }
+
+    #[test]
+    fn test_checkmultisig_nulldummy() {
+        let script = script::Builder::new()
+            .push_int(1)
+            .append(multisig_script(0, &[], OP_CHECKMULTISIG))
+            .into_script();
+
+        assert_eq!(
+            execute_legacy(script.clone()).error,
+            Some(ExecError::SigNullDummy)
+        );
+
+        let opt = Options {
+            verify_nulldummy: false,
+            ..Default::default()
+        };
+        assert!(execute(ExecCtx::Legacy, opt, script, vec![]).success);
+    }
+
+    #[test]
+    fn test_checkmultisig_in_tapscript() {
+        let script = script::Builder::new()
+            .push_int(0)
+            .append(multisig_script(0, &[], OP_CHECKMULTISIG))
+            .into_script();
+
+        let result = execute(ExecCtx::Tapscript, Options::default(), script, vec![]);
+        assert_eq!(result.error, Some(ExecError::TapscriptCheckMultiSig));
+    }
+
+    #[test]
+    fn test_checkmultisig_with_signature() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let pk = secret_key.public_key(&secp).serialize();
+        let other_pk = SecretKey::from_slice(&[2; 32])
+            .unwrap()
+            .public_key(&secp)
+            .serialize();
+
+        let sign = |script: &Script| {
+            let sighash = SighashCache::new(tx_template().tx)
+                .legacy_signature_hash(0, script, 1)
+                .unwrap();
+            let mut sig = secp
+                .sign_ecdsa(&secp256k1::Message::from(sighash), &secret_key)
+                .serialize_der()
+                .to_vec();
+            sig.push(1);
+            sig
+        };
+
+        // 1-of-2 with the signing key in either position
+        for pks in [[&other_pk, &pk], [&pk, &other_pk]] {
+            let script =
+                multisig_script(1, &[pks[0].as_slice(), pks[1].as_slice()], OP_CHECKMULTISIG)
+                    .into_script();
+            let witness = vec![vec![], sign(&script)];
+
+            let result = execute(ExecCtx::Legacy, Options::default(), script, witness);
+            assert!(result.success, "{:?}", result.error);
+        }
+
+        // 2-of-2 with the same signature twice fails
+        let script = multisig_script(
+            2,
+            &[other_pk.as_slice(), pk.as_slice()],
+            OP_CHECKMULTISIGVERIFY,
+        )
+        .into_script();
+        let sig = sign(&script);
+        let result = execute(
+            ExecCtx::Legacy,
+            Options::default(),
+            script,
+            vec![vec![], sig.clone(), sig],
+        );
+        assert_eq!(result.error, Some(ExecError::CheckMultiSigVerify));
+    }
+
+    #[test]
+    fn test_find_and_delete() {
+        let sig = [7; 10];
+        let script = script::Builder::new()
+            .push_slice(sig)
+            .push_opcode(OP_DROP)
+            .push_slice(sig)
+            .push_slice(sig)
+            .push_slice([7; 11])
+            .into_script();
+
+        let expected = script::Builder::new()
+            .push_opcode(OP_DROP)
+            .push_slice([7; 11])
+            .into_script();
+        assert_eq!(find_and_delete(&script, &sig), expected);
+    }
+
+    fn run_to_end(exec: &mut Exec) -> ExecutionResult {
+        loop {
+            if let Err(res) = exec.exec_next() {
+                return res.clone();
+            }
+        }
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let script = script::Builder::new()
+            .push_int(1)
+            .push_opcode(OP_IF)
+            .push_int(2)
+            .push_int(3)
+            .push_opcode(OP_ADD)
+            .push_opcode(OP_TOALTSTACK)
+            .push_opcode(OP_ELSE)
+            .push_opcode(OP_RETURN)
+            .push_opcode(OP_ENDIF)
+            .push_opcode(OP_FROMALTSTACK)
+            .push_int(5)
+            .push_opcode(OP_EQUAL)
+            .into_script();
+        let mut exec = Exec::new(
+            ExecCtx::Tapscript,
+            Options::default(),
+            tx_template(),
+            script,
+            vec![],
+        )
+        .expect("error when creating the execution body");
+
+        // Inside the conditional, with 2 on the stack
+        for _ in 0..3 {
+            exec.exec_next().unwrap();
+        }
+        let snapshot = exec.snapshot();
+        assert_eq!(snapshot.script_position(), 3);
+        assert_eq!(snapshot.stack().len(), 1);
+
+        let mut clone = exec.clone();
+        let result = run_to_end(&mut exec);
+        assert!(result.success);
+        let stats = exec.stats().clone();
+
+        exec.restore(&snapshot);
+        assert_eq!(exec.script_position(), 3);
+        assert_eq!(exec.stack(), snapshot.stack());
+        assert!(exec.result().is_none());
+        assert_eq!(run_to_end(&mut exec), result);
+        assert_eq!(exec.stats(), &stats);
+
+        assert_eq!(run_to_end(&mut clone), result);
+        assert_eq!(clone.stats(), &stats);
+    }
+}
@@ -4,10 +4,13 @@ use std::path::PathBuf;
 
 use bitcoin::hashes::Hash;
 use bitcoin::hex::DisplayHex;
+use bitcoin::script::Instruction;
 use bitcoin::taproot::TapLeafHash;
 use bitcoin::{ScriptBuf, Transaction};
 use clap::Parser;
 
+use bitcoin_scriptexec::debugger::{Debugger, Stop};
+use bitcoin_scriptexec::profile::{ExecProfile, Region};
 use bitcoin_scriptexec::*;
 
 #[derive(Parser)]
@@ -22,6 +25,20 @@ struct Args {
     /// Whether to output result in JSON.
     #[arg(long)]
     json: bool,
+    /// Whether to step through the script interactively.
+    #[arg(long, short)]
+    interactive: bool,
+    /// Profile the execution and write the profile in JSON to the file.
+    #[arg(long, value_name = "PATH")]
+    profile: Option<PathBuf>,
+    /// Profile the execution and write the profile in the folded-stack
+    /// format (for flamegraphs) to the file.
+    #[arg(long, value_name = "PATH")]
+    folded: Option<PathBuf>,
+    /// Labelled region of the script to attribute the profiled costs to,
+    /// as <label>=<start>..<end> with the offsets in bytes. Can be repeated.
+    #[arg(long = "region", value_name = "REGION")]
+    regions: Vec<Region>,
 }
 
 /// A wrapper for the stack types to print them better.
@@ -39,6 +56,175 @@ impl<'a> fmt::Display for FmtStack<'a> {
     }
 }
 
+/// A wrapper for instructions to print them as in the ASM.
+struct FmtInstruction<'a>(Instruction<'a>);
+impl<'a> fmt::Display for FmtInstruction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Instruction::Op(op) => write!(f, "{}", op),
+            Instruction::PushBytes(data) => write!(f, "<{}>", data.as_bytes().as_hex()),
+        }
+    }
+}
+
+const REPL_HELP: &str = "\
+Commands:
+  step [n], s [n]         execute the next n instructions (1 by default)
+  continue, c             execute until a breakpoint or a DEBUG marker
+  run, r                  execute until the end or a failure, ignoring breakpoints
+  break <bp>, b <bp>      add a breakpoint: offset <n> | op <opcode> | depth <cmp> <n>
+  delete <id>, d <id>     remove the breakpoint
+  breakpoints, bl         list the breakpoints
+  print <what>, p <what>  print the stack, altstack, script or stats
+  help, h                 print this help
+  quit, q                 exit the debugger";
+
+/// Number of the next instructions printed by `print script`
+const REPL_SCRIPT_LEN: usize = 10;
+
+fn print_location(debugger: &Debugger) {
+    match debugger.next_instruction() {
+        Some(instruction) => println!(
+            "At offset {}: {}",
+            debugger.exec().script_position(),
+            FmtInstruction(instruction)
+        ),
+        None => println!("At the end of the script"),
+    }
+}
+
+fn print_result(res: &ExecutionResult) {
+    println!("Execution ended. Success: {}", res.success);
+    println!("Final stack: {}", FmtStack(&res.final_stack));
+    if !res.success {
+        println!("Failed on opcode: {:?}", res.opcode);
+        println!("Error: {:?}", res.error);
+    }
+}
+
+/// Writes the profile to the files requested by `--profile` and `--folded`.
+fn write_profile(
+    profile: &ExecProfile,
+    json_path: Option<PathBuf>,
+    folded_path: Option<PathBuf>,
+) -> Result<(), String> {
+    if let Some(path) = json_path {
+        std::fs::write(&path, profile.to_json()).map_err(|e| e.to_string())?;
+        println!("Profile written to {}", path.display());
+    }
+    if let Some(path) = folded_path {
+        std::fs::write(&path, profile.folded()).map_err(|e| e.to_string())?;
+        println!("Folded stacks written to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Interactive step debugger, see [`REPL_HELP`].
+fn repl(mut debugger: Debugger) -> Result<(), String> {
+    println!("{}", REPL_HELP);
+    print_location(&debugger);
+
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("(btcexec) ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        line.clear();
+        if stdin.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Ok(());
+        }
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let stop = match words.as_slice() {
+            [] => continue,
+            ["step" | "s"] => debugger.step(1),
+            ["step" | "s", steps] => match steps.parse() {
+                Ok(steps) => debugger.step(steps),
+                Err(_) => {
+                    println!("invalid number: {}", steps);
+                    continue;
+                }
+            },
+            ["continue" | "c"] => debugger.resume(),
+            ["run" | "r"] => Stop::Finished(debugger.run()),
+            ["break" | "b", breakpoint @ ..] => {
+                match breakpoint.join(" ").parse() {
+                    Ok(breakpoint) => {
+                        debugger.breakpoints.push(breakpoint);
+                        println!(
+                            "Breakpoint {}: {}",
+                            debugger.breakpoints.len() - 1,
+                            breakpoint
+                        );
+                    }
+                    Err(e) => println!("{}", e),
+                }
+                continue;
+            }
+            ["delete" | "d", id] => {
+                match id
+                    .parse()
+                    .ok()
+                    .filter(|id| *id < debugger.breakpoints.len())
+                {
+                    Some(id) => {
+                        debugger.breakpoints.remove(id);
+                    }
+                    None => println!("no breakpoint {}", id),
+                }
+                continue;
+            }
+            ["breakpoints" | "bl"] => {
+                for (id, breakpoint) in debugger.breakpoints.iter().enumerate() {
+                    println!("{}: {}", id, breakpoint);
+                }
+                continue;
+            }
+            ["print" | "p", what] => {
+                let exec = debugger.exec();
+                match *what {
+                    "stack" => println!("Stack: {}", FmtStack(exec.stack())),
+                    "altstack" => println!("AltStack: {}", FmtStack(exec.altstack())),
+                    "script" => {
+                        for instruction in exec
+                            .remaining_script()
+                            .instructions()
+                            .take(REPL_SCRIPT_LEN)
+                            .flatten()
+                        {
+                            println!("{}", FmtInstruction(instruction));
+                        }
+                    }
+                    "stats" => println!("Stats:\n{:#?}", exec.stats()),
+                    _ => println!("can only print stack, altstack, script or stats"),
+                }
+                continue;
+            }
+            ["help" | "h"] => {
+                println!("{}", REPL_HELP);
+                continue;
+            }
+            ["quit" | "q"] => return Ok(()),
+            _ => {
+                println!("unknown command, see help");
+                continue;
+            }
+        };
+
+        match stop {
+            Stop::Breakpoint(id) => println!("Breakpoint {}: {}", id, debugger.breakpoints[id]),
+            Stop::Marker => println!("DEBUG marker"),
+            Stop::Step => {}
+            Stop::Finished(res) => {
+                print_result(&res);
+                continue;
+            }
+        }
+        print_location(&debugger);
+    }
+}
+
 fn inner_main() -> Result<(), String> {
     let args = Args::parse();
 
@@ -50,7 +236,10 @@ fn inner_main() -> Result<(), String> {
     let start = std::time::Instant::now();
     let mut exec = Exec::new(
         ExecCtx::Tapscript,
-        Options::default(),
+        Options {
+            debug_markers: args.interactive,
+            ..Default::default()
+        },
         TxTemplate {
             tx: Transaction {
                 version: bitcoin::transaction::Version::TWO,
@@ -67,6 +256,17 @@ fn inner_main() -> Result<(), String> {
     )
     .expect("error creating exec");
 
+    if args.interactive {
+        return repl(Debugger::new(exec));
+    }
+
+    if args.profile.is_some() || args.folded.is_some() {
+        let profile = ExecProfile::run(&mut exec, args.regions);
+        print_result(exec.result().unwrap());
+        println!("Time elapsed: {}ms", start.elapsed().as_millis());
+        return write_profile(&profile, args.profile, args.folded);
+    }
+
     const SEP: &str = "--------------------------------------------------";
 
     let mut out = io::stdout();
@@ -110,13 +310,7 @@ fn inner_main() -> Result<(), String> {
         };
         serde_json::to_writer(&out, &ret).expect("I/O error");
     } else {
-        println!("Execution ended. Success: {}", res.success);
-        print!("Final stack: {}", FmtStack(&res.final_stack));
-        println!();
-        if !res.success {
-            println!("Failed on opcode: {:?}", res.opcode);
-            println!("Error: {:?}", res.error);
-        }
+        print_result(&res);
         println!("Stats:\n{:#?}", exec.stats());
         println!("Time elapsed: {}ms", start.elapsed().as_millis());
     }
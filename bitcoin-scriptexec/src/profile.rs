@@ -0,0 +1,330 @@
+//! Per-opcode profile of an execution, used by `btcexec --profile` and
+//! `bitcoin_utils::debug::profile_script`.
+//!
+//! The costs are attributed to the whole script and to each of the labelled
+//! [`Region`]s containing the instruction. Regions may nest, e.g. a region
+//! per call of `Fq::square` inside a region of the whole multiplication.
+//! Besides the JSON (with the `json` feature), the profile is exported in
+//! the folded-stack format of the flamegraph tools, see
+//! [`ExecProfile::folded`].
+
+use alloc::collections::BTreeMap;
+use core::cmp::Reverse;
+use core::fmt;
+use core::ops::Range;
+use core::str::FromStr;
+
+use bitcoin::opcodes::Opcode;
+use bitcoin::script::{Script, ScriptBuf};
+
+use crate::Exec;
+
+/// Labelled byte range of the script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub label: String,
+    /// Offset of the first byte of the region
+    pub start: usize,
+    /// Offset of the byte after the region
+    pub end: usize,
+}
+
+impl Region {
+    pub fn new(label: impl Into<String>, range: Range<usize>) -> Self {
+        Self {
+            label: label.into(),
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    /// Concatenates the labelled scripts, returning the region of each.
+    pub fn concat<'a>(
+        parts: impl IntoIterator<Item = (&'a str, &'a Script)>,
+    ) -> (ScriptBuf, Vec<Region>) {
+        let mut bytes = Vec::new();
+        let mut regions = Vec::new();
+        for (label, script) in parts {
+            let start = bytes.len();
+            bytes.extend_from_slice(script.as_bytes());
+            regions.push(Region::new(label, start..bytes.len()));
+        }
+
+        (ScriptBuf::from_bytes(bytes), regions)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}..{}", self.label, self.start, self.end)
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    /// Parses `<label>=<start>..<end>`, where the offsets are in bytes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid region: {}", s);
+        let (label, range) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+        let start = start.parse().map_err(|_| invalid())?;
+        let end = end.parse().map_err(|_| invalid())?;
+        if label.is_empty() || start > end {
+            return Err(invalid());
+        }
+
+        Ok(Region::new(label, start..end))
+    }
+}
+
+/// Costs of the instructions with the same opcode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpcodeCost {
+    /// Number of times the instructions were reached, including inside the
+    /// branches that are not executed
+    pub steps: usize,
+    /// Size of the instructions in the script, in bytes
+    pub bytes: usize,
+}
+
+/// Costs of the whole script or of a region.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Costs {
+    /// Number of instructions reached
+    pub steps: usize,
+    /// Size in bytes
+    pub bytes: usize,
+    /// The highest number of stack items (both the stack and the altstack)
+    /// after the instructions
+    pub peak_stack_items: usize,
+    /// Costs per opcode, by its name (e.g. `OP_ADD` or `OP_PUSHBYTES_4`)
+    pub opcodes: BTreeMap<String, OpcodeCost>,
+}
+
+impl Costs {
+    fn opcode(&mut self, opcode: Opcode) -> &mut OpcodeCost {
+        self.opcodes.entry(opcode.to_string()).or_default()
+    }
+
+    fn add_bytes(&mut self, opcode: Opcode, bytes: usize) {
+        self.bytes += bytes;
+        self.opcode(opcode).bytes += bytes;
+    }
+
+    fn add_step(&mut self, opcode: Opcode, stack_items: usize) {
+        self.steps += 1;
+        self.peak_stack_items = self.peak_stack_items.max(stack_items);
+        self.opcode(opcode).steps += 1;
+    }
+}
+
+/// Costs of a labelled region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionProfile {
+    pub region: Region,
+    pub costs: Costs,
+}
+
+/// Profile of an execution, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecProfile {
+    /// Costs of the whole script
+    pub total: Costs,
+    /// Costs of each region, outermost first
+    pub regions: Vec<RegionProfile>,
+    /// Steps per stack of the containing region labels and the opcode
+    pub folded: BTreeMap<String, usize>,
+}
+
+impl ExecProfile {
+    /// Executes the rest of the script, profiling the execution.
+    pub fn run(exec: &mut Exec, mut regions: Vec<Region>) -> Self {
+        regions.sort_by_key(|region| (region.start, Reverse(region.end)));
+        let mut profile = ExecProfile {
+            total: Costs::default(),
+            regions: regions
+                .into_iter()
+                .map(|region| RegionProfile {
+                    region,
+                    costs: Costs::default(),
+                })
+                .collect(),
+            folded: BTreeMap::new(),
+        };
+
+        let script = exec.script().to_owned();
+        let bytes = script.as_bytes();
+        let mut instructions = script.instruction_indices();
+        while let Some(Ok((offset, _))) = instructions.next() {
+            let size = bytes.len() - offset - instructions.as_script().len();
+            let opcode = Opcode::from(bytes[offset]);
+            profile.total.add_bytes(opcode, size);
+            for region in profile.regions_at(offset) {
+                region.costs.add_bytes(opcode, size);
+            }
+        }
+
+        loop {
+            let offset = exec.script_position();
+            let finished = exec.exec_next().is_err();
+            // Reaching the end of the script is not an instruction
+            if offset == bytes.len() {
+                break;
+            }
+
+            let opcode = Opcode::from(bytes[offset]);
+            let stack_items = exec.stack().len() + exec.altstack().len();
+            profile.total.add_step(opcode, stack_items);
+            let mut frames = Vec::new();
+            for region in profile.regions_at(offset) {
+                region.costs.add_step(opcode, stack_items);
+                frames.push(region.region.label.replace([';', ' '], "_"));
+            }
+            frames.push(opcode.to_string());
+            *profile.folded.entry(frames.join(";")).or_default() += 1;
+
+            if finished {
+                break;
+            }
+        }
+
+        profile
+    }
+
+    fn regions_at(&mut self, offset: usize) -> impl Iterator<Item = &mut RegionProfile> {
+        self.regions
+            .iter_mut()
+            .filter(move |region| region.region.contains(offset))
+    }
+
+    /// The profile in JSON.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("profile is serializable")
+    }
+
+    /// The profile in the folded-stack format, one `<frames> <steps>` line
+    /// per stack, which the flamegraph tools (e.g. `inferno-flamegraph`)
+    /// take as the input.
+    pub fn folded(&self) -> String {
+        self.folded
+            .iter()
+            .map(|(frames, steps)| format!("{} {}\n", frames, steps))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::hashes::Hash;
+    use bitcoin::opcodes::all::*;
+    use bitcoin::script::Builder;
+    use bitcoin::taproot::TapLeafHash;
+    use bitcoin::{absolute::LockTime, transaction, Transaction};
+
+    use super::*;
+    use crate::{ExecCtx, Options, TxTemplate};
+
+    fn new_exec(script: ScriptBuf, witness: Vec<Vec<u8>>) -> Exec {
+        Exec::new(
+            ExecCtx::Tapscript,
+            Options::default(),
+            TxTemplate {
+                tx: Transaction {
+                    version: transaction::Version::TWO,
+                    lock_time: LockTime::ZERO,
+                    input: vec![],
+                    output: vec![],
+                },
+                prevouts: vec![],
+                input_idx: 0,
+                taproot_annex_scriptleaf: Some((TapLeafHash::all_zeros(), None)),
+            },
+            script,
+            witness,
+        )
+        .expect("error when creating the execution body")
+    }
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!("mul=3..10".parse(), Ok(Region::new("mul", 3..10)));
+        assert_eq!("a=b=0..1".parse(), Ok(Region::new("a=b", 0..1)));
+        assert!("mul=10..3".parse::<Region>().is_err());
+        assert!("=0..1".parse::<Region>().is_err());
+        assert!("mul".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let square = Builder::new()
+            .push_opcode(OP_DUP)
+            .push_opcode(OP_ADD)
+            .into_script();
+        let push = Builder::new().push_slice([1, 2, 3, 4]).into_script();
+        let rest = Builder::new()
+            .push_opcode(OP_DROP)
+            .push_int(1)
+            .push_opcode(OP_IF)
+            .push_int(2)
+            .push_opcode(OP_ELSE)
+            .push_int(3)
+            .push_opcode(OP_ENDIF)
+            .push_opcode(OP_SUB)
+            .into_script();
+        let (script, mut regions) = Region::concat([
+            ("square", square.as_script()),
+            ("square", square.as_script()),
+            ("push", push.as_script()),
+            ("rest", rest.as_script()),
+        ]);
+        // The first two regions are nested in this one
+        regions.push(Region::new("double square", 0..4));
+
+        let mut exec = new_exec(script, vec![vec![3]]);
+        let profile = ExecProfile::run(&mut exec, regions);
+        assert!(exec.result().unwrap().success);
+
+        assert_eq!(profile.total.steps, 13);
+        assert_eq!(profile.total.bytes, 17);
+        assert_eq!(
+            profile.total.opcodes["OP_ADD"],
+            OpcodeCost { steps: 2, bytes: 2 }
+        );
+        assert_eq!(
+            profile.total.opcodes["OP_PUSHBYTES_4"],
+            OpcodeCost { steps: 1, bytes: 5 }
+        );
+        assert_eq!(profile.total.peak_stack_items, 2);
+
+        let labels: Vec<&str> = profile
+            .regions
+            .iter()
+            .map(|region| region.region.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            ["double square", "square", "square", "push", "rest"]
+        );
+        assert_eq!(profile.regions[0].costs.steps, 4);
+        assert_eq!(profile.regions[1].costs.peak_stack_items, 2);
+        assert_eq!(profile.regions[4].costs.steps, 8);
+        assert_eq!(profile.regions[4].costs.bytes, 8);
+
+        assert_eq!(profile.folded["double_square;square;OP_DUP"], 2);
+        assert_eq!(profile.folded["push;OP_PUSHBYTES_4"], 1);
+        // The non-executed branch is reached as well
+        assert_eq!(profile.folded["rest;OP_PUSHNUM_3"], 1);
+        assert!(profile.folded().contains("rest;OP_IF 1\n"));
+    }
+}
@@ -12,6 +12,7 @@
 /// This uses an optimized implementation that does not materialize the
 /// actual stack. Instead, it just stores the size of the would-be stack,
 /// and the position of the first false value in it.
+#[derive(Debug, Clone)]
 pub struct ConditionStack {
     /// The size of the implied stack.
     size: usize,
@@ -0,0 +1,392 @@
+//! Conformance of the interpreter against Bitcoin Core's script test vectors.
+//!
+//! `data/script_tests.json` is a subset of Core's
+//! `src/test/data/script_tests.json` in the same format: the vectors that do
+//! not need signatures, witnesses or P2SH. Each of them is run the way Core's
+//! `VerifyScript` spends a non-witness output: the scriptSig is executed on
+//! the empty stack, and the scriptPubKey on the resulting one, in the input
+//! of the same spending transaction as Core's test builds.
+//!
+//! The harness takes Core's full file as is: the witness vectors, the P2SH
+//! outputs and the vectors with flags the harness does not support are
+//! skipped, and the summary counts them by the reason. Every other vector
+//! either matches Core or is listed in [`KNOWN_DIVERGENCES`].
+//!
+//! Core's `tx_valid.json` and `tx_invalid.json` are not vendored yet.
+
+use std::collections::{BTreeMap, HashMap};
+use std::iter;
+
+use bitcoin::absolute::LockTime;
+use bitcoin::hex::FromHex;
+use bitcoin::opcodes::{all::*, Opcode};
+use bitcoin::script::{self, PushBytes, ScriptBuf};
+use bitcoin::{transaction, Amount, OutPoint, Sequence, Transaction, TxIn, TxOut, Witness};
+use bitcoin_scriptexec::{Error, Exec, ExecCtx, ExecError, ExecutionResult, Options, TxTemplate};
+
+const SCRIPT_TESTS: &str = include_str!("data/script_tests.json");
+
+/// Known divergences from Core, as (scriptSig, scriptPubKey, flags of the
+/// vector, our result, description).
+const KNOWN_DIVERGENCES: &[(&str, &str, &str, &str, &str)] = &[
+    (
+        "0",
+        "IF 0x50 ENDIF 1",
+        "P2SH,STRICTENC",
+        "DEBUG",
+        "OP_RESERVED is a debug breakpoint, which fails even when not executed",
+    ),
+    (
+        "1",
+        "IF 0x50 ENDIF 1",
+        "P2SH,STRICTENC",
+        "DEBUG",
+        "OP_RESERVED is a debug breakpoint",
+    ),
+    (
+        "1",
+        "IF",
+        "P2SH,STRICTENC",
+        "EVAL_FALSE",
+        "unterminated OP_IF at the end of the script is not checked",
+    ),
+    (
+        "1",
+        "IF 1",
+        "P2SH,STRICTENC",
+        "OK",
+        "unterminated OP_IF at the end of the script is not checked",
+    ),
+    (
+        "",
+        "IF 1 ENDIF",
+        "P2SH,STRICTENC",
+        "INVALID_STACK_OPERATION",
+        "OP_IF on the empty stack",
+    ),
+    (
+        "1",
+        "FROMALTSTACK",
+        "P2SH,STRICTENC",
+        "INVALID_STACK_OPERATION",
+        "there is no separate error for the empty altstack",
+    ),
+    (
+        "1",
+        "TOALTSTACK FROMALTSTACK FROMALTSTACK",
+        "P2SH,STRICTENC",
+        "INVALID_STACK_OPERATION",
+        "there is no separate error for the empty altstack",
+    ),
+    (
+        "0x02 0x0000",
+        "1ADD DROP 1",
+        "MINIMALDATA",
+        "MINIMALDATA",
+        "non-minimally encoded script numbers",
+    ),
+    (
+        "0",
+        "'pk' CHECKSIG NOT",
+        "STRICTENC",
+        "OK",
+        "public key encodings (STRICTENC) are not checked",
+    ),
+];
+
+/// Name of the error in Core's `ScriptErrorString` format
+fn core_error(err: &ExecError) -> &'static str {
+    match err {
+        ExecError::DisabledOpcode => "DISABLED_OPCODE",
+        ExecError::OpCodeseparator => "OP_CODESEPARATOR",
+        ExecError::BadOpcode => "BAD_OPCODE",
+        ExecError::OpCount => "OP_COUNT",
+        ExecError::PushSize => "PUSH_SIZE",
+        ExecError::MinimalData => "MINIMALDATA",
+        ExecError::InvalidStackOperation => "INVALID_STACK_OPERATION",
+        ExecError::NegativeLocktime => "NEGATIVE_LOCKTIME",
+        ExecError::UnsatisfiedLocktime => "UNSATISFIED_LOCKTIME",
+        ExecError::UnbalancedConditional => "UNBALANCED_CONDITIONAL",
+        ExecError::TapscriptMinimalIf => "MINIMALIF",
+        ExecError::Verify => "VERIFY",
+        ExecError::OpReturn => "OP_RETURN",
+        ExecError::EqualVerify => "EQUALVERIFY",
+        ExecError::NumEqualVerify => "NUMEQUALVERIFY",
+        ExecError::CheckSigVerify => "CHECKSIGVERIFY",
+        ExecError::CheckMultiSigVerify => "CHECKMULTISIGVERIFY",
+        ExecError::TapscriptValidationWeight => "TAPSCRIPT_VALIDATION_WEIGHT",
+        ExecError::PubkeyType => "PUBKEYTYPE",
+        ExecError::SchnorrSigSize => "SCHNORR_SIG_SIZE",
+        ExecError::SchnorrSigHashtype => "SCHNORR_SIG_HASHTYPE",
+        ExecError::SchnorrSig => "SCHNORR_SIG",
+        ExecError::TapscriptCheckMultiSig => "TAPSCRIPT_CHECKMULTISIG",
+        ExecError::PubkeyCount => "PUBKEY_COUNT",
+        ExecError::SigCount => "SIG_COUNT",
+        ExecError::SigNullDummy => "SIG_NULLDUMMY",
+        ExecError::StackSize => "STACK_SIZE",
+        ExecError::WitnessPubkeyType => "WITNESS_PUBKEYTYPE",
+        // Core throws on the numeric overflow, which results in the unknown error
+        ExecError::ScriptIntNumericOverflow => "UNKNOWN_ERROR",
+        ExecError::Debug => "DEBUG",
+    }
+}
+
+/// Opcode names accepted by Core's `ParseScript`, with and without the
+/// `OP_` prefix
+fn opcode_names() -> HashMap<String, Opcode> {
+    let mut names = HashMap::new();
+    for byte in iter::once(OP_RESERVED.to_u8()).chain(OP_NOP.to_u8()..=OP_CHECKSIGADD.to_u8()) {
+        let op = Opcode::from(byte);
+        let name = op.to_string();
+        names.insert(name.trim_start_matches("OP_").to_owned(), op);
+        names.insert(name, op);
+    }
+    for (name, op) in [
+        ("NOP2", OP_CLTV),
+        ("CHECKLOCKTIMEVERIFY", OP_CLTV),
+        ("NOP3", OP_CSV),
+        ("CHECKSEQUENCEVERIFY", OP_CSV),
+    ] {
+        names.insert(name.to_owned(), op);
+        names.insert(format!("OP_{name}"), op);
+    }
+    names
+}
+
+/// Parses the script the way Core's `ParseScript` does: numbers are pushed
+/// as script numbers, `0x` words are inserted as raw bytes, and quoted words
+/// are pushed as strings.
+fn parse_script(asm: &str, names: &HashMap<String, Opcode>) -> ScriptBuf {
+    let mut bytes = Vec::new();
+    for word in asm.split_whitespace() {
+        let digits = word.strip_prefix('-').unwrap_or(word);
+        if !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            let n: i64 = word.parse().expect("number is in range");
+            bytes.extend(script::Builder::new().push_int(n).into_bytes());
+        } else if let Some(hex) = word.strip_prefix("0x").filter(|hex| !hex.is_empty()) {
+            bytes.extend(Vec::<u8>::from_hex(hex).expect("valid hex"));
+        } else if word.len() >= 2 && word.starts_with('\'') && word.ends_with('\'') {
+            let data = <&PushBytes>::try_from(word[1..word.len() - 1].as_bytes())
+                .expect("string fits into a push");
+            bytes.extend(script::Builder::new().push_slice(data).into_bytes());
+        } else {
+            let op = names
+                .get(word)
+                .unwrap_or_else(|| panic!("unknown opcode {word}"));
+            bytes.push(op.to_u8());
+        }
+    }
+
+    ScriptBuf::from_bytes(bytes)
+}
+
+/// Maps the flags onto the [`Options`], or returns the reason the vector is
+/// skipped for the first flag that is not supported by the harness.
+fn options(flags: &str) -> Result<Options, String> {
+    let mut opt = Options {
+        require_minimal: false,
+        verify_cltv: false,
+        verify_csv: false,
+        verify_minimal_if: false,
+        verify_nulldummy: false,
+        ..Default::default()
+    };
+
+    for flag in flags
+        .split(',')
+        .filter(|flag| !flag.is_empty() && *flag != "NONE")
+    {
+        match flag {
+            "MINIMALDATA" => opt.require_minimal = true,
+            "NULLDUMMY" => opt.verify_nulldummy = true,
+            "CHECKLOCKTIMEVERIFY" => opt.verify_cltv = true,
+            "CHECKSEQUENCEVERIFY" => opt.verify_csv = true,
+            // P2SH outputs are skipped, and the encodings are not checked by
+            // the interpreter, which shows up as a divergence
+            "P2SH" | "STRICTENC" => {}
+            _ => return Err(format!("unsupported flag {flag}")),
+        }
+    }
+
+    Ok(opt)
+}
+
+/// Transaction spending the output with `script_pubkey` by `script_sig`,
+/// built as Core's `BuildSpendingTransaction` does
+fn spending_tx(script_sig: &ScriptBuf, script_pubkey: &ScriptBuf) -> (Transaction, TxOut) {
+    let prevout = TxOut {
+        value: Amount::ZERO,
+        script_pubkey: script_pubkey.clone(),
+    };
+    let credit = Transaction {
+        version: transaction::Version::ONE,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: ScriptBuf::from_bytes(vec![0, 0]),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output: vec![prevout.clone()],
+    };
+    let spend = Transaction {
+        version: transaction::Version::ONE,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(credit.compute_txid(), 0),
+            script_sig: script_sig.clone(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: ScriptBuf::new(),
+        }],
+    };
+
+    (spend, prevout)
+}
+
+/// Executes the `script` on the `stack`, returning Core's name of the error
+/// if it fails.
+fn execute(
+    script: ScriptBuf,
+    stack: Vec<Vec<u8>>,
+    tx: &(Transaction, TxOut),
+    opt: &Options,
+) -> Result<ExecutionResult, String> {
+    let tx = TxTemplate {
+        tx: tx.0.clone(),
+        prevouts: vec![tx.1.clone()],
+        input_idx: 0,
+        taproot_annex_scriptleaf: None,
+    };
+    let mut exec =
+        Exec::new(ExecCtx::Legacy, opt.clone(), tx, script, stack).map_err(|err| match err {
+            Error::InvalidScript(script::Error::EarlyEndOfScript) => "BAD_OPCODE".to_owned(),
+            Error::InvalidScript(script::Error::NonMinimalPush) => "MINIMALDATA".to_owned(),
+            err => format!("{err:?}"),
+        })?;
+
+    loop {
+        if let Err(res) = exec.exec_next() {
+            return match &res.error {
+                Some(err) => Err(core_error(err).to_owned()),
+                None => Ok(res.clone()),
+            };
+        }
+    }
+}
+
+/// Runs the vector, returning the result in Core's format, or the reason
+/// it is skipped.
+fn run(
+    script_sig: &str,
+    script_pubkey: &str,
+    flags: &str,
+    names: &HashMap<String, Opcode>,
+) -> Result<String, String> {
+    let opt = options(flags)?;
+    let script_sig = parse_script(script_sig, names);
+    let script_pubkey = parse_script(script_pubkey, names);
+    if flags.split(',').any(|flag| flag == "P2SH") && script_pubkey.is_p2sh() {
+        return Err("P2SH output".to_owned());
+    }
+
+    let tx = spending_tx(&script_sig, &script_pubkey);
+    let result = execute(script_sig, vec![], &tx, &opt).and_then(|result| {
+        let stack = result.final_stack.iter_str().collect();
+        execute(script_pubkey, stack, &tx, &opt)
+    });
+
+    Ok(match result {
+        Ok(result) if result.success => "OK".to_owned(),
+        Ok(_) => "EVAL_FALSE".to_owned(),
+        Err(err) => err,
+    })
+}
+
+#[test]
+fn test_script_tests() {
+    let vectors: Vec<serde_json::Value> =
+        serde_json::from_str(SCRIPT_TESTS).expect("vectors are valid JSON");
+    let names = opcode_names();
+
+    let mut passed = 0;
+    let mut divergences = Vec::new();
+    let mut known_hits = vec![false; KNOWN_DIVERGENCES.len()];
+    let mut skipped = BTreeMap::<String, usize>::new();
+    let mut unexpected = Vec::new();
+    for vector in vectors.iter() {
+        let vector = vector.as_array().expect("vector is an array");
+        // Comments
+        if vector.len() == 1 {
+            continue;
+        }
+        // Witness vectors start with the witness and the amount
+        if vector[0].is_array() {
+            *skipped.entry("witness".to_owned()).or_default() += 1;
+            continue;
+        }
+
+        let field = |i: usize| vector[i].as_str().expect("field is a string");
+        let (script_sig, script_pubkey, flags, expected) = (field(0), field(1), field(2), field(3));
+        let result = match run(script_sig, script_pubkey, flags, &names) {
+            Ok(result) => result,
+            Err(reason) => {
+                *skipped.entry(reason).or_default() += 1;
+                continue;
+            }
+        };
+        if result == expected {
+            passed += 1;
+            continue;
+        }
+
+        let known = KNOWN_DIVERGENCES
+            .iter()
+            .position(|(sig, pubkey, fl, ours, _)| {
+                (*sig, *pubkey, *fl, *ours) == (script_sig, script_pubkey, flags, result.as_str())
+            });
+        let vector = format!("[{script_sig:?}, {script_pubkey:?}, {flags:?}]");
+        match known {
+            Some(idx) => {
+                known_hits[idx] = true;
+                let description = KNOWN_DIVERGENCES[idx].4;
+                divergences.push(format!(
+                    "{vector}: expected {expected}, got {result}: {description}"
+                ));
+            }
+            None => unexpected.push(format!("{vector}: expected {expected}, got {result}")),
+        }
+    }
+
+    println!("Passed: {passed}");
+    println!("Known divergences from Bitcoin Core:");
+    for divergence in divergences.iter() {
+        println!("  {divergence}");
+    }
+    println!("Skipped:");
+    for (reason, count) in skipped.iter() {
+        println!("  {count} with {reason}");
+    }
+
+    assert!(
+        unexpected.is_empty(),
+        "unexpected results:\n{}",
+        unexpected.join("\n")
+    );
+    let stale: Vec<_> = KNOWN_DIVERGENCES
+        .iter()
+        .zip(known_hits)
+        .filter(|(_, hit)| !hit)
+        .map(|((sig, pubkey, flags, ours, _), _)| {
+            format!("[{sig:?}, {pubkey:?}, {flags:?}]: {ours}")
+        })
+        .collect();
+    assert!(
+        stale.is_empty(),
+        "known divergences not observed:\n{}",
+        stale.join("\n")
+    );
+}
@@ -0,0 +1,596 @@
+//! Static analysis of the stack and altstack heights during the script
+//! execution.
+//!
+//! Heights of almost all opcodes do not depend on the values on the stack,
+//! so they can be computed without executing the script, which is much
+//! cheaper than executing it. The splitter uses the heights to evaluate
+//! the cut points, and executes only the chosen split.
+//!
+//! The only values which matter are the conditions of `OP_IF`/`OP_NOTIF`
+//! and the indices of `OP_PICK`/`OP_ROLL`. The analyzer tracks which
+//! elements are the same (e.g. copied by `OP_DUP`) and which are constants,
+//! and follows both branches of each unknown condition as separate paths.
+//! So, the scripts like
+//!
+//! ```bitcoin_script
+//! OP_DUP
+//! OP_IF OP_FROMALTSTACK OP_DROP OP_ENDIF
+//! ...
+//! OP_NOTIF OP_FROMALTSTACK OP_DROP OP_ENDIF
+//! ```
+//!
+//! where the heights differ between the branches, but are the same after
+//! both conditionals, are analyzed as well.
+
+use core::fmt;
+
+use bitcoin::{
+    opcodes::{all::*, Opcode},
+    script::{read_scriptint, Instruction},
+};
+use bitcoin_utils::treepp::*;
+
+/// Maximal number of paths analyzed simultaneously.
+const MAX_PATHS: usize = 64;
+
+/// Heights of the stack and altstack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StackHeight {
+    pub stack: usize,
+    pub altstack: usize,
+}
+
+impl StackHeight {
+    /// Returns the total height (stack + altstack), which is the same as
+    /// [`super::intermediate_state::IntermediateState::size`] of the state
+    pub fn size(&self) -> usize {
+        self.stack + self.altstack
+    }
+}
+
+/// Reason why the heights cannot be computed statically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisError {
+    /// Effect of the opcode depends on the values, which are unknown
+    DataDependent { instruction: usize, opcode: Opcode },
+    /// The opcode fails regardless of the values on the stack
+    InvalidOpcode { instruction: usize, opcode: Opcode },
+    /// The opcode requires more elements than there are on the stack
+    Underflow { instruction: usize },
+    /// Too many paths with different heights are possible
+    TooManyPaths { instruction: usize },
+    /// `OP_ELSE` or `OP_ENDIF` without `OP_IF`, or `OP_IF` without `OP_ENDIF`
+    UnbalancedConditional,
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DataDependent {
+                instruction,
+                opcode,
+            } => write!(
+                f,
+                "effect of {opcode} at instruction {instruction} depends on the stack values"
+            ),
+            Self::InvalidOpcode {
+                instruction,
+                opcode,
+            } => write!(f, "{opcode} at instruction {instruction} always fails"),
+            Self::Underflow { instruction } => {
+                write!(f, "stack underflow at instruction {instruction}")
+            }
+            Self::TooManyPaths { instruction } => write!(
+                f,
+                "more than {MAX_PATHS} paths are possible at instruction {instruction}"
+            ),
+            Self::UnbalancedConditional => write!(f, "OP_IF and OP_ENDIF are not balanced"),
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+/// Computes the heights of the stack and altstack before the script and
+/// after each of its instructions, given the `initial` heights (typically,
+/// the heights after the input), so that the `i`-th element corresponds
+/// to the first `i` instructions.
+///
+/// The height is `None` if it depends on the values, e.g. inside the
+/// conditional, which branches leave stacks of different heights.
+pub fn stack_heights(
+    script: &Script,
+    initial: StackHeight,
+) -> Result<Vec<Option<StackHeight>>, AnalysisError> {
+    let mut analyzer = Analyzer::new(initial);
+    let mut heights = vec![Some(initial)];
+
+    for (instruction_id, instruction) in script.instructions().enumerate() {
+        let instruction = instruction.expect("script is most likely corrupted");
+        analyzer.step(instruction_id, instruction)?;
+        heights.push(analyzer.height());
+    }
+
+    if analyzer
+        .paths
+        .iter()
+        .any(|path| !path.conditions.is_empty())
+    {
+        return Err(AnalysisError::UnbalancedConditional);
+    }
+
+    Ok(heights)
+}
+
+/// Abstract value of the stack element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// The number known in advance
+    Const(i64),
+    /// Unknown value, elements with the same id are equal
+    Unknown(usize),
+}
+
+/// Single path of the execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Path {
+    stack: Vec<Value>,
+    altstack: Vec<Value>,
+    /// Whether each of the entered conditionals is executed
+    conditions: Vec<bool>,
+    /// Truth of the unknown values, which the path relies on
+    assumptions: Vec<(usize, bool)>,
+}
+
+/// Outcome of the instruction on one path.
+enum Step {
+    Continue,
+    /// The path forks into two paths with the given assumption on the value
+    Fork {
+        id: usize,
+        opcode: Opcode,
+    },
+    /// The instruction always fails on this path
+    Fail(AnalysisError),
+}
+
+impl Path {
+    fn is_executed(&self) -> bool {
+        self.conditions.iter().all(|&condition| condition)
+    }
+
+    fn truth(&self, value: Value) -> Option<bool> {
+        match value {
+            Value::Const(value) => Some(value != 0),
+            Value::Unknown(id) => self
+                .assumptions
+                .iter()
+                .find(|(assumed, _)| *assumed == id)
+                .map(|(_, truth)| *truth),
+        }
+    }
+
+    fn assume(&mut self, value: Value, truth: bool) {
+        if let Value::Unknown(id) = value {
+            self.assumptions.push((id, truth));
+        }
+    }
+
+    /// Element at `depth` from the top of the stack
+    fn at(&self, depth: usize) -> Value {
+        self.stack[self.stack.len() - 1 - depth]
+    }
+
+    /// Applies the instruction, given that the unknown values, which truth
+    /// is not assumed, are resolved to `resolved` truth.
+    fn apply(
+        &mut self,
+        instruction_id: usize,
+        instruction: Instruction,
+        resolved: Option<(usize, bool)>,
+        next_id: &mut usize,
+    ) -> Step {
+        let mut fresh = || {
+            *next_id += 1;
+            Value::Unknown(*next_id - 1)
+        };
+        let underflow = Step::Fail(AnalysisError::Underflow {
+            instruction: instruction_id,
+        });
+
+        let op = match instruction {
+            Instruction::PushBytes(bytes) => {
+                if self.is_executed() {
+                    let value = read_scriptint(bytes.as_bytes())
+                        .map(Value::Const)
+                        .unwrap_or_else(|_| fresh());
+                    self.stack.push(value);
+                }
+                return Step::Continue;
+            }
+            Instruction::Op(op) => op,
+        };
+
+        // Conditionals are processed even if the path is not executed
+        match op {
+            OP_IF | OP_NOTIF if !self.is_executed() => {
+                self.conditions.push(false);
+                return Step::Continue;
+            }
+            OP_ELSE => {
+                return match self.conditions.last_mut() {
+                    Some(condition) => {
+                        *condition = !*condition;
+                        Step::Continue
+                    }
+                    None => Step::Fail(AnalysisError::UnbalancedConditional),
+                };
+            }
+            OP_ENDIF => {
+                return match self.conditions.pop() {
+                    Some(_) => Step::Continue,
+                    None => Step::Fail(AnalysisError::UnbalancedConditional),
+                };
+            }
+            _ if !self.is_executed() => return Step::Continue,
+            _ => {}
+        }
+
+        // Checking whether the opcode condition is known on this path
+        let truth = |path: &mut Self, value: Value| match (path.truth(value), value) {
+            (Some(truth), _) => Ok(truth),
+            (None, Value::Unknown(id)) => match resolved {
+                Some((resolved_id, truth)) if resolved_id == id => {
+                    path.assume(value, truth);
+                    Ok(truth)
+                }
+                _ => Err(Step::Fork { id, opcode: op }),
+            },
+            (None, Value::Const(_)) => unreachable!("truth of constants is always known"),
+        };
+
+        match op {
+            OP_IF | OP_NOTIF => {
+                let Some(&condition) = self.stack.last() else {
+                    return underflow;
+                };
+                let condition = match truth(self, condition) {
+                    Ok(condition) => condition,
+                    Err(fork) => return fork,
+                };
+                self.stack.pop();
+                self.conditions.push(condition == (op == OP_IF));
+            }
+            OP_IFDUP => {
+                let Some(&top) = self.stack.last() else {
+                    return underflow;
+                };
+                match truth(self, top) {
+                    Ok(true) => self.stack.push(top),
+                    Ok(false) => {}
+                    Err(fork) => return fork,
+                }
+            }
+            OP_VERIFY => {
+                let Some(top) = self.stack.pop() else {
+                    return underflow;
+                };
+                // The script fails otherwise
+                self.assume(top, true);
+            }
+            OP_TOALTSTACK => {
+                let Some(top) = self.stack.pop() else {
+                    return underflow;
+                };
+                self.altstack.push(top);
+            }
+            OP_FROMALTSTACK => {
+                let Some(top) = self.altstack.pop() else {
+                    return underflow;
+                };
+                self.stack.push(top);
+            }
+            OP_PICK | OP_ROLL => {
+                let Some(index) = self.stack.pop() else {
+                    return underflow;
+                };
+                match index {
+                    Value::Const(index) => {
+                        let Some(position) = usize::try_from(index)
+                            .ok()
+                            .and_then(|index| self.stack.len().checked_sub(index + 1))
+                        else {
+                            return underflow;
+                        };
+                        let value = if op == OP_ROLL {
+                            self.stack.remove(position)
+                        } else {
+                            self.stack[position]
+                        };
+                        self.stack.push(value);
+                    }
+                    Value::Unknown(_) => {
+                        if self.stack.is_empty() {
+                            return underflow;
+                        }
+                        // It is unknown which element is moved, so the
+                        // elements are not tracked anymore
+                        if op == OP_ROLL {
+                            self.stack.iter_mut().for_each(|value| *value = fresh());
+                        } else {
+                            self.stack.push(fresh());
+                        }
+                    }
+                }
+            }
+            OP_DEPTH => self.stack.push(Value::Const(self.stack.len() as i64)),
+            OP_PUSHNUM_NEG1 => self.stack.push(Value::Const(-1)),
+            _ if op.to_u8() >= OP_PUSHNUM_1.to_u8() && op.to_u8() <= OP_PUSHNUM_16.to_u8() => {
+                let value = op.to_u8() - OP_PUSHNUM_1.to_u8() + 1;
+                self.stack.push(Value::Const(value as i64));
+            }
+            _ => {
+                let required = match required_elements(op, instruction_id) {
+                    Ok(required) => required,
+                    Err(err) => return Step::Fail(err),
+                };
+                if self.stack.len() < required {
+                    return underflow;
+                }
+
+                let len = self.stack.len();
+                match op {
+                    OP_DROP => {
+                        self.stack.pop();
+                    }
+                    OP_2DROP => self.stack.truncate(len - 2),
+                    OP_DUP => {
+                        let top = self.at(0);
+                        self.stack.push(top);
+                    }
+                    OP_2DUP | OP_3DUP | OP_OVER | OP_2OVER => {
+                        let (depth, count) = match op {
+                            OP_2DUP => (1, 2),
+                            OP_3DUP => (2, 3),
+                            OP_OVER => (1, 1),
+                            _ => (3, 2),
+                        };
+                        for _ in 0..count {
+                            let value = self.at(depth);
+                            self.stack.push(value);
+                        }
+                    }
+                    OP_NIP => {
+                        self.stack.remove(len - 2);
+                    }
+                    OP_ROT | OP_2ROT | OP_2SWAP => {
+                        let (depth, count) = match op {
+                            OP_ROT => (3, 1),
+                            OP_2ROT => (6, 2),
+                            _ => (4, 2),
+                        };
+                        for _ in 0..count {
+                            let value = self.stack.remove(len - depth);
+                            self.stack.push(value);
+                        }
+                    }
+                    OP_SWAP => self.stack.swap(len - 1, len - 2),
+                    OP_TUCK => {
+                        let top = self.at(0);
+                        self.stack.insert(len - 2, top);
+                    }
+                    OP_SIZE => self.stack.push(fresh()),
+                    _ => {
+                        let (pops, pushes) = generic_effect(op);
+                        self.stack.truncate(len - pops);
+                        for _ in 0..pushes {
+                            self.stack.push(fresh());
+                        }
+                    }
+                }
+            }
+        }
+
+        Step::Continue
+    }
+
+    /// Whether two paths can be analyzed as one
+    fn is_mergeable(&self, other: &Self) -> bool {
+        self.conditions == other.conditions
+            && self.stack.len() == other.stack.len()
+            && self.altstack.len() == other.altstack.len()
+    }
+
+    /// Merges the other path into this one, forgetting what differs
+    fn merge(&mut self, other: &Self, next_id: &mut usize) {
+        for (value, other) in self
+            .stack
+            .iter_mut()
+            .zip(&other.stack)
+            .chain(self.altstack.iter_mut().zip(&other.altstack))
+        {
+            if value != other {
+                *value = Value::Unknown(*next_id);
+                *next_id += 1;
+            }
+        }
+
+        self.assumptions
+            .retain(|assumption| other.assumptions.contains(assumption));
+    }
+}
+
+/// Analyzer of all the possible paths of the execution.
+struct Analyzer {
+    paths: Vec<Path>,
+    next_id: usize,
+}
+
+impl Analyzer {
+    fn new(initial: StackHeight) -> Self {
+        let path = Path {
+            stack: (0..initial.stack).map(Value::Unknown).collect(),
+            altstack: (initial.stack..initial.size())
+                .map(Value::Unknown)
+                .collect(),
+            conditions: vec![],
+            assumptions: vec![],
+        };
+
+        Self {
+            paths: vec![path],
+            next_id: initial.size(),
+        }
+    }
+
+    /// Height, which is the same on all the paths
+    fn height(&self) -> Option<StackHeight> {
+        let heights = self.paths.iter().map(|path| StackHeight {
+            stack: path.stack.len(),
+            altstack: path.altstack.len(),
+        });
+
+        let mut result = None;
+        for height in heights {
+            if result.is_some_and(|result| result != height) {
+                return None;
+            }
+            result = Some(height);
+        }
+
+        result
+    }
+
+    fn step(
+        &mut self,
+        instruction_id: usize,
+        instruction: Instruction,
+    ) -> Result<(), AnalysisError> {
+        let mut paths = Vec::with_capacity(self.paths.len());
+        let mut failure = None;
+
+        for mut path in std::mem::take(&mut self.paths) {
+            match path.apply(instruction_id, instruction, None, &mut self.next_id) {
+                Step::Continue => paths.push(path),
+                Step::Fork { id, opcode } => {
+                    // Applying the instruction to each of the alternatives
+                    for truth in [true, false] {
+                        let mut alternative = path.clone();
+                        let step = alternative.apply(
+                            instruction_id,
+                            instruction,
+                            Some((id, truth)),
+                            &mut self.next_id,
+                        );
+                        match step {
+                            Step::Continue => paths.push(alternative),
+                            Step::Fork { .. } => {
+                                return Err(AnalysisError::DataDependent {
+                                    instruction: instruction_id,
+                                    opcode,
+                                })
+                            }
+                            Step::Fail(err) => failure = Some(err),
+                        }
+                    }
+                }
+                Step::Fail(err) => failure = Some(err),
+            }
+        }
+
+        // Paths, where the script fails, are not possible for the valid
+        // input, so they are skipped unless there are no other paths
+        if paths.is_empty() {
+            return Err(failure.expect("failure is set if there are no paths"));
+        }
+        if let Some(err @ AnalysisError::InvalidOpcode { .. }) = failure {
+            return Err(err);
+        }
+
+        // Merging the paths with the same heights
+        let mut merged: Vec<Path> = Vec::with_capacity(paths.len());
+        for path in paths {
+            match merged.iter_mut().find(|merged| merged.is_mergeable(&path)) {
+                Some(merged) => merged.merge(&path, &mut self.next_id),
+                None => merged.push(path),
+            }
+        }
+
+        if merged.len() > MAX_PATHS {
+            return Err(AnalysisError::TooManyPaths {
+                instruction: instruction_id,
+            });
+        }
+
+        self.paths = merged;
+        Ok(())
+    }
+}
+
+/// Returns the number of elements the opcode requires on the stack.
+fn required_elements(op: Opcode, instruction: usize) -> Result<usize, AnalysisError> {
+    let required = match op {
+        OP_NOP | OP_NOP1 | OP_CLTV | OP_CSV | OP_NOP4 | OP_NOP5 | OP_NOP6 | OP_NOP7 | OP_NOP8
+        | OP_NOP9 | OP_NOP10 | OP_CODESEPARATOR => 0,
+
+        OP_DROP | OP_DUP | OP_SIZE => 1,
+        OP_2DROP | OP_2DUP | OP_NIP | OP_OVER | OP_SWAP | OP_TUCK => 2,
+        OP_3DUP | OP_ROT => 3,
+        OP_2OVER | OP_2SWAP => 4,
+        OP_2ROT => 6,
+
+        _ => {
+            let (pops, _) = generic_effect(op);
+            if pops == 0 {
+                return Err(match op {
+                    OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => AnalysisError::DataDependent {
+                        instruction,
+                        opcode: op,
+                    },
+                    _ => AnalysisError::InvalidOpcode {
+                        instruction,
+                        opcode: op,
+                    },
+                });
+            }
+            pops
+        }
+    };
+
+    Ok(required)
+}
+
+/// Returns the number of elements the opcode pops and the number of new
+/// elements it pushes, or zeros if the opcode is not a computation.
+fn generic_effect(op: Opcode) -> (usize, usize) {
+    match op {
+        OP_CAT => (2, 1),
+
+        OP_EQUAL => (2, 1),
+        OP_EQUALVERIFY => (2, 0),
+
+        OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => (1, 1),
+        OP_ADD
+        | OP_SUB
+        | OP_BOOLAND
+        | OP_BOOLOR
+        | OP_NUMEQUAL
+        | OP_NUMNOTEQUAL
+        | OP_LESSTHAN
+        | OP_GREATERTHAN
+        | OP_LESSTHANOREQUAL
+        | OP_GREATERTHANOREQUAL
+        | OP_MIN
+        | OP_MAX => (2, 1),
+        OP_NUMEQUALVERIFY => (2, 0),
+        OP_WITHIN => (3, 1),
+
+        OP_RIPEMD160 | OP_SHA1 | OP_SHA256 | OP_HASH160 | OP_HASH256 => (1, 1),
+
+        OP_CHECKSIG => (2, 1),
+        OP_CHECKSIGVERIFY => (2, 0),
+        OP_CHECKSIGADD => (3, 1),
+
+        _ => (0, 0),
+    }
+}
@@ -0,0 +1,269 @@
+//! Conditional (`OP_IF`/`OP_NOTIF` ... `OP_ENDIF`) regions of the script.
+//!
+//! The splitter never cuts the script inside the conditional, so the
+//! conditional larger than [`MAX_SCRIPT_SIZE`](super::core::MAX_SCRIPT_SIZE) makes the script unsplittable.
+//! Since the condition is a stack value, such conditional can be rewritten
+//! so that the condition is kept in the intermediate state (on top of the
+//! altstack), and each part of the branch is guarded separately:
+//!
+//! ```bitcoin_script
+//! OP_IF                       OP_TOALTSTACK
+//!   { A1 } { A2 }             OP_FROMALTSTACK OP_IF { A1 } OP_1 OP_ELSE OP_0 OP_ENDIF OP_TOALTSTACK
+//! OP_ELSE             =>      OP_FROMALTSTACK OP_IF { A2 } OP_1 OP_ELSE OP_0 OP_ENDIF OP_TOALTSTACK
+//!   { B1 }                    OP_FROMALTSTACK OP_IF OP_1 OP_ELSE { B1 } OP_0 OP_ENDIF OP_TOALTSTACK
+//! OP_ENDIF                    OP_FROMALTSTACK OP_DROP
+//! ```
+//!
+//! The condition is taken from the altstack before each part and put back
+//! after it, so each part is executed on the same stacks as in the original
+//! script, and the splitter can cut between the parts.
+
+use bitcoin::{opcodes::all::*, script::Instruction};
+use bitcoin_utils::treepp::*;
+
+use super::error::SplitError;
+
+/// Size in bytes of the guards around each part of the rewritten
+/// conditional: `OP_FROMALTSTACK OP_IF` and `OP_1 OP_ELSE OP_0 OP_ENDIF
+/// OP_TOALTSTACK` (with the part placed in either branch).
+pub const GUARD_OVERHEAD: usize = 7;
+
+/// Conditional region of the script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConditionalRegion {
+    /// Index of the `OP_IF`/`OP_NOTIF` instruction
+    pub start: usize,
+    /// Index of the matching `OP_ENDIF` instruction
+    pub end: usize,
+    /// Size of the region in bytes
+    pub size: usize,
+}
+
+/// Returns the outermost conditional regions of the script.
+pub fn conditional_regions(script: &Script) -> Result<Vec<ConditionalRegion>, SplitError> {
+    let instructions = collect_instructions(script);
+    let nodes = parse_script(&instructions)?;
+
+    Ok(nodes.iter().filter_map(Node::region).collect())
+}
+
+/// Rewrites each conditional larger than `max_size` bytes as described in
+/// the [module documentation](self), so that the resultant script can be cut
+/// into parts of at most `max_size` bytes, including the guards.
+///
+/// Conditionals with more than one `OP_ELSE` are not rewritten, so
+/// [`SplitError::OversizedConditional`] is returned for them.
+pub fn split_conditionals(script: &Script, max_size: usize) -> Result<Script, SplitError> {
+    let instructions = collect_instructions(script);
+    let nodes = parse_script(&instructions)?;
+
+    let mut result = Script::new();
+    for unit in rewrite(&nodes, max_size)? {
+        append(&mut result, &unit);
+    }
+
+    Ok(result)
+}
+
+fn collect_instructions(script: &Script) -> Vec<Instruction> {
+    script
+        .instructions()
+        .map(|instruction| instruction.expect("script is most likely corrupted"))
+        .collect()
+}
+
+/// Instruction or the whole conditional.
+enum Node<'a> {
+    Instruction(Instruction<'a>),
+    Conditional {
+        /// Whether the conditional starts with `OP_NOTIF`
+        negated: bool,
+        /// Instructions between `OP_IF`, `OP_ELSE`s and `OP_ENDIF`
+        branches: Vec<Vec<Node<'a>>>,
+        region: ConditionalRegion,
+    },
+}
+
+impl Node<'_> {
+    fn region(&self) -> Option<ConditionalRegion> {
+        match self {
+            Node::Instruction(_) => None,
+            Node::Conditional { region, .. } => Some(*region),
+        }
+    }
+
+    /// Pushes the node to the `script` as it is
+    fn push_to(&self, script: &mut Script) {
+        match self {
+            Node::Instruction(instruction) => {
+                script.push_instruction(*instruction);
+            }
+            Node::Conditional {
+                negated, branches, ..
+            } => {
+                script.push_opcode(if *negated { OP_NOTIF } else { OP_IF });
+                for (branch_id, branch) in branches.iter().enumerate() {
+                    if branch_id > 0 {
+                        script.push_opcode(OP_ELSE);
+                    }
+                    for node in branch {
+                        node.push_to(script);
+                    }
+                }
+                script.push_opcode(OP_ENDIF);
+            }
+        }
+    }
+}
+
+/// Parses the whole script, checking that there is no `OP_ELSE`/`OP_ENDIF`
+/// without `OP_IF`
+fn parse_script<'a>(instructions: &[Instruction<'a>]) -> Result<Vec<Node<'a>>, SplitError> {
+    let mut position = 0;
+    let nodes = parse(instructions, &mut position)?;
+    if position != instructions.len() {
+        return Err(SplitError::UnbalancedConditional);
+    }
+
+    Ok(nodes)
+}
+
+/// Parses the instructions starting at `position` till the end of the
+/// script or the `OP_ELSE`/`OP_ENDIF` of the enclosing conditional.
+fn parse<'a>(
+    instructions: &[Instruction<'a>],
+    position: &mut usize,
+) -> Result<Vec<Node<'a>>, SplitError> {
+    let mut nodes = vec![];
+
+    while let Some(&instruction) = instructions.get(*position) {
+        match instruction {
+            Instruction::Op(op @ (OP_IF | OP_NOTIF)) => {
+                let start = *position;
+                *position += 1;
+
+                let mut branches = vec![parse(instructions, position)?];
+                loop {
+                    match instructions.get(*position) {
+                        Some(Instruction::Op(OP_ELSE)) => {
+                            *position += 1;
+                            branches.push(parse(instructions, position)?);
+                        }
+                        Some(Instruction::Op(OP_ENDIF)) => break,
+                        _ => return Err(SplitError::UnbalancedConditional),
+                    }
+                }
+
+                let end = *position;
+                *position += 1;
+
+                let mut node = Node::Conditional {
+                    negated: op == OP_NOTIF,
+                    branches,
+                    region: ConditionalRegion {
+                        start,
+                        end,
+                        size: 0,
+                    },
+                };
+                let mut script = Script::new();
+                node.push_to(&mut script);
+                if let Node::Conditional { region, .. } = &mut node {
+                    region.size = script.len();
+                }
+
+                nodes.push(node);
+            }
+            Instruction::Op(OP_ELSE | OP_ENDIF) => break,
+            _ => {
+                nodes.push(Node::Instruction(instruction));
+                *position += 1;
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// Rewrites the nodes into the units, which can be cut between
+fn rewrite(nodes: &[Node], max_size: usize) -> Result<Vec<Script>, SplitError> {
+    let mut units = vec![];
+
+    for node in nodes {
+        let Node::Conditional {
+            negated,
+            branches,
+            region,
+        } = node
+        else {
+            let mut unit = Script::new();
+            node.push_to(&mut unit);
+            units.push(unit);
+            continue;
+        };
+
+        if region.size <= max_size {
+            let mut unit = Script::new();
+            node.push_to(&mut unit);
+            units.push(unit);
+            continue;
+        }
+
+        let empty: &[Node] = &[];
+        let (then_branch, else_branch) = match branches.as_slice() {
+            [then_branch] => (then_branch.as_slice(), empty),
+            [then_branch, else_branch] => (then_branch.as_slice(), else_branch.as_slice()),
+            _ => return Err(SplitError::OversizedConditional(*region)),
+        };
+        // OP_NOTIF is the same as OP_IF with swapped branches
+        let (then_branch, else_branch) = if *negated {
+            (else_branch, then_branch)
+        } else {
+            (then_branch, else_branch)
+        };
+
+        // Each part is guarded, so the part itself (including the nested
+        // conditionals) must leave room for the guards
+        let part_size = max_size.saturating_sub(GUARD_OVERHEAD);
+
+        units.push(script! { OP_TOALTSTACK });
+        for part in pack(rewrite(then_branch, part_size)?, part_size) {
+            units.push(script! {
+                OP_FROMALTSTACK
+                OP_IF { part } OP_1 OP_ELSE OP_0 OP_ENDIF
+                OP_TOALTSTACK
+            });
+        }
+        for part in pack(rewrite(else_branch, part_size)?, part_size) {
+            units.push(script! {
+                OP_FROMALTSTACK
+                OP_IF OP_1 OP_ELSE { part } OP_0 OP_ENDIF
+                OP_TOALTSTACK
+            });
+        }
+        units.push(script! { OP_FROMALTSTACK OP_DROP });
+    }
+
+    Ok(units)
+}
+
+/// Packs consecutive units into parts of at most `max_size` bytes, unless
+/// the unit itself is larger.
+fn pack(units: Vec<Script>, max_size: usize) -> Vec<Script> {
+    let mut parts: Vec<Script> = vec![];
+
+    for unit in units {
+        match parts.last_mut() {
+            Some(part) if part.len() + unit.len() <= max_size => append(part, &unit),
+            _ => parts.push(unit),
+        }
+    }
+
+    parts
+}
+
+fn append(script: &mut Script, other: &Script) {
+    for instruction in other.instructions() {
+        script.push_instruction(instruction.expect("script is valid"));
+    }
+}
@@ -1,6 +1,6 @@
 //! Module containing the logic of splitting the script into smaller parts
 
-use std::panic;
+use std::collections::{HashMap, VecDeque};
 
 use bitcoin::{
     opcodes::all::{OP_ENDIF, OP_IF, OP_NOTIF},
@@ -8,8 +8,17 @@ use bitcoin::{
 };
 use bitcoin_utils::treepp::*;
 use indicatif::ProgressBar;
-
-use super::script::SplitResult;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    analysis::{stack_heights, StackHeight},
+    conditional::conditional_regions,
+    cost::{shard_shapes, worst_cost, CostModel, ShardShape, StackSizeIndexCost},
+    error::SplitError,
+    script::SplitResult,
+    trace::ExecutionTrace,
+};
 use crate::split::intermediate_state::IntermediateState;
 
 /// Optimal size of the script in bytes
@@ -22,7 +31,11 @@ use crate::split::intermediate_state::IntermediateState;
 pub(super) const DEFAULT_SCRIPT_SIZE: usize = 7000;
 
 /// Maximum scriptsize in bytes that is allowed by the Bitcoin network
-pub(super) const MAX_SCRIPT_SIZE: usize = 50000;
+pub const MAX_SCRIPT_SIZE: usize = 50000;
+
+/// Maximum number of elements on the stack and altstack together
+/// that is allowed by the Bitcoin network
+pub const MAX_STACK_ITEMS: usize = 1000;
 
 /// When dealing with BitVM2 Disprove transaction,
 /// there are two factors that we need to consider:
@@ -40,16 +53,80 @@ pub(super) const STACK_SIZE_INDEX: usize = 1000;
 ///
 /// - [`SplitType::ByInstructions`]- splits the script by the number of instructions
 /// - [`SplitType::ByBytes`] - splits the script by the number of bytes
-#[derive(Debug, Clone, Copy, Default)]
+/// - [`SplitType::ByMarkers`] - splits the script at each marker (see
+///   [`super::script::SplitableScript::markers`])
+/// - [`SplitType::ByPackedMarkers`] - splits the script at markers, packing the
+///   consecutive marked segments into one shard while it is within the chunk size
+///   in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SplitType {
     #[default]
     ByInstructions,
     ByBytes,
+    ByMarkers,
+    ByPackedMarkers,
 }
 
 /// Splits the given script into smaller parts. Tries to keep each chunk size
 /// to the optimal size `chunk_size` as close as possible.
-pub fn split_into_shards(script: &Script, chunk_size: usize, split_type: SplitType) -> Vec<Script> {
+///
+/// Fails if the script cannot be split into shards of at most
+/// [`MAX_SCRIPT_SIZE`] bytes, see [`SplitError`].
+pub fn split_into_shards(
+    script: &Script,
+    chunk_size: usize,
+    split_type: SplitType,
+) -> Result<Vec<Script>, SplitError> {
+    split_into_shards_with_markers(script, &[], chunk_size, split_type)
+}
+
+/// The same as [`split_into_shards`], but with the `markers` used by
+/// [`SplitType::ByMarkers`] and [`SplitType::ByPackedMarkers`]. Each marker
+/// is the number of instructions before the cut.
+pub fn split_into_shards_with_markers(
+    script: &Script,
+    markers: &[usize],
+    chunk_size: usize,
+    split_type: SplitType,
+) -> Result<Vec<Script>, SplitError> {
+    check_conditionals(script)?;
+
+    let shards = match split_type {
+        SplitType::ByInstructions | SplitType::ByBytes => {
+            cut_into_shards(script, chunk_size, split_type)
+        }
+        SplitType::ByMarkers => split_by_markers(script, markers, chunk_size, false)?,
+        SplitType::ByPackedMarkers => split_by_markers(script, markers, chunk_size, true)?,
+    };
+
+    // Checking that the total size has not exceeded the maximum size
+    if let Some((shard, size)) = shards
+        .iter()
+        .map(|shard| shard.len())
+        .enumerate()
+        .find(|(_, size)| *size > MAX_SCRIPT_SIZE)
+    {
+        return Err(SplitError::OversizedShard { shard, size });
+    }
+
+    Ok(shards)
+}
+
+/// Checks that the conditionals of the script are balanced and fit into
+/// [`MAX_SCRIPT_SIZE`]. Conditionals are never cut, so otherwise the script
+/// cannot be split at all.
+fn check_conditionals(script: &Script) -> Result<(), SplitError> {
+    match conditional_regions(script)?
+        .into_iter()
+        .find(|region| region.size > MAX_SCRIPT_SIZE)
+    {
+        Some(region) => Err(SplitError::OversizedConditional(region)),
+        None => Ok(()),
+    }
+}
+
+/// Cuts the script into shards of `chunk_size` instructions or bytes
+fn cut_into_shards(script: &Script, chunk_size: usize, split_type: SplitType) -> Vec<Script> {
     let instructions: Vec<Instruction> = script
         .instructions()
         .map(|instruction| instruction.expect("script is most likely corrupted"))
@@ -72,6 +149,9 @@ pub fn split_into_shards(script: &Script, chunk_size: usize, split_type: SplitTy
         let current_shard_size = match split_type {
             SplitType::ByInstructions => instruction_id % chunk_size + 1,
             SplitType::ByBytes => current_shard.len(),
+            SplitType::ByMarkers | SplitType::ByPackedMarkers => {
+                unreachable!("split by markers is handled separately")
+            }
         };
 
         // Checking if the current instruction is OP_IF or OP_ENDIF
@@ -94,82 +174,449 @@ pub fn split_into_shards(script: &Script, chunk_size: usize, split_type: SplitTy
             if_count = 0;
             endif_count = 0;
         }
-
-        // Checking that the total size has not exceeded the maximum size
-        assert!(
-            current_shard_size <= MAX_SCRIPT_SIZE,
-            "Script size has exceeded the maximum size"
-        );
     }
 
     shards
 }
 
+/// Splits the script at the `markers`. If `packed` is set, the consecutive
+/// segments between markers are put into one shard while its size in bytes
+/// does not exceed `chunk_size`.
+fn split_by_markers(
+    script: &Script,
+    markers: &[usize],
+    chunk_size: usize,
+    packed: bool,
+) -> Result<Vec<Script>, SplitError> {
+    let instructions: Vec<Instruction> = script
+        .instructions()
+        .map(|instruction| instruction.expect("script is most likely corrupted"))
+        .collect();
+
+    // Number of open OP_IFs before each instruction and at the end
+    let mut depths = Vec::with_capacity(instructions.len() + 1);
+    let mut depth = 0;
+    depths.push(depth);
+    for instruction in instructions.iter() {
+        match instruction {
+            Instruction::Op(OP_IF | OP_NOTIF) => depth += 1,
+            Instruction::Op(OP_ENDIF) => depth -= 1,
+            _ => {}
+        }
+        depths.push(depth);
+    }
+
+    let mut shards: Vec<Script> = vec![Script::new()];
+    let mut start = 0;
+    for &marker in markers.iter().chain([instructions.len()].iter()) {
+        if marker < start || marker > instructions.len() || depths[marker] != 0 {
+            return Err(SplitError::InvalidMarker { marker });
+        }
+        if marker == start {
+            continue;
+        }
+
+        let mut segment = Script::new();
+        for instruction in &instructions[start..marker] {
+            segment.push_instruction(*instruction);
+        }
+        start = marker;
+
+        // Starting the new shard unless the segment can be packed
+        // into the current one
+        let current_shard = shards.last_mut().expect("shards should not be empty");
+        if !current_shard.is_empty()
+            && (!packed || current_shard.len() + segment.len() > chunk_size)
+        {
+            shards.push(segment);
+        } else {
+            for instruction in segment.instructions() {
+                current_shard.push_instruction(instruction.expect("segment is valid"));
+            }
+        }
+    }
+
+    Ok(shards)
+}
+
 /// Fuzzy split of the script into smaller parts by searching for the optimal size
-/// by checking various script sizes
-pub fn fuzzy_split(input: Script, script: Script, split_type: SplitType) -> SplitResult {
+/// by checking various script sizes.
+///
+/// The script is executed only once (see [`ExecutionTrace`]), and the chunk
+/// sizes are evaluated in parallel using the stack heights from the trace.
+///
+/// Fails if the script cannot be split with any chunk size, see
+/// [`split_into_shards`].
+pub fn fuzzy_split(
+    input: Script,
+    script: Script,
+    split_type: SplitType,
+) -> Result<SplitResult, SplitError> {
+    fuzzy_split_with_markers(input, script, &[], split_type)
+}
+
+/// The same as [`fuzzy_split`], but with the `markers`, see
+/// [`split_into_shards_with_markers`]
+pub fn fuzzy_split_with_markers(
+    input: Script,
+    script: Script,
+    markers: &[usize],
+    split_type: SplitType,
+) -> Result<SplitResult, SplitError> {
+    fuzzy_split_with_cost(input, script, markers, split_type, &StackSizeIndexCost)
+}
+
+/// The same as [`fuzzy_split_with_markers`], but the worst cost of the
+/// disprove script is computed by `cost_model` instead of
+/// [`SplitResult::complexity_index`]. Chunk sizes with the shard rejected
+/// by the model are skipped, and [`SplitError::NoValidSplit`] is returned
+/// if all of them are.
+pub fn fuzzy_split_with_cost<C: CostModel>(
+    input: Script,
+    script: Script,
+    markers: &[usize],
+    split_type: SplitType,
+    cost_model: &C,
+) -> Result<SplitResult, SplitError> {
     // Define the limits
     const MIN_CHUNK_SIZE: usize = 100;
     const MAX_CHUNK_SIZE: usize = MAX_SCRIPT_SIZE;
     const STEP_SIZE: usize = 20;
 
-    // Defining the final result
-    let mut resultant_split = SplitResult::new(vec![], vec![]);
-    let mut resultant_complexity = usize::MAX;
+    let trace = ExecutionTrace::new(&input, &script);
+    let heights = trace.heights();
 
     // Now, displaying the progress bar
     let total_progress = (MAX_CHUNK_SIZE - MIN_CHUNK_SIZE) / STEP_SIZE;
     let bar = ProgressBar::new(total_progress as u64);
 
     // Trying to find the optimal size of the chunk by checking
-    // each size from MIN_CHUNK_SIZE to MAX_CHUNK_SIZE
-    for chunk_size in (MIN_CHUNK_SIZE..MAX_CHUNK_SIZE).step_by(STEP_SIZE) {
-        // Incrementing the progress bar
-        bar.inc(1);
-        // We are using panic::catch_unwind to catch any panics that might occur
-        // during the splitting process. If a panic occurs, we just skip the current
-        // chunk size and continue with the next one.
-        let current_split_result = panic::catch_unwind(|| {
-            naive_split(input.clone(), script.clone(), split_type, chunk_size)
+    // each size from MIN_CHUNK_SIZE to MAX_CHUNK_SIZE. If the script
+    // cannot be split with the chunk size, it is skipped. On equal
+    // complexity, the smallest chunk size is chosen.
+    let chunk_sizes: Vec<usize> = (MIN_CHUNK_SIZE..MAX_CHUNK_SIZE)
+        .step_by(STEP_SIZE)
+        .collect();
+    let resultant = chunk_sizes
+        .into_par_iter()
+        .filter_map(|chunk_size| {
+            // Incrementing the progress bar
+            bar.inc(1);
+            let shards =
+                split_into_shards_with_markers(&script, markers, chunk_size, split_type).ok()?;
+            let complexity = worst_cost(&shard_shapes(&shards, heights), cost_model)?;
+
+            Some((complexity, chunk_size))
+        })
+        .min();
+
+    bar.finish();
+    let (_, chunk_size) = resultant.ok_or(SplitError::NoValidSplit)?;
+    let shards = split_into_shards_with_markers(&script, markers, chunk_size, split_type)?;
+    let intermediate_states = trace.states_of_shards(&shards);
+
+    Ok(SplitResult::new(shards, intermediate_states))
+}
+
+/// Heights of the stack and altstack after the input
+fn input_height(input: &Script) -> StackHeight {
+    let state = IntermediateState::from_input_script(input, &Script::new());
+
+    StackHeight {
+        stack: state.stack.len(),
+        altstack: state.altstack.len(),
+    }
+}
+
+/// Optimal split of the script into smaller parts minimizing the worst
+/// cost of the shard by [`StackSizeIndexCost`] (see
+/// [`SplitResult::complexity_index`]), see [`optimal_split_with_cost`].
+pub fn optimal_split(input: Script, script: Script) -> Result<SplitResult, SplitError> {
+    optimal_split_with_cost(input, script, &StackSizeIndexCost)
+}
+
+/// Optimal split of the script into smaller parts. Unlike [`naive_split`],
+/// shards are of different sizes: the script is cut at instruction boundaries
+/// outside of `OP_IF`/`OP_ENDIF` blocks, chosen so that the worst cost of
+/// the shard computed by `cost_model` is minimal.
+///
+/// The cost model must not decrease the cost of the shard (or accept the
+/// shard it rejected) when the shard grows, given the same heights of the
+/// states before and after it. Both [`StackSizeIndexCost`] and the models
+/// based on the disprove script size and stack usage are such. Since the
+/// number of shards before is not known in advance, [`ShardShape::index`]
+/// is 0 for the first shard and 1 for the others.
+///
+/// Stack heights are computed statically (see [`stack_heights`]) if
+/// possible, otherwise the script is executed once.
+///
+/// Fails if the conditionals are unbalanced or some of them does not fit
+/// into [`MAX_SCRIPT_SIZE`] (see [`split_into_shards`]), or if the cost model
+/// rejects every split.
+pub fn optimal_split_with_cost<C: CostModel>(
+    input: Script,
+    script: Script,
+    cost_model: &C,
+) -> Result<SplitResult, SplitError> {
+    check_conditionals(&script)?;
+
+    let instructions: Vec<Instruction> = script
+        .instructions()
+        .map(|instruction| instruction.expect("script is most likely corrupted"))
+        .collect();
+
+    if instructions.is_empty() {
+        let shards = vec![Script::new()];
+        let intermediate_states = form_states_from_shards(shards.clone(), input);
+        return Ok(SplitResult::new(shards, intermediate_states));
+    }
+
+    let heights = match stack_heights(&script, input_height(&input))
+        .ok()
+        .and_then(|heights| heights.into_iter().collect::<Option<Vec<_>>>())
+    {
+        Some(heights) => heights,
+        None => ExecutionTrace::new(&input, &script).heights().to_vec(),
+    };
+    let cuts = minimal_cuts(&instructions, &heights, cost_model).ok_or(SplitError::NoValidSplit)?;
+
+    let shards: Vec<Script> = cuts
+        .windows(2)
+        .map(|cut| {
+            let mut shard = Script::new();
+            for instruction in &instructions[cut[0]..cut[1]] {
+                shard.push_instruction(*instruction);
+            }
+            shard
+        })
+        .collect();
+    let intermediate_states = form_states_from_shards(shards.clone(), input);
+
+    Ok(SplitResult::new(shards, intermediate_states))
+}
+
+/// Finds the cuts (as the number of instructions before each of them,
+/// including the start and the end of the script) with the minimal worst
+/// cost of the shard, given the `heights` before each instruction and at
+/// the end of the script. Returns `None` if the cost model rejects every
+/// split.
+///
+/// For each boundary outside of `OP_IF`/`OP_ENDIF` blocks, the previous
+/// cut is chosen among the boundaries not further than [`MAX_SCRIPT_SIZE`]
+/// bytes, grouped by their heights. Within the group, the closer boundary
+/// gives the cheaper shard, so the further one is only kept if the worst
+/// cost of the shards before it is lower. Then, the best previous cut of
+/// the group is where the worst cost before it and the cost of the shard
+/// cross, which is found by the binary search.
+fn minimal_cuts<C: CostModel>(
+    instructions: &[Instruction],
+    heights: &[StackHeight],
+    cost_model: &C,
+) -> Option<Vec<usize>> {
+    // Number of bytes before each instruction and at the end
+    let mut offsets = Vec::with_capacity(instructions.len() + 1);
+    offsets.push(0);
+    for instruction in instructions {
+        offsets.push(offsets[offsets.len() - 1] + instruction_size(instruction));
+    }
+
+    // For each boundary, the worst cost of the shards before it and the
+    // previous cut if the script prefix before the boundary can be split
+    let mut worst_before: Vec<Option<usize>> = vec![None; instructions.len() + 1];
+    let mut previous: Vec<usize> = vec![0; instructions.len() + 1];
+    worst_before[0] = Some(0);
+
+    // Candidates for the previous cut by the heights of the state and
+    // whether the shard is the first one, from the furthest to the closest
+    let mut candidates: HashMap<(StackHeight, bool), VecDeque<usize>> = HashMap::new();
+    candidates.insert((heights[0], true), VecDeque::from([0]));
+
+    let mut peaks = RunningPeaks::default();
+    peaks.push(0, heights[0].size());
+
+    let mut depth = 0;
+    for (instruction_id, instruction) in instructions.iter().enumerate() {
+        let end = instruction_id + 1;
+        peaks.push(end, heights[end].size());
+
+        if let Instruction::Op(op) = instruction {
+            match *op {
+                OP_IF | OP_NOTIF => depth += 1,
+                OP_ENDIF => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth != 0 {
+            continue;
+        }
+
+        // The minimal worst cost of the shards up to the boundary
+        // and the previous cut
+        let mut best: Option<(usize, usize)> = None;
+        candidates.retain(|&(from, first), starts| {
+            while starts
+                .front()
+                .is_some_and(|&start| offsets[end] - offsets[start] > MAX_SCRIPT_SIZE)
+            {
+                starts.pop_front();
+            }
+
+            let shard_cost = |start: usize| {
+                cost_model.cost(&ShardShape {
+                    index: usize::from(!first),
+                    shard_size: offsets[end] - offsets[start],
+                    from,
+                    to: heights[end],
+                    peak_items: peaks.max_since(start),
+                })
+            };
+            let cost_before = |start: usize| worst_before[start].expect("candidate is reachable");
+
+            // Towards the closer candidates, the worst cost before them
+            // increases, while the cost of the shard does not
+            let starts_slice = starts.make_contiguous();
+            let crossing = starts_slice.partition_point(|&start| {
+                shard_cost(start).map_or(true, |cost| cost > cost_before(start))
+            });
+            let crossing_candidates = [
+                starts_slice
+                    .get(crossing)
+                    .map(|&start| (cost_before(start), start)),
+                crossing
+                    .checked_sub(1)
+                    .and_then(|idx| Some((shard_cost(starts_slice[idx])?, starts_slice[idx]))),
+            ];
+            for candidate in crossing_candidates.into_iter().flatten() {
+                if best.map_or(true, |best| candidate.0 < best.0) {
+                    best = Some(candidate);
+                }
+            }
+
+            !starts.is_empty()
         });
-        if let Ok(split_result) = current_split_result {
-            let current_complexity = split_result.complexity_index();
 
-            if current_complexity < resultant_complexity {
-                resultant_complexity = current_complexity;
-                resultant_split = split_result;
+        if let Some((cost, start)) = best {
+            worst_before[end] = Some(cost);
+            previous[end] = start;
+
+            let starts = candidates.entry((heights[end], false)).or_default();
+            while starts
+                .back()
+                .is_some_and(|&start| worst_before[start] >= Some(cost))
+            {
+                starts.pop_back();
             }
+            starts.push_back(end);
         }
     }
 
-    bar.finish();
-    resultant_split
+    worst_before[instructions.len()]?;
+    let mut cuts = vec![instructions.len()];
+    while let Some(&last) = cuts.last() {
+        if last == 0 {
+            break;
+        }
+        cuts.push(previous[last]);
+    }
+    cuts.reverse();
+
+    Some(cuts)
+}
+
+/// Maximal state size over the ranges of instructions ending at the last
+/// pushed one.
+#[derive(Debug, Default)]
+struct RunningPeaks {
+    /// Positions with the state size greater than at any later one, which
+    /// are the maximums of the ranges starting after the previous position
+    maximums: Vec<(usize, usize)>,
+}
+
+impl RunningPeaks {
+    /// Pushes the state size at the next position
+    fn push(&mut self, position: usize, size: usize) {
+        while self
+            .maximums
+            .last()
+            .is_some_and(|&(_, maximum)| maximum <= size)
+        {
+            self.maximums.pop();
+        }
+        self.maximums.push((position, size));
+    }
+
+    /// Maximal state size from the `start` position to the last pushed one
+    fn max_since(&self, start: usize) -> usize {
+        let idx = self
+            .maximums
+            .partition_point(|&(position, _)| position < start);
+        self.maximums[idx].1
+    }
+}
+
+/// Size of the instruction in bytes when pushed to the shard
+fn instruction_size(instruction: &Instruction) -> usize {
+    let mut script = Script::new();
+    script.push_instruction(*instruction);
+    script.len()
 }
 
 /// Default split of the script into smaller parts with the hard-coded optimal size
-pub fn default_split(input: Script, script: Script, split_type: SplitType) -> SplitResult {
+pub fn default_split(
+    input: Script,
+    script: Script,
+    split_type: SplitType,
+) -> Result<SplitResult, SplitError> {
     naive_split(input, script, split_type, DEFAULT_SCRIPT_SIZE)
 }
 
+/// The same as [`default_split`], but fails with [`SplitError::RejectedShard`]
+/// if `cost_model` rejects the disprove script of some shard.
+pub fn default_split_with_cost<C: CostModel>(
+    input: Script,
+    script: Script,
+    split_type: SplitType,
+    cost_model: &C,
+) -> Result<SplitResult, SplitError> {
+    let split_result = default_split(input.clone(), script, split_type)?;
+    split_result.check_costs(&input, cost_model)?;
+
+    Ok(split_result)
+}
+
 /// Naive split of the script into smaller parts. It works as follows:
 /// 1. We split the script into smaller parts
 /// 2. We execute each shard with the input
 /// 3. Save intermediate results
 /// 4. Return all the shards and intermediate results in the form of [`SplitResult`]
+///
+/// Fails if the script cannot be split, see [`split_into_shards`].
 pub fn naive_split(
     input: Script,
     script: Script,
     split_type: SplitType,
     chunk_size: usize,
-) -> SplitResult {
-    let shards = split_into_shards(&script, chunk_size, split_type);
+) -> Result<SplitResult, SplitError> {
+    naive_split_with_markers(input, script, &[], split_type, chunk_size)
+}
+
+/// The same as [`naive_split`], but with the `markers`, see
+/// [`split_into_shards_with_markers`]
+pub fn naive_split_with_markers(
+    input: Script,
+    script: Script,
+    markers: &[usize],
+    split_type: SplitType,
+    chunk_size: usize,
+) -> Result<SplitResult, SplitError> {
+    let shards = split_into_shards_with_markers(&script, markers, chunk_size, split_type)?;
     let intermediate_states: Vec<IntermediateState> =
         form_states_from_shards(shards.clone(), input);
 
-    SplitResult {
+    Ok(SplitResult {
         shards,
         intermediate_states,
-    }
+    })
 }
 
 /// Given an array of shards and input, creates the vector of intermediate states
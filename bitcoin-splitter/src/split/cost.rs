@@ -0,0 +1,123 @@
+//! Cost model of the disprove script of the shard, which the splitter
+//! minimizes when choosing the split.
+//!
+//! The default [`StackSizeIndexCost`] is the approximation used by
+//! [`super::script::SplitResult::complexity_index`]. Models knowing the
+//! actual disprove script (e.g. its size and peak stack usage) can reject
+//! shards whose disprove script would be invalid.
+
+use bitcoin_utils::treepp::*;
+
+use super::{
+    analysis::StackHeight, core::STACK_SIZE_INDEX, error::SplitError, script::SplitResult,
+    trace::ExecutionTrace,
+};
+
+/// What is known about the shard and its disprove script before it is formed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardShape {
+    /// Index of the shard
+    pub index: usize,
+    /// Size of the shard in bytes
+    pub shard_size: usize,
+    /// Heights of the state before the shard (the input for the first one)
+    pub from: StackHeight,
+    /// Heights of the state after the shard
+    pub to: StackHeight,
+    /// Maximal number of stack and altstack elements during the execution
+    /// of the shard, including the state before it
+    pub peak_items: usize,
+}
+
+/// Cost of the disprove script of the shard.
+///
+/// The cost must not decrease (and the rejected shard must stay rejected)
+/// when the shard grows with the same heights of the states before and
+/// after it, which [`super::core::optimal_split_with_cost`] relies on.
+pub trait CostModel: Sync {
+    /// Returns the cost of the disprove script of the shard, or `None` if
+    /// such disprove script would be invalid.
+    fn cost(&self, shape: &ShardShape) -> Option<usize>;
+}
+
+/// Cost `|f[i]| + (|z[i]| + |z[i-1]|) * STACK_SIZE_INDEX`, where the input is
+/// not counted. Never rejects the shard.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StackSizeIndexCost;
+
+impl CostModel for StackSizeIndexCost {
+    fn cost(&self, shape: &ShardShape) -> Option<usize> {
+        let previous_state_size = match shape.index {
+            0 => 0,
+            _ => shape.from.size(),
+        };
+
+        Some(shape.shard_size + (shape.to.size() + previous_state_size) * STACK_SIZE_INDEX)
+    }
+}
+
+/// Shapes of the `shards`, which are the consecutive parts of the script,
+/// given the `heights` after each prefix of its instructions (see
+/// [`super::trace::ExecutionTrace::heights`]).
+pub fn shard_shapes(shards: &[Script], heights: &[StackHeight]) -> Vec<ShardShape> {
+    let mut instructions_before = 0;
+
+    shards
+        .iter()
+        .enumerate()
+        .map(|(index, shard)| {
+            let start = instructions_before;
+            instructions_before += shard.instructions().count();
+
+            ShardShape {
+                index,
+                shard_size: shard.len(),
+                from: heights[start],
+                to: heights[instructions_before],
+                peak_items: heights[start..=instructions_before]
+                    .iter()
+                    .map(StackHeight::size)
+                    .max()
+                    .expect("range is not empty"),
+            }
+        })
+        .collect()
+}
+
+/// The worst cost of the shards, or `None` if any of them is rejected
+pub fn worst_cost<C: CostModel + ?Sized>(shapes: &[ShardShape], cost_model: &C) -> Option<usize> {
+    shapes
+        .iter()
+        .map(|shape| cost_model.cost(shape))
+        .try_fold(0, |worst, cost| Some(worst.max(cost?)))
+}
+
+impl SplitResult {
+    /// Shapes of the shards executed on `input`, see [`shard_shapes`].
+    pub fn shapes(&self, input: &Script) -> Vec<ShardShape> {
+        let script = script! {
+            for shard in self.shards.iter() {
+                { shard.clone() }
+            }
+        };
+
+        shard_shapes(&self.shards, ExecutionTrace::new(input, &script).heights())
+    }
+
+    /// Checks that `cost_model` accepts the disprove script of each shard
+    /// executed on `input`.
+    pub fn check_costs<C: CostModel + ?Sized>(
+        &self,
+        input: &Script,
+        cost_model: &C,
+    ) -> Result<(), SplitError> {
+        match self
+            .shapes(input)
+            .into_iter()
+            .find(|shape| cost_model.cost(shape).is_none())
+        {
+            Some(shape) => Err(SplitError::RejectedShard { shard: shape.index }),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Errors of splitting the script into shards.
+
+use core::fmt;
+
+use super::{conditional::ConditionalRegion, core::MAX_SCRIPT_SIZE};
+
+/// Reason why the script cannot be split into shards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitError {
+    /// The conditional cannot be cut and does not fit into [`MAX_SCRIPT_SIZE`].
+    /// Such conditionals can be rewritten by
+    /// [`super::conditional::split_conditionals`].
+    OversizedConditional(ConditionalRegion),
+    /// The shard exceeds [`MAX_SCRIPT_SIZE`] with the chosen chunk size
+    OversizedShard { shard: usize, size: usize },
+    /// The marker is out of order, beyond the end of the script, or inside
+    /// the conditional
+    InvalidMarker { marker: usize },
+    /// `OP_ELSE` or `OP_ENDIF` without `OP_IF`, or `OP_IF` without `OP_ENDIF`
+    UnbalancedConditional,
+    /// The split plan was made for another program, see
+    /// [`super::plan::SplitPlan`]
+    ProgramMismatch,
+    /// The cut of the split plan is out of order, beyond the end of the
+    /// script, or inside the conditional
+    InvalidCut { cut: usize },
+    /// Every split of the script has the shard exceeding
+    /// [`MAX_SCRIPT_SIZE`] or rejected by the cost model, see
+    /// [`super::cost::CostModel`]
+    NoValidSplit,
+    /// The disprove script of the shard is rejected by the cost model
+    RejectedShard { shard: usize },
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OversizedConditional(region) => write!(
+                f,
+                "conditional at instructions {}..={} is {} bytes, which exceeds the maximum size of {} bytes",
+                region.start, region.end, region.size, MAX_SCRIPT_SIZE
+            ),
+            Self::OversizedShard { shard, size } => write!(
+                f,
+                "Script size has exceeded the maximum size: shard {shard} is {size} bytes"
+            ),
+            Self::InvalidMarker { marker } => write!(
+                f,
+                "marker {marker} is out of order, beyond the end of the script, or is inside the OP_IF/OP_ENDIF block"
+            ),
+            Self::UnbalancedConditional => write!(f, "OP_IF and OP_ENDIF are not balanced"),
+            Self::ProgramMismatch => write!(f, "split plan was made for another program"),
+            Self::InvalidCut { cut } => write!(
+                f,
+                "cut {cut} is out of order, beyond the end of the script, or is inside the OP_IF/OP_ENDIF block"
+            ),
+            Self::NoValidSplit => write!(
+                f,
+                "no split of the script fits into the maximum size and is accepted by the cost model"
+            ),
+            Self::RejectedShard { shard } => write!(
+                f,
+                "disprove script of shard {shard} is rejected by the cost model"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
@@ -3,6 +3,7 @@
 
 use core::fmt;
 
+use bitcoin::script::scriptint_vec;
 use bitcoin_scriptexec::Stack;
 use bitcoin_utils::{stack_to_script, treepp::*};
 
@@ -102,6 +103,208 @@ impl IntermediateState {
     pub fn interpret_as_u32_array(&self) -> Vec<u8> {
         self.stack.clone().serialize_to_bytes()
     }
+
+    /// Elements of the stack, keeping byte strings as they are
+    pub fn stack_elements(&self) -> Vec<StateElement> {
+        self.stack
+            .iter_str()
+            .map(StateElement::from_stack_bytes)
+            .collect()
+    }
+
+    /// Elements of the altstack, keeping byte strings as they are
+    pub fn altstack_elements(&self) -> Vec<StateElement> {
+        self.altstack
+            .iter_str()
+            .map(StateElement::from_stack_bytes)
+            .collect()
+    }
+
+    /// Whether any element of the state is a byte string rather than a number
+    pub fn has_byte_strings(&self) -> bool {
+        self.stack_elements()
+            .iter()
+            .chain(self.altstack_elements().iter())
+            .any(|element| matches!(element, StateElement::Bytes(_)))
+    }
+
+    /// Creates the state consisting of the given elements
+    pub fn from_elements(stack: &[StateElement], altstack: &[StateElement]) -> Self {
+        let into_stack = |elements: &[StateElement]| {
+            let mut stack = Stack::new();
+            for element in elements {
+                stack.pushstr(&element.to_stack_bytes());
+            }
+            stack
+        };
+
+        Self {
+            stack: into_stack(stack),
+            altstack: into_stack(altstack),
+        }
+    }
+
+    /// Layout of the state, see [`StateLayout`]
+    pub fn layout(&self) -> StateLayout {
+        StateLayout {
+            stack: self.stack_elements().iter().map(ElementKind::of).collect(),
+            altstack: self
+                .altstack_elements()
+                .iter()
+                .map(ElementKind::of)
+                .collect(),
+        }
+    }
+
+    /// The state where each byte string is replaced by its bytes, see
+    /// [`StateElement::unpack`]. The state consisting of numbers only
+    /// stays the same.
+    pub fn unpacked(&self) -> Self {
+        let unpack = |elements: Vec<StateElement>| {
+            let mut stack = Stack::new();
+            for value in elements.iter().flat_map(StateElement::unpack) {
+                stack.pushnum(value.into());
+            }
+            stack
+        };
+
+        Self {
+            stack: unpack(self.stack_elements()),
+            altstack: unpack(self.altstack_elements()),
+        }
+    }
+}
+
+/// Element of the intermediate state as it is committed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StateElement {
+    /// Non-negative number below `2^31` in its minimal encoding, that is,
+    /// the element pushed by `{ n }`
+    Number(u32),
+    /// Any other byte string (e.g. the result of `OP_SHA256`)
+    Bytes(Vec<u8>),
+}
+
+impl StateElement {
+    /// Classifies the element given as it is stored on the stack
+    pub fn from_stack_bytes(bytes: Vec<u8>) -> Self {
+        let Some(&last) = bytes.last() else {
+            return Self::Number(0);
+        };
+
+        let negative = last & 0x80 != 0;
+        let minimal = last & 0x7f != 0 || (bytes.len() > 1 && bytes[bytes.len() - 2] & 0x80 != 0);
+        if negative || !minimal || bytes.len() > 4 {
+            return Self::Bytes(bytes);
+        }
+
+        Self::Number(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, byte| (value << 8) | u32::from(*byte)),
+        )
+    }
+
+    /// The element as it is stored on the stack
+    pub fn to_stack_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Number(value) => scriptint_vec((*value).into()),
+            Self::Bytes(bytes) => bytes.clone(),
+        }
+    }
+
+    /// The element as numbers, from the bottom of the stack to the top.
+    /// The number stays as it is, while the byte string is split into its
+    /// bytes with the first one on top, which is the layout of
+    /// byte-oriented scripts (e.g. `push_bytes_hex` in `bitcoin-testscripts`).
+    pub fn unpack(&self) -> Vec<u32> {
+        match self {
+            Self::Number(value) => vec![*value],
+            Self::Bytes(bytes) => bytes.iter().rev().map(|byte| u32::from(*byte)).collect(),
+        }
+    }
+}
+
+/// Kind of the element of the state, see [`StateElement`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementKind {
+    Number,
+    /// Byte string of the given length
+    Bytes(usize),
+}
+
+impl ElementKind {
+    /// Kind of the given element
+    pub fn of(element: &StateElement) -> Self {
+        match element {
+            StateElement::Number(_) => Self::Number,
+            StateElement::Bytes(bytes) => Self::Bytes(bytes.len()),
+        }
+    }
+
+    /// Number of elements in the unpacked form, see [`StateElement::unpack`]
+    pub fn unpacked_len(&self) -> usize {
+        match self {
+            Self::Number => 1,
+            Self::Bytes(len) => *len,
+        }
+    }
+}
+
+/// Kinds of the elements of the state, which allow packing the unpacked
+/// state (see [`IntermediateState::unpacked`]) back, so that the lengths of
+/// the byte strings round-trip exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct StateLayout {
+    pub stack: Vec<ElementKind>,
+    pub altstack: Vec<ElementKind>,
+}
+
+impl StateLayout {
+    /// Number of the stack elements in the unpacked form
+    pub fn unpacked_stack_len(&self) -> usize {
+        self.stack.iter().map(ElementKind::unpacked_len).sum()
+    }
+
+    /// Number of the altstack elements in the unpacked form
+    pub fn unpacked_altstack_len(&self) -> usize {
+        self.altstack.iter().map(ElementKind::unpacked_len).sum()
+    }
+
+    /// Packs the values of the unpacked state back into the state, or
+    /// returns `None` if the values do not fit the layout.
+    pub fn pack(&self, stack: &[u32], altstack: &[u32]) -> Option<IntermediateState> {
+        Some(IntermediateState::from_elements(
+            &pack_elements(&self.stack, stack)?,
+            &pack_elements(&self.altstack, altstack)?,
+        ))
+    }
+}
+
+fn pack_elements(kinds: &[ElementKind], mut values: &[u32]) -> Option<Vec<StateElement>> {
+    let mut elements = Vec::with_capacity(kinds.len());
+
+    for kind in kinds {
+        if values.len() < kind.unpacked_len() {
+            return None;
+        }
+        let (element, rest) = values.split_at(kind.unpacked_len());
+        values = rest;
+
+        elements.push(match kind {
+            ElementKind::Number => StateElement::Number(element[0]),
+            ElementKind::Bytes(_) => StateElement::Bytes(
+                element
+                    .iter()
+                    .rev()
+                    .map(|value| u8::try_from(*value).ok())
+                    .collect::<Option<_>>()?,
+            ),
+        });
+    }
+
+    values.is_empty().then_some(elements)
 }
 
 impl IntermediateStateAsBytes {
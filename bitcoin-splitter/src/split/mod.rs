@@ -1,9 +1,16 @@
 //! Module that contains the implementation of the splitter
 //! together with all auxiliary functions and data structures.
 
+pub mod analysis;
+pub mod conditional;
 pub mod core;
+pub mod cost;
+pub mod error;
 pub mod intermediate_state;
+pub mod plan;
 pub mod script;
+pub mod trace;
+pub mod validation;
 
 #[cfg(test)]
 pub mod tests;
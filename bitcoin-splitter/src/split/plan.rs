@@ -0,0 +1,118 @@
+//! Split plan: the boundaries of the shards as a reproducible artifact.
+//!
+//! Operators and challengers must agree on exactly the same shards. Instead
+//! of exchanging the shards themselves or re-running the search (e.g.
+//! [`super::core::fuzzy_split`]), the operator publishes the [`SplitPlan`],
+//! from which anyone re-materializes the same shards byte-for-byte and the
+//! intermediate states for any input.
+
+use bitcoin::{
+    hashes::{sha256, Hash},
+    script::Instruction,
+};
+use bitcoin_utils::treepp::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    conditional::conditional_regions,
+    core::{form_states_from_shards, SplitType},
+    error::SplitError,
+    script::SplitResult,
+};
+
+/// Boundaries of the shards of the program.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SplitPlan {
+    /// SHA-256 of the program the plan was made for
+    pub program_hash: sha256::Hash,
+    /// Split type the cuts were chosen by. The cuts do not depend on it
+    /// when re-materializing the shards.
+    pub split_type: SplitType,
+    /// Number of instructions before each cut, in the non-decreasing order.
+    /// There is one cut less than the shards.
+    pub cuts: Vec<usize>,
+}
+
+impl SplitPlan {
+    /// Creates the plan from the `shards`, which are the consecutive parts
+    /// of the `script`.
+    pub fn from_shards(script: &Script, shards: &[Script], split_type: SplitType) -> Self {
+        let mut instructions_before = 0;
+        let cuts = shards
+            .iter()
+            .take(shards.len().saturating_sub(1))
+            .map(|shard| {
+                instructions_before += shard.instructions().count();
+                instructions_before
+            })
+            .collect();
+
+        Self {
+            program_hash: program_hash(script),
+            split_type,
+            cuts,
+        }
+    }
+
+    /// Cuts the `script` into the shards of the plan.
+    ///
+    /// Fails if the plan was made for another script, or any of its cuts
+    /// is invalid.
+    pub fn shards(&self, script: &Script) -> Result<Vec<Script>, SplitError> {
+        if program_hash(script) != self.program_hash {
+            return Err(SplitError::ProgramMismatch);
+        }
+
+        let instructions: Vec<Instruction> = script
+            .instructions()
+            .map(|instruction| instruction.expect("script is most likely corrupted"))
+            .collect();
+        let regions = conditional_regions(script)?;
+
+        let mut shards = Vec::with_capacity(self.cuts.len() + 1);
+        let mut start = 0;
+        for &cut in self.cuts.iter() {
+            let inside_conditional = regions
+                .iter()
+                .any(|region| region.start < cut && cut <= region.end);
+            if cut < start || cut > instructions.len() || inside_conditional {
+                return Err(SplitError::InvalidCut { cut });
+            }
+
+            shards.push(shard_of(&instructions[start..cut]));
+            start = cut;
+        }
+        shards.push(shard_of(&instructions[start..]));
+
+        Ok(shards)
+    }
+
+    /// Re-materializes the [`SplitResult`] of the `script` for the `input`.
+    pub fn materialize(&self, input: Script, script: &Script) -> Result<SplitResult, SplitError> {
+        let shards = self.shards(script)?;
+        let intermediate_states = form_states_from_shards(shards.clone(), input);
+
+        Ok(SplitResult::new(shards, intermediate_states))
+    }
+}
+
+impl SplitResult {
+    /// Returns the [`SplitPlan`] of the split of the `script`, see
+    /// [`SplitPlan::from_shards`].
+    pub fn plan(&self, script: &Script, split_type: SplitType) -> SplitPlan {
+        SplitPlan::from_shards(script, &self.shards, split_type)
+    }
+}
+
+fn shard_of(instructions: &[Instruction]) -> Script {
+    let mut shard = Script::new();
+    for instruction in instructions {
+        shard.push_instruction(*instruction);
+    }
+    shard
+}
+
+/// SHA-256 of the program the plan is made for
+pub fn program_hash(script: &Script) -> sha256::Hash {
+    sha256::Hash::hash(script.as_bytes())
+}
@@ -3,7 +3,12 @@
 use core::fmt;
 
 use super::{
-    core::{default_split, fuzzy_split, naive_split, SplitType, STACK_SIZE_INDEX},
+    core::{
+        fuzzy_split_with_cost, fuzzy_split_with_markers, naive_split_with_markers, optimal_split,
+        optimal_split_with_cost, SplitType, DEFAULT_SCRIPT_SIZE, STACK_SIZE_INDEX,
+    },
+    cost::CostModel,
+    error::SplitError,
     intermediate_state::IntermediateState,
 };
 use bitcoin_utils::{comparison::OP_LONGEQUALVERIFY, stack_to_script, treepp::*};
@@ -130,8 +135,28 @@ impl SplitResult {
     ///
     /// **WARNING**: This function is used for testing purposes only, DO NOT ever try to use it in production code.
     pub fn distort(&self) -> (Self, usize) {
+        self.distort_with_rng(&mut rand::thread_rng())
+    }
+
+    /// The same as [`Self::distort`], but the shard is chosen using provided `rng`.
+    ///
+    /// **WARNING**: This function is used for testing purposes only, DO NOT ever try to use it in production code.
+    pub fn distort_with_rng<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> (Self, usize) {
         // Choosing a random shard to distort
-        let distorted_shard_id = rand::random::<usize>() % self.shards.len();
+        let distorted_shard_id = rng.gen_range(0..self.shards.len());
+
+        (self.distort_at(distorted_shard_id), distorted_shard_id)
+    }
+
+    /// Distorts the intermediate state of the shard with index `distorted_shard_id`,
+    /// making two state transitions incorrect.
+    ///
+    /// **WARNING**: This function is used for testing purposes only, DO NOT ever try to use it in production code.
+    pub fn distort_at(&self, distorted_shard_id: usize) -> Self {
+        assert!(
+            distorted_shard_id < self.shards.len(),
+            "Shard index is out of bounds"
+        );
 
         // Getting the current stack (if it is empty, we cannot distort it)
         let current_stack = self.intermediate_states[distorted_shard_id].stack.clone();
@@ -151,7 +176,7 @@ impl SplitResult {
             execute_script(random_state).main_stack
         };
 
-        (new_split_result, distorted_shard_id)
+        new_split_result
     }
 }
 
@@ -199,18 +224,83 @@ pub trait SplitableScript {
         Self::verify(input, output)
     }
 
-    /// Splits the script into smaller parts
-    fn default_split(input: Script, split_type: SplitType) -> SplitResult {
-        default_split(input, Self::script(), split_type)
+    /// Returns the natural cut points of the script (e.g. after each round),
+    /// as the number of instructions before each of them, in the increasing order.
+    /// Used by [`SplitType::ByMarkers`] and [`SplitType::ByPackedMarkers`].
+    fn markers() -> Vec<usize> {
+        vec![]
+    }
+
+    /// Splits the script into smaller parts. Fails if the script cannot be
+    /// split, see [`naive_split_with_markers`]
+    fn default_split(input: Script, split_type: SplitType) -> Result<SplitResult, SplitError> {
+        Self::split(input, split_type, DEFAULT_SCRIPT_SIZE)
+    }
+
+    /// The same as [`Self::default_split`], but fails if `cost_model`
+    /// rejects the disprove script of some shard, see
+    /// [`SplitResult::check_costs`]
+    fn default_split_with_cost<C: CostModel>(
+        input: Script,
+        split_type: SplitType,
+        cost_model: &C,
+    ) -> Result<SplitResult, SplitError> {
+        let split_result = Self::default_split(input.clone(), split_type)?;
+        split_result.check_costs(&input, cost_model)?;
+
+        Ok(split_result)
     }
 
     /// Splits the script into smaller parts with the specified chunk size
-    fn split(input: Script, split_type: SplitType, chunk_size: usize) -> SplitResult {
-        naive_split(input, Self::script(), split_type, chunk_size)
+    fn split(
+        input: Script,
+        split_type: SplitType,
+        chunk_size: usize,
+    ) -> Result<SplitResult, SplitError> {
+        naive_split_with_markers(
+            input,
+            Self::script(),
+            &Self::markers(),
+            split_type,
+            chunk_size,
+        )
+    }
+
+    /// Splits the script into smaller parts with the fuzzy split. Fails if
+    /// the script cannot be split, see [`fuzzy_split_with_markers`]
+    fn fuzzy_split(input: Script, split_type: SplitType) -> Result<SplitResult, SplitError> {
+        fuzzy_split_with_markers(input, Self::script(), &Self::markers(), split_type)
+    }
+
+    /// The same as [`Self::fuzzy_split`], but the cost of the disprove
+    /// script is computed by `cost_model`
+    fn fuzzy_split_with_cost<C: CostModel>(
+        input: Script,
+        split_type: SplitType,
+        cost_model: &C,
+    ) -> Result<SplitResult, SplitError> {
+        fuzzy_split_with_cost(
+            input,
+            Self::script(),
+            &Self::markers(),
+            split_type,
+            cost_model,
+        )
+    }
+
+    /// Splits the script into smaller parts of different sizes, minimizing
+    /// the worst disprove script size. Fails if the script cannot be split,
+    /// see [`optimal_split`]
+    fn optimal_split(input: Script) -> Result<SplitResult, SplitError> {
+        optimal_split(input, Self::script())
     }
 
-    /// Splits the script into smaller parts with the fuzzy split
-    fn fuzzy_split(input: Script, split_type: SplitType) -> SplitResult {
-        fuzzy_split(input, Self::script(), split_type)
+    /// The same as [`Self::optimal_split`], but the cost of the disprove
+    /// script is computed by `cost_model`, see [`optimal_split_with_cost`]
+    fn optimal_split_with_cost<C: CostModel>(
+        input: Script,
+        cost_model: &C,
+    ) -> Result<SplitResult, SplitError> {
+        optimal_split_with_cost(input, Self::script(), cost_model)
     }
 }
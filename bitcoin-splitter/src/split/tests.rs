@@ -1,5 +1,22 @@
-use super::{core::split_into_shards, intermediate_state::IntermediateState};
+use super::{
+    analysis::{stack_heights, AnalysisError, StackHeight},
+    conditional::{conditional_regions, split_conditionals, ConditionalRegion, GUARD_OVERHEAD},
+    core::{
+        default_split_with_cost, form_states_from_shards, fuzzy_split_with_cost, naive_split,
+        optimal_split, optimal_split_with_cost, split_into_shards, split_into_shards_with_markers,
+        MAX_SCRIPT_SIZE,
+    },
+    cost::{shard_shapes, worst_cost, CostModel, ShardShape, StackSizeIndexCost},
+    error::SplitError,
+    intermediate_state::{ElementKind, IntermediateState, StateElement},
+    plan::SplitPlan,
+    script::SplitResult,
+    trace::{ExecutionTrace, CHECKPOINT_INTERVAL},
+};
 use crate::split::core::SplitType;
+use bitcoin::opcodes::all::{OP_ENDIF, OP_IF, OP_NOTIF};
+use bitcoin::script::Instruction;
+use bitcoin_scriptexec::ExecError;
 use bitcoin_utils::{stack_to_script, treepp::*};
 
 /// Tests whether splitting the script into subprograms (shards)
@@ -20,7 +37,7 @@ fn test_split_basic() {
     assert!(result.success, "Test script failed");
 
     // Splitting the script into shards
-    let shards = split_into_shards(&test_script, CHUNK_SIZE, SplitType::ByInstructions);
+    let shards = split_into_shards(&test_script, CHUNK_SIZE, SplitType::ByInstructions).unwrap();
 
     // Debugging the shards
     for (i, shard) in shards.clone().into_iter().enumerate() {
@@ -181,7 +198,7 @@ fn test_if_split() {
     };
 
     // Now, splitting the main_script:
-    let shards = split_into_shards(&main_script, CHUNK_SIZE, SplitType::ByInstructions);
+    let shards = split_into_shards(&main_script, CHUNK_SIZE, SplitType::ByInstructions).unwrap();
 
     // Creating the first intermediate state
     let z1 = IntermediateState::from_input_script(&input_script, &shards[0]);
@@ -211,7 +228,7 @@ fn test_state_from_state() {
     };
 
     // Now, splitting the main_script:
-    let shards = split_into_shards(&main_script, CHUNK_SIZE, SplitType::ByInstructions);
+    let shards = split_into_shards(&main_script, CHUNK_SIZE, SplitType::ByInstructions).unwrap();
 
     // Creating the first intermediate state
     let z1 = IntermediateState::from_input_script(&input_script, &shards[0]);
@@ -275,3 +292,818 @@ fn test_state_from_state() {
         "z3 altstack should be empty at this point"
     );
 }
+
+/// Tests whether the optimal split keeps `OP_IF`/`OP_ENDIF` blocks in one shard
+/// and is not worse than the naive split with any chunk size
+#[test]
+fn test_optimal_split() {
+    // Adding input and main scripts
+    let input_script = script! {
+        { 5 } { 10 }
+    };
+    let main_script = script! {
+        for _ in 0..20 {
+            OP_2DUP OP_ADD OP_TOALTSTACK
+            OP_DUP 10 OP_EQUAL OP_IF
+                { 20 } OP_ADD
+                OP_DUP OP_NOTIF OP_1ADD OP_ENDIF
+            OP_ELSE
+                { 50 } OP_ADD
+            OP_ENDIF
+            OP_FROMALTSTACK OP_DROP
+        }
+    };
+
+    let split_result = optimal_split(input_script.clone(), main_script.clone()).unwrap();
+
+    // Shards must constitute the main script
+    let concatenated = script! {
+        for shard in split_result.shards.iter() {
+            { shard.clone() }
+        }
+    };
+    assert_eq!(
+        concatenated, main_script,
+        "shards do not constitute the script"
+    );
+
+    // Each shard must contain whole OP_IF/OP_ENDIF blocks
+    for shard in split_result.shards.iter() {
+        let mut depth = 0;
+        for instruction in shard.instructions() {
+            match instruction.expect("shard is corrupted") {
+                Instruction::Op(OP_IF | OP_NOTIF) => depth += 1,
+                Instruction::Op(OP_ENDIF) => depth -= 1,
+                _ => {}
+            }
+            assert!(depth >= 0, "OP_ENDIF without OP_IF in the shard");
+        }
+        assert_eq!(depth, 0, "OP_IF without OP_ENDIF in the shard");
+    }
+
+    // The last state must be the same as the result of the whole script
+    let result = execute_script(script! {
+        { input_script.clone() }
+        { main_script.clone() }
+    });
+    let last_state = split_result.must_last_state();
+    assert_eq!(last_state.stack, result.main_stack, "stack mismatch");
+    assert_eq!(last_state.altstack, result.alt_stack, "altstack mismatch");
+
+    // The optimal split must be not worse than the naive one
+    for chunk_size in 1..main_script.len() {
+        let naive_result = naive_split(
+            input_script.clone(),
+            main_script.clone(),
+            SplitType::ByBytes,
+            chunk_size,
+        )
+        .unwrap();
+        assert!(
+            split_result.complexity_index() <= naive_result.complexity_index(),
+            "optimal split is worse than the naive split with chunk size {}",
+            chunk_size
+        );
+    }
+}
+
+/// Tests whether statically computed stack heights are the same as
+/// the ones after the execution of each script prefix
+#[test]
+fn test_stack_heights_match_execution() {
+    // Adding input and main scripts
+    let input_script = script! {
+        { 5 } { 10 } { 15 }
+    };
+    let main_script = script! {
+        OP_2DUP OP_ADD OP_TOALTSTACK
+        OP_DUP OP_TOALTSTACK
+        OP_DUP 10 OP_EQUAL OP_IF
+            OP_DROP { 20 } { 30 }
+        OP_ELSE
+            OP_2 OP_PICK OP_SWAP
+        OP_ENDIF
+
+        // Heights differ after the first conditional, but the second
+        // one depends on the same value and makes them equal again
+        OP_DUP OP_0NOTEQUAL OP_DUP
+        OP_IF OP_FROMALTSTACK OP_DROP OP_ENDIF
+        OP_NOTIF OP_FROMALTSTACK OP_DROP OP_ENDIF
+
+        OP_3DUP OP_2ROT OP_2DROP
+        OP_1 OP_ROLL OP_NIP OP_TUCK OP_SIZE
+        OP_DEPTH OP_WITHIN OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF
+        OP_FROMALTSTACK OP_SHA256 OP_SWAP OP_TOALTSTACK
+    };
+
+    let initial = StackHeight {
+        stack: 3,
+        altstack: 0,
+    };
+    let heights = stack_heights(&main_script, initial).expect("heights must be computed");
+
+    let instructions: Vec<_> = main_script
+        .instructions()
+        .map(|instruction| instruction.unwrap())
+        .collect();
+    assert_eq!(heights.len(), instructions.len() + 1);
+    assert!(heights.last().unwrap().is_some(), "final height is unknown");
+
+    // Executing each prefix of the script outside of OP_IF/OP_ENDIF blocks
+    let mut prefix = Script::new();
+    let mut depth = 0;
+    for (instruction, height) in instructions.into_iter().zip(heights.iter().skip(1)) {
+        prefix.push_instruction(instruction);
+        match instruction {
+            Instruction::Op(OP_IF | OP_NOTIF) => depth += 1,
+            Instruction::Op(OP_ENDIF) => depth -= 1,
+            _ => {}
+        }
+        let (0, Some(height)) = (depth, height) else {
+            continue;
+        };
+
+        let state = IntermediateState::from_input_script(&input_script, &prefix);
+        assert_eq!(state.stack.len(), height.stack, "stack height mismatch");
+        assert_eq!(
+            state.altstack.len(),
+            height.altstack,
+            "altstack height mismatch"
+        );
+    }
+}
+
+/// Tests whether the heights, which cannot be computed statically, are reported
+#[test]
+fn test_stack_heights_unknown() {
+    let initial = StackHeight {
+        stack: 2,
+        altstack: 0,
+    };
+
+    let unbalanced_branches = script! {
+        OP_IF OP_DUP OP_ELSE OP_DROP OP_ENDIF
+    };
+    let heights = stack_heights(&unbalanced_branches, initial).unwrap();
+    assert_eq!(heights.last(), Some(&None));
+
+    let data_dependent = script! {
+        OP_ADD OP_CHECKMULTISIG
+    };
+    assert!(matches!(
+        stack_heights(&data_dependent, initial),
+        Err(AnalysisError::DataDependent { instruction: 1, .. })
+    ));
+
+    let disabled = script! {
+        OP_MUL
+    };
+    assert!(matches!(
+        stack_heights(&disabled, initial),
+        Err(AnalysisError::InvalidOpcode { instruction: 0, .. })
+    ));
+
+    let underflow = script! {
+        OP_ADD OP_ADD
+    };
+    assert_eq!(
+        stack_heights(&underflow, initial),
+        Err(AnalysisError::Underflow { instruction: 1 })
+    );
+
+    let missing_endif = script! {
+        OP_IF OP_1
+    };
+    assert_eq!(
+        stack_heights(&missing_endif, initial),
+        Err(AnalysisError::UnbalancedConditional)
+    );
+}
+
+/// Tests whether the script is split at the markers and the marked
+/// segments are packed up to the chunk size
+#[test]
+fn test_split_by_markers() {
+    let test_script = script! {
+        {13123} {1235} OP_ADD
+        OP_DUP OP_IF {4234} OP_ADD OP_ENDIF
+        {3} OP_ADD
+        {18595} OP_EQUAL
+    };
+    let markers = [3, 8, 10];
+
+    let shards =
+        split_into_shards_with_markers(&test_script, &markers, 0, SplitType::ByMarkers).unwrap();
+    let expected_shards = vec![
+        script! { {13123} {1235} OP_ADD },
+        script! { OP_DUP OP_IF {4234} OP_ADD OP_ENDIF },
+        script! { {3} OP_ADD },
+        script! { {18595} OP_EQUAL },
+    ];
+    assert_eq!(shards, expected_shards);
+
+    // Packing the segments while the shard is within the size of the first two
+    let chunk_size = expected_shards[0].len() + expected_shards[1].len();
+    let shards = split_into_shards_with_markers(
+        &test_script,
+        &markers,
+        chunk_size,
+        SplitType::ByPackedMarkers,
+    )
+    .unwrap();
+    assert_eq!(shards.len(), 2);
+    assert_eq!(shards[0].len(), chunk_size);
+
+    let verification_script = script! {
+        for shard in shards {
+            { shard }
+        }
+    };
+    let result = execute_script(verification_script);
+    assert!(result.success, "Verification has failed");
+}
+
+/// Tests whether the marker inside the OP_IF/OP_ENDIF block is rejected
+#[test]
+fn test_split_by_markers_inside_if() {
+    let test_script = script! {
+        OP_1 OP_IF OP_1 OP_ENDIF
+    };
+    assert_eq!(
+        split_into_shards_with_markers(&test_script, &[2], 0, SplitType::ByMarkers),
+        Err(SplitError::InvalidMarker { marker: 2 })
+    );
+}
+
+/// Tests whether the conditional that does not fit into the shard is
+/// reported and can be rewritten into the splittable script
+#[test]
+fn test_oversized_conditional() {
+    const BRANCH_LEN: usize = 30000;
+
+    let test_script = script! {
+        OP_DUP OP_DROP
+        OP_IF
+            for _ in 0..BRANCH_LEN { OP_1ADD }
+            OP_DUP 0 OP_LESSTHAN OP_IF OP_NEGATE OP_ENDIF
+        OP_ELSE
+            for _ in 0..BRANCH_LEN { OP_1SUB }
+        OP_ENDIF
+        OP_1ADD
+    };
+
+    let regions = conditional_regions(&test_script).unwrap();
+    assert_eq!(
+        regions,
+        vec![ConditionalRegion {
+            start: 2,
+            end: 2 * BRANCH_LEN + 10,
+            size: test_script.len() - 3,
+        }]
+    );
+
+    let result = split_into_shards_with_markers(&test_script, &[], 1000, SplitType::ByBytes);
+    assert_eq!(result, Err(SplitError::OversizedConditional(regions[0])));
+    let result = optimal_split(script! { { 5 } { 1 } }, test_script.clone());
+    assert_eq!(
+        result.err(),
+        Some(SplitError::OversizedConditional(regions[0]))
+    );
+
+    // Rewriting the conditional, so each shard fits
+    let rewritten = split_conditionals(&test_script, 1000).unwrap();
+    let shards = split_into_shards_with_markers(&rewritten, &[], 1000, SplitType::ByBytes).unwrap();
+    assert!(shards.iter().all(|shard| shard.len() <= MAX_SCRIPT_SIZE));
+
+    for condition in [0, 1] {
+        let input_script = script! {
+            { 5 } { condition }
+        };
+        let expected = IntermediateState::from_input_script(&input_script, &test_script);
+        let actual = IntermediateState::from_input_script(&input_script, &rewritten);
+        assert_eq!(actual.stack, expected.stack, "stack mismatch");
+        assert_eq!(actual.altstack, expected.altstack, "altstack mismatch");
+    }
+}
+
+/// Tests whether the conditionals rewritten for [`MAX_SCRIPT_SIZE`] fit into
+/// it together with the guards, including the nested conditional which
+/// fits into the limit only without them
+#[test]
+fn test_oversized_conditional_max_script_size() {
+    const BRANCH_LEN: usize = 60000;
+    // The nested conditional is `OP_IF { .. } OP_ENDIF`
+    const NESTED_LEN: usize = MAX_SCRIPT_SIZE - GUARD_OVERHEAD / 2 - 2;
+
+    let test_script = script! {
+        OP_DUP OP_DROP
+        OP_IF
+            for _ in 0..BRANCH_LEN { OP_1ADD }
+            OP_DUP 0 OP_LESSTHAN
+            OP_IF
+                for _ in 0..NESTED_LEN { OP_1SUB }
+            OP_ENDIF
+        OP_ELSE
+            for _ in 0..BRANCH_LEN { OP_1SUB }
+        OP_ENDIF
+        OP_1ADD
+    };
+
+    let rewritten = split_conditionals(&test_script, MAX_SCRIPT_SIZE).unwrap();
+    let regions = conditional_regions(&rewritten).unwrap();
+    assert!(
+        regions.iter().all(|region| region.size <= MAX_SCRIPT_SIZE),
+        "rewritten conditional exceeds the maximum size"
+    );
+    // Parts are packed up to the limit, where `OP_FROMALTSTACK` and
+    // `OP_TOALTSTACK` of the guards are outside of the region
+    assert!(regions
+        .iter()
+        .any(|region| region.size == MAX_SCRIPT_SIZE - 2));
+
+    for condition in [0, 1] {
+        let input_script = script! {
+            { 5 } { condition }
+        };
+        let split_result = optimal_split(input_script.clone(), rewritten.clone()).unwrap();
+        assert!(split_result
+            .shards
+            .iter()
+            .all(|shard| shard.len() <= MAX_SCRIPT_SIZE));
+
+        let expected = IntermediateState::from_input_script(&input_script, &test_script);
+        let actual = split_result.must_last_state();
+        assert_eq!(actual.stack, expected.stack, "stack mismatch");
+        assert_eq!(actual.altstack, expected.altstack, "altstack mismatch");
+    }
+}
+
+/// Tests whether the unbalanced conditional is reported
+#[test]
+fn test_unbalanced_conditional() {
+    let test_script = script! {
+        OP_1 OP_IF OP_1 OP_ELSE OP_0
+    };
+    assert_eq!(
+        conditional_regions(&test_script),
+        Err(SplitError::UnbalancedConditional)
+    );
+    assert_eq!(
+        split_into_shards_with_markers(&test_script, &[], 1, SplitType::ByInstructions),
+        Err(SplitError::UnbalancedConditional)
+    );
+    assert_eq!(
+        optimal_split(Script::new(), test_script).err(),
+        Some(SplitError::UnbalancedConditional)
+    );
+}
+
+/// Tests whether the states formed from the execution trace are the same
+/// as the states formed by executing the shards
+#[test]
+fn test_execution_trace() {
+    const CHUNK_SIZE: usize = 4;
+
+    let input_script = script! {
+        { 5 } { 10 } { 15 }
+    };
+    let test_script = script! {
+        OP_2DUP OP_ADD OP_TOALTSTACK
+        OP_DUP 10 OP_EQUAL OP_IF
+            OP_DROP { 20 } { 30 }
+        OP_ENDIF
+        OP_ADD OP_FROMALTSTACK OP_SWAP OP_TOALTSTACK
+        OP_3DUP OP_ADD OP_ADD
+    };
+
+    let trace = ExecutionTrace::new(&input_script, &test_script);
+    let instructions_number = test_script.instructions().count();
+    assert_eq!(trace.state_sizes().len(), instructions_number + 1);
+    assert_eq!(trace.state_sizes()[0], 3);
+    assert!(trace.state(7).is_none(), "state inside the conditional");
+
+    let shards = split_into_shards(&test_script, CHUNK_SIZE, SplitType::ByInstructions).unwrap();
+    let traced_states = trace.states_of_shards(&shards);
+    let executed_states = form_states_from_shards(shards, input_script);
+    assert_eq!(traced_states.len(), executed_states.len());
+    for (traced, executed) in traced_states.iter().zip(executed_states.iter()) {
+        assert_eq!(traced.size(), executed.size(), "state size mismatch");
+        assert_eq!(
+            traced.inject_script(),
+            executed.inject_script(),
+            "state mismatch"
+        );
+    }
+
+    // Resuming from the boundary reaches the same final state
+    let boundary = shards[0].instructions().count();
+    let mut exec = trace
+        .resume(boundary)
+        .expect("boundary is outside of the conditional");
+    assert_eq!(exec.stack(), &traced_states[0].stack);
+    let result = loop {
+        if let Err(result) = exec.exec_next() {
+            break result.clone();
+        }
+    };
+    let final_state = trace.state(instructions_number).unwrap();
+    assert_eq!(result.final_stack, final_state.stack);
+    assert_eq!(exec.altstack(), &final_state.altstack);
+    assert!(trace.resume(7).is_none(), "state inside the conditional");
+}
+
+/// Tests whether the states restored from the checkpoints of the trace are
+/// the same as the states of the executed prefixes of the script
+#[test]
+fn test_execution_trace_checkpoints() {
+    let input_script = script! {
+        { 5 } { 10 } { 15 }
+    };
+    let test_script = script! {
+        OP_2DUP OP_ADD OP_TOALTSTACK
+        OP_DUP 10 OP_EQUAL OP_IF
+            OP_DROP { 20 } { 30 }
+        OP_ENDIF
+        OP_ADD OP_FROMALTSTACK OP_SWAP OP_TOALTSTACK
+        OP_3DUP OP_ADD OP_ADD
+    };
+    let instructions: Vec<Instruction> = test_script
+        .instructions()
+        .map(|instruction| instruction.unwrap())
+        .collect();
+
+    for interval in [1, 2, 3, CHECKPOINT_INTERVAL] {
+        let trace = ExecutionTrace::with_interval(&input_script, &test_script, interval);
+        let final_state = trace.state(instructions.len()).unwrap();
+
+        for prefix_len in 0..=instructions.len() {
+            let Some(state) = trace.state(prefix_len) else {
+                continue;
+            };
+
+            let mut prefix = Script::new();
+            for instruction in &instructions[..prefix_len] {
+                prefix.push_instruction(*instruction);
+            }
+            let expected = IntermediateState::from_input_script(&input_script, &prefix);
+            assert_eq!(state.stack, expected.stack, "stack mismatch");
+            assert_eq!(state.altstack, expected.altstack, "altstack mismatch");
+            assert_eq!(state.size(), trace.heights()[prefix_len].size());
+
+            // Resuming from any boundary reaches the same final state
+            let mut exec = trace.resume(prefix_len).unwrap();
+            let result = loop {
+                if let Err(result) = exec.exec_next() {
+                    break result.clone();
+                }
+            };
+            assert_eq!(result.final_stack, final_state.stack);
+            assert_eq!(exec.altstack(), &final_state.altstack);
+        }
+    }
+}
+
+/// Tests whether the validation of the split result detects the
+/// distorted state and the wrong script
+#[test]
+fn test_validate_split_result() {
+    const CHUNK_SIZE: usize = 3;
+
+    let input_script = script! {
+        { 5 } { 10 }
+    };
+    let test_script = script! {
+        OP_2DUP OP_ADD OP_TOALTSTACK
+        OP_DUP 10 OP_EQUAL OP_IF
+            OP_DROP { 20 }
+        OP_ENDIF
+        OP_FROMALTSTACK OP_ADD OP_ADD
+    };
+
+    let split_result = naive_split(
+        input_script.clone(),
+        test_script.clone(),
+        SplitType::ByInstructions,
+        CHUNK_SIZE,
+    )
+    .unwrap();
+    let report = split_result.validate(&input_script, &test_script);
+    assert!(report.is_valid(), "valid split is rejected: {:?}", report);
+    assert_eq!(report.shards.len(), split_result.len());
+    assert!(split_result.verify_final_output(&script! { { 40 } }));
+    assert!(!split_result.verify_final_output(&script! { { 41 } }));
+
+    // Distorting the state makes both adjacent shards invalid
+    let distorted = split_result.distort_at(1);
+    let report = distorted.validate(&input_script, &test_script);
+    assert!(report.script_matches);
+    assert_eq!(report.invalid_shards(), vec![1, 2]);
+
+    let other_script = script! {
+        { test_script } OP_1ADD
+    };
+    let report = split_result.validate(&input_script, &other_script);
+    assert!(!report.script_matches);
+    assert!(!report.is_valid());
+
+    // The failed execution is invalid even if the stacks stay as claimed
+    let failing_script = script! {
+        OP_ADD OP_RETURN
+    };
+    let failing_result = SplitResult::new(
+        vec![script! { OP_ADD }, script! { OP_RETURN }],
+        vec![
+            IntermediateState::from_input_script(&input_script, &script! { OP_ADD }),
+            IntermediateState::from_input_script(&input_script, &script! { OP_ADD }),
+        ],
+    );
+    let report = failing_result.validate(&input_script, &failing_script);
+    assert!(report.shards[1].state_matches);
+    assert_eq!(report.shards[1].error, Some(ExecError::OpReturn));
+    assert_eq!(report.invalid_shards(), vec![1]);
+}
+
+/// Tests whether the default cost model agrees with the complexity index
+/// and the split respects the model rejecting the shards
+#[test]
+fn test_cost_model() {
+    /// Rejects the shards with more stack elements during the execution than the limit
+    struct PeakLimit(usize);
+
+    impl CostModel for PeakLimit {
+        fn cost(&self, shape: &ShardShape) -> Option<usize> {
+            (shape.peak_items <= self.0).then_some(shape.shard_size)
+        }
+    }
+
+    let input_script = script! {
+        { 5 } { 10 }
+    };
+    let test_script = script! {
+        OP_2DUP OP_2DUP OP_ADD OP_ADD OP_ADD OP_ADD
+        for _ in 0..50 { OP_DUP OP_1ADD OP_ADD }
+        OP_DUP OP_DUP OP_DUP OP_ADD OP_ADD OP_ADD
+    };
+
+    let trace = ExecutionTrace::new(&input_script, &test_script);
+    let split_result = naive_split(
+        input_script.clone(),
+        test_script.clone(),
+        SplitType::ByInstructions,
+        4,
+    )
+    .unwrap();
+    let shapes = shard_shapes(&split_result.shards, trace.heights());
+    assert_eq!(shapes[0].from.size(), 2);
+    assert_eq!(shapes[0].peak_items, 6);
+    assert_eq!(
+        worst_cost(&shapes, &StackSizeIndexCost),
+        Some(split_result.complexity_index())
+    );
+    assert_eq!(worst_cost(&shapes, &PeakLimit(5)), None);
+
+    assert_eq!(
+        split_result.check_costs(&input_script, &PeakLimit(5)),
+        Err(SplitError::RejectedShard { shard: 0 })
+    );
+    assert_eq!(
+        split_result.check_costs(&input_script, &PeakLimit(6)),
+        Ok(())
+    );
+    assert_eq!(
+        default_split_with_cost(
+            input_script.clone(),
+            test_script.clone(),
+            SplitType::ByInstructions,
+            &PeakLimit(5),
+        )
+        .err(),
+        Some(SplitError::RejectedShard { shard: 0 })
+    );
+
+    // The first instruction alone already exceeds the limit
+    let split_result = fuzzy_split_with_cost(
+        input_script.clone(),
+        test_script.clone(),
+        &[],
+        SplitType::ByInstructions,
+        &PeakLimit(3),
+    );
+    assert_eq!(split_result.err(), Some(SplitError::NoValidSplit));
+
+    let split_result = fuzzy_split_with_cost(
+        input_script.clone(),
+        test_script.clone(),
+        &[],
+        SplitType::ByInstructions,
+        &PeakLimit(6),
+    )
+    .unwrap();
+    assert!(!split_result.is_empty());
+    assert!(split_result
+        .validate(&input_script, &test_script)
+        .is_valid());
+}
+
+/// Tests whether the optimal split respects the cost model and is not
+/// worse by it than the naive split with any chunk size
+#[test]
+fn test_optimal_split_with_cost() {
+    /// Cost is the shard size, rejects the shards with more stack elements
+    /// during the execution than the limit
+    struct PeakLimit(usize);
+
+    impl CostModel for PeakLimit {
+        fn cost(&self, shape: &ShardShape) -> Option<usize> {
+            (shape.peak_items <= self.0).then_some(shape.shard_size)
+        }
+    }
+
+    let input_script = script! {
+        { 5 } { 10 }
+    };
+    let test_script = script! {
+        OP_2DUP OP_2DUP OP_ADD OP_ADD OP_ADD OP_ADD
+        for _ in 0..50 { OP_DUP OP_1ADD OP_ADD }
+        OP_DUP OP_IF OP_DUP OP_DUP OP_ADD OP_ADD OP_ENDIF
+    };
+    let trace = ExecutionTrace::new(&input_script, &test_script);
+
+    // The first instruction alone already exceeds the limit
+    assert_eq!(
+        optimal_split_with_cost(input_script.clone(), test_script.clone(), &PeakLimit(3)).err(),
+        Some(SplitError::NoValidSplit)
+    );
+
+    let split_result =
+        optimal_split_with_cost(input_script.clone(), test_script.clone(), &PeakLimit(6)).unwrap();
+    assert!(split_result
+        .validate(&input_script, &test_script)
+        .is_valid());
+    let optimal_cost = worst_cost(
+        &shard_shapes(&split_result.shards, trace.heights()),
+        &PeakLimit(6),
+    )
+    .expect("optimal split is rejected by the cost model");
+
+    for chunk_size in 1..test_script.len() {
+        let naive_result = naive_split(
+            input_script.clone(),
+            test_script.clone(),
+            SplitType::ByBytes,
+            chunk_size,
+        )
+        .unwrap();
+        let shapes = shard_shapes(&naive_result.shards, trace.heights());
+        if let Some(naive_cost) = worst_cost(&shapes, &PeakLimit(6)) {
+            assert!(
+                optimal_cost <= naive_cost,
+                "optimal split is worse than the naive split with chunk size {}",
+                chunk_size
+            );
+        }
+    }
+}
+
+#[test]
+fn test_state_elements_round_trip() {
+    let state = IntermediateState::from_input_script(
+        &script! {},
+        &script! {
+            { 1234 } OP_0
+            { vec![0xaa; 32] } OP_SHA256
+            { vec![5, 0] }
+            OP_1NEGATE OP_TOALTSTACK
+            { (1 << 31) - 1 } OP_TOALTSTACK
+        },
+    );
+
+    let stack = state.stack_elements();
+    assert_eq!(stack[0], StateElement::Number(1234));
+    assert_eq!(stack[1], StateElement::Number(0));
+    assert!(matches!(&stack[2], StateElement::Bytes(bytes) if bytes.len() == 32));
+    assert_eq!(stack[3], StateElement::Bytes(vec![5, 0]));
+    assert_eq!(
+        state.altstack_elements(),
+        vec![
+            StateElement::Bytes(vec![0x81]),
+            StateElement::Number((1 << 31) - 1)
+        ]
+    );
+    assert!(state.has_byte_strings());
+
+    let layout = state.layout();
+    assert_eq!(
+        layout.stack,
+        vec![
+            ElementKind::Number,
+            ElementKind::Number,
+            ElementKind::Bytes(32),
+            ElementKind::Bytes(2)
+        ]
+    );
+
+    // The unpacked state consists of numbers only, and packs back exactly
+    let unpacked = state.unpacked();
+    assert!(!unpacked.has_byte_strings());
+    assert_eq!(unpacked.stack.len(), layout.unpacked_stack_len());
+    assert_eq!(unpacked.altstack.len(), layout.unpacked_altstack_len());
+
+    let as_u32 = |elements: Vec<StateElement>| -> Vec<u32> {
+        elements
+            .iter()
+            .map(|element| match element {
+                StateElement::Number(value) => *value,
+                StateElement::Bytes(_) => unreachable!("unpacked state has numbers only"),
+            })
+            .collect()
+    };
+    let packed = layout
+        .pack(
+            &as_u32(unpacked.stack_elements()),
+            &as_u32(unpacked.altstack_elements()),
+        )
+        .expect("values fit the layout");
+    assert!(packed.stack.iter_str().eq(state.stack.iter_str()));
+    assert!(packed.altstack.iter_str().eq(state.altstack.iter_str()));
+
+    // The values of the wrong length or the bytes out of range do not fit
+    assert!(layout.pack(&[1, 2], &[]).is_none());
+    let mut out_of_range = as_u32(unpacked.stack_elements());
+    out_of_range[2] = 256;
+    assert!(layout
+        .pack(&out_of_range, &as_u32(unpacked.altstack_elements()))
+        .is_none());
+}
+
+/// Tests whether the split plan re-materializes the same shards and
+/// states, and rejects the plans that do not fit the script
+#[test]
+fn test_split_plan() {
+    let input_script = script! {
+        { 5 } { 10 }
+    };
+    let test_script = script! {
+        OP_2DUP OP_ADD OP_TOALTSTACK
+        OP_DUP 10 OP_EQUAL OP_IF
+            OP_DROP { 20 }
+        OP_ENDIF
+        OP_FROMALTSTACK OP_ADD OP_ADD
+    };
+
+    let split_result = naive_split(
+        input_script.clone(),
+        test_script.clone(),
+        SplitType::ByInstructions,
+        4,
+    )
+    .unwrap();
+    let plan = split_result.plan(&test_script, SplitType::ByInstructions);
+    assert_eq!(plan.cuts.len() + 1, split_result.len());
+
+    // The plan survives the serialization
+    let serialized = serde_json::to_string(&plan).expect("plan is serializable");
+    let plan: SplitPlan = serde_json::from_str(&serialized).expect("plan is deserializable");
+
+    let materialized = plan
+        .materialize(input_script.clone(), &test_script)
+        .expect("plan fits the script");
+    assert_eq!(materialized.shards, split_result.shards);
+    for (state, expected) in materialized
+        .intermediate_states
+        .iter()
+        .zip(split_result.intermediate_states.iter())
+    {
+        assert!(state.stack.iter_str().eq(expected.stack.iter_str()));
+        assert!(state.altstack.iter_str().eq(expected.altstack.iter_str()));
+    }
+
+    // The same plan works for another input
+    let other_input = script! {
+        { 3 } { 4 }
+    };
+    let materialized = plan
+        .materialize(other_input.clone(), &test_script)
+        .expect("plan fits the script");
+    assert!(materialized.validate(&other_input, &test_script).is_valid());
+
+    let other_script = script! {
+        { test_script.clone() } OP_1ADD
+    };
+    assert_eq!(plan.shards(&other_script), Err(SplitError::ProgramMismatch));
+
+    // Cutting inside the conditional, out of order or beyond the end
+    for cuts in [vec![8], vec![10, 3], vec![14]] {
+        let invalid = SplitPlan {
+            cuts: cuts.clone(),
+            ..plan.clone()
+        };
+        assert!(
+            matches!(
+                invalid.shards(&test_script),
+                Err(SplitError::InvalidCut { .. })
+            ),
+            "cuts {cuts:?} are accepted"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Execution trace of the script, which allows forming the intermediate
+//! states of any split without executing the shards.
+
+use bitcoin::{hashes::Hash, opcodes::all::*, script::Instruction, TapLeafHash, Transaction};
+use bitcoin_scriptexec::{Exec, ExecCtx, ExecSnapshot, Options, TxTemplate};
+use bitcoin_utils::treepp::*;
+
+use super::{analysis::StackHeight, intermediate_state::IntermediateState};
+
+/// Number of instructions between the checkpoints of [`ExecutionTrace`]
+pub(super) const CHECKPOINT_INTERVAL: usize = 1024;
+
+/// Stacks recorded while executing the input and the script once.
+///
+/// The heights of the stack and altstack are recorded before each
+/// instruction of the script and at its end, so that the `i`-th height
+/// corresponds to the first `i` instructions. The whole execution is kept
+/// only at the checkpoints every [`CHECKPOINT_INTERVAL`] instructions, so
+/// the memory is proportional to the number of instructions divided by the
+/// interval times the stack depth. The state at any other boundary is
+/// restored by re-running at most the interval of instructions from the
+/// nearest checkpoint before it.
+pub struct ExecutionTrace {
+    /// Execution of the input followed by the script, at its end
+    exec: Exec,
+    /// Heights of the stack and altstack after each prefix of instructions
+    heights: Vec<StackHeight>,
+    /// Whether each prefix of instructions ends outside of conditionals,
+    /// since the script is never cut inside them
+    outside_conditionals: Vec<bool>,
+    /// Number of instructions between the checkpoints
+    interval: usize,
+    /// Execution after each prefix of `interval` multiple instructions
+    checkpoints: Vec<ExecSnapshot>,
+}
+
+impl ExecutionTrace {
+    /// Executes the `input` followed by the `script`, recording the states.
+    ///
+    /// If the execution fails, the state stays the same till the end.
+    pub fn new(input: &Script, script: &Script) -> Self {
+        Self::with_interval(input, script, CHECKPOINT_INTERVAL)
+    }
+
+    /// The same as [`Self::new`], but the checkpoints are kept every
+    /// `interval` instructions.
+    pub(super) fn with_interval(input: &Script, script: &Script, interval: usize) -> Self {
+        assert!(interval > 0, "checkpoint interval must be positive");
+
+        let instructions: Vec<Instruction> = script
+            .instructions()
+            .map(|instruction| instruction.expect("script is most likely corrupted"))
+            .collect();
+
+        let mut full_script = input.clone();
+        for instruction in instructions.iter() {
+            full_script.push_instruction(*instruction);
+        }
+
+        let mut exec = Exec::new(
+            ExecCtx::Tapscript,
+            Options {
+                require_minimal: false,
+                enforce_stack_limit: false,
+                ..Default::default()
+            },
+            TxTemplate {
+                tx: Transaction {
+                    version: bitcoin::transaction::Version::TWO,
+                    lock_time: bitcoin::locktime::absolute::LockTime::ZERO,
+                    input: vec![],
+                    output: vec![],
+                },
+                prevouts: vec![],
+                input_idx: 0,
+                taproot_annex_scriptleaf: Some((TapLeafHash::all_zeros(), None)),
+            },
+            full_script,
+            vec![],
+        )
+        .expect("error when creating the execution body");
+
+        for _ in input.instructions() {
+            let _ = exec.exec_next();
+        }
+
+        let mut heights = Vec::with_capacity(instructions.len() + 1);
+        let mut outside_conditionals = Vec::with_capacity(instructions.len() + 1);
+        let mut checkpoints = Vec::with_capacity(instructions.len() / interval + 1);
+        let mut record = |exec: &Exec, outside: bool| {
+            if heights.len() % interval == 0 {
+                checkpoints.push(exec.snapshot());
+            }
+            heights.push(StackHeight {
+                stack: exec.stack().len(),
+                altstack: exec.altstack().len(),
+            });
+            outside_conditionals.push(outside);
+        };
+        record(&exec, true);
+
+        let mut depth = 0;
+        for instruction in instructions.iter() {
+            let _ = exec.exec_next();
+
+            if let Instruction::Op(op) = instruction {
+                match *op {
+                    OP_IF | OP_NOTIF => depth += 1,
+                    OP_ENDIF => depth -= 1,
+                    _ => {}
+                }
+            }
+            record(&exec, depth == 0);
+        }
+
+        Self {
+            exec,
+            heights,
+            outside_conditionals,
+            interval,
+            checkpoints,
+        }
+    }
+
+    /// Heights of the stack and altstack after each prefix of instructions
+    pub fn heights(&self) -> &[StackHeight] {
+        &self.heights
+    }
+
+    /// Size of the state (stack + altstack) after each prefix of instructions
+    pub fn state_sizes(&self) -> Vec<usize> {
+        self.heights.iter().map(StackHeight::size).collect()
+    }
+
+    /// State after the first `instructions` instructions, if it is
+    /// outside of the conditional
+    pub fn state(&self, instructions: usize) -> Option<IntermediateState> {
+        let exec = self.resume(instructions)?;
+
+        Some(IntermediateState {
+            stack: exec.stack().clone(),
+            altstack: exec.altstack().clone(),
+        })
+    }
+
+    /// Execution restored after the first `instructions` instructions, if
+    /// it is outside of the conditional. The rest of the script can be
+    /// executed from there without re-running the prefix.
+    pub fn resume(&self, instructions: usize) -> Option<Exec> {
+        if !*self.outside_conditionals.get(instructions)? {
+            return None;
+        }
+
+        let mut exec = self.exec.clone();
+        exec.restore(&self.checkpoints[instructions / self.interval]);
+        for _ in 0..instructions % self.interval {
+            let _ = exec.exec_next();
+        }
+
+        Some(exec)
+    }
+
+    /// Intermediate states after each of the `shards`, which are the
+    /// consecutive parts of the traced script.
+    pub fn states_of_shards(&self, shards: &[Script]) -> Vec<IntermediateState> {
+        let mut instructions_before = 0;
+
+        shards
+            .iter()
+            .map(|shard| {
+                instructions_before += shard.instructions().count();
+                self.state(instructions_before)
+                    .expect("shard should end outside of the conditional")
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,143 @@
+//! Self-check of the [`SplitResult`]: whether the intermediate states are
+//! the results of the shards and the shards constitute the original script.
+//! Meant to be run before anything is committed on-chain.
+
+use bitcoin_scriptexec::{ExecError, ExecStats, Stack};
+use bitcoin_utils::treepp::*;
+
+use super::{
+    core::{MAX_SCRIPT_SIZE, MAX_STACK_ITEMS},
+    intermediate_state::IntermediateState,
+    script::SplitResult,
+};
+
+/// Result of the execution of one shard on the previous intermediate state.
+#[derive(Debug, Clone)]
+pub struct ShardReport {
+    /// Whether the execution results in the claimed intermediate state
+    pub state_matches: bool,
+    /// Error the execution has failed with, if any. The stacks at the
+    /// failure may still match the claimed state, e.g. for `OP_RETURN`
+    pub error: Option<ExecError>,
+    /// Size of the shard in bytes
+    pub shard_size: usize,
+    /// Size of the claimed intermediate state (stack + altstack)
+    pub state_size: usize,
+    /// Statistics of the execution, including the injection of the
+    /// previous state
+    pub stats: ExecStats,
+}
+
+impl ShardReport {
+    /// Whether the shard fits into [`MAX_SCRIPT_SIZE`]
+    pub fn within_script_size(&self) -> bool {
+        self.shard_size <= MAX_SCRIPT_SIZE
+    }
+
+    /// Whether the number of stack items has never exceeded [`MAX_STACK_ITEMS`]
+    /// during the execution
+    pub fn within_stack_limit(&self) -> bool {
+        self.stats.max_nb_stack_items <= MAX_STACK_ITEMS
+    }
+
+    /// Whether the shard passes all the checks
+    pub fn is_valid(&self) -> bool {
+        self.state_matches
+            && self.error.is_none()
+            && self.within_script_size()
+            && self.within_stack_limit()
+    }
+}
+
+/// Result of [`SplitResult::validate`].
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    /// Whether the concatenated shards are equal to the original script
+    pub script_matches: bool,
+    /// Whether the number of the intermediate states is the number of shards
+    pub states_number_matches: bool,
+    /// Reports of the shards in the same order
+    pub shards: Vec<ShardReport>,
+}
+
+impl ValidationReport {
+    /// Whether the whole split passes all the checks
+    pub fn is_valid(&self) -> bool {
+        self.script_matches
+            && self.states_number_matches
+            && self.shards.iter().all(ShardReport::is_valid)
+    }
+
+    /// Indices of the shards that do not pass the checks
+    pub fn invalid_shards(&self) -> Vec<usize> {
+        self.shards
+            .iter()
+            .enumerate()
+            .filter(|(_, report)| !report.is_valid())
+            .map(|(shard_id, _)| shard_id)
+            .collect()
+    }
+}
+
+impl SplitResult {
+    /// Checks that `intermediate_states[i] == shards[i](intermediate_states[i-1])`,
+    /// where the state before the first shard is the result of the `input`,
+    /// and that the concatenated shards are equal to the `script`.
+    pub fn validate(&self, input: &Script, script: &Script) -> ValidationReport {
+        let concatenated: Vec<u8> = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.as_bytes().iter().copied())
+            .collect();
+
+        let shards = self
+            .shards
+            .iter()
+            .zip(self.intermediate_states.iter())
+            .enumerate()
+            .map(|(shard_id, (shard, state))| {
+                let previous_state = match shard_id {
+                    0 => input.clone(),
+                    _ => self.intermediate_states[shard_id - 1].inject_script(),
+                };
+                let result = execute_script(script! {
+                    { previous_state }
+                    { shard.clone() }
+                });
+
+                ShardReport {
+                    state_matches: same_stack(&result.main_stack, &state.stack)
+                        && same_stack(&result.alt_stack, &state.altstack),
+                    error: result.error,
+                    shard_size: shard.len(),
+                    state_size: state.size(),
+                    stats: result.stats,
+                }
+            })
+            .collect();
+
+        ValidationReport {
+            script_matches: concatenated == script.as_bytes(),
+            states_number_matches: self.shards.len() == self.intermediate_states.len(),
+            shards,
+        }
+    }
+
+    /// Checks that the last intermediate state consists of the elements
+    /// pushed by the `output` (e.g. [`super::script::IOPair::output`]) and
+    /// the empty altstack.
+    pub fn verify_final_output(&self, output: &Script) -> bool {
+        let Some(last_state) = self.intermediate_states.last() else {
+            return false;
+        };
+        let expected = IntermediateState::from_input_script(output, &Script::new());
+
+        last_state.altstack.is_empty() && same_stack(&last_state.stack, &expected.stack)
+    }
+}
+
+/// Compares the stacks element by element, regardless of whether the
+/// element is stored as a number or as a byte string
+fn same_stack(lhs: &Stack, rhs: &Stack) -> bool {
+    lhs.len() == rhs.len() && lhs.iter_str().eq(rhs.iter_str())
+}
@@ -4,6 +4,7 @@
 
 use bitcoin_splitter::split::{
     core::{form_states_from_shards, SplitType},
+    error::SplitError,
     script::{IOPair, SplitResult, SplitableScript},
 };
 use bitcoin_utils::{pseudo::OP_2K_MUL, treepp::*};
@@ -288,7 +289,7 @@ where
         }
     }
 
-    fn default_split(input: Script, _split_type: SplitType) -> SplitResult {
+    fn default_split(input: Script, _split_type: SplitType) -> Result<SplitResult, SplitError> {
         // First, we need to form the script
         let mut shards: Vec<Script> = vec![];
         shards.push(script! {
@@ -365,10 +366,10 @@ where
         // Now, we need to form the intermediate states
         let intermediate_states = form_states_from_shards(shards.clone(), input);
 
-        SplitResult {
+        Ok(SplitResult {
             shards,
             intermediate_states,
-        }
+        })
     }
 }
 
@@ -452,7 +453,7 @@ mod tests {
 
         // Splitting the script into shards
         let split_result =
-            FriendlyU254MulScript::default_split(input.clone(), SplitType::ByInstructions);
+            FriendlyU254MulScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         // Now, we are going to concatenate all the shards and verify that the script is also correct
         let verification_script = script! {
@@ -487,7 +488,8 @@ mod tests {
         );
 
         // Splitting the script into shards
-        let split_result = FriendlyU254MulScript::default_split(input, SplitType::ByInstructions);
+        let split_result =
+            FriendlyU254MulScript::default_split(input, SplitType::ByInstructions).unwrap();
 
         for (i, shard) in split_result.shards.iter().enumerate() {
             println!("Shard {i} length: {} bytes", shard.len());
@@ -538,7 +540,8 @@ mod tests {
         );
 
         // Splitting the script into shards
-        let split_result = FriendlyU64MulScript::default_split(input, SplitType::ByInstructions);
+        let split_result =
+            FriendlyU64MulScript::default_split(input, SplitType::ByInstructions).unwrap();
 
         for (i, shard) in split_result.shards.iter().enumerate() {
             println!("Shard {i} length: {} bytes", shard.len());
@@ -577,7 +580,7 @@ mod tests {
 
         // Splitting the script into shards
         let split_result =
-            FriendlyU254MulScript::default_split(input.clone(), SplitType::ByInstructions);
+            FriendlyU254MulScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         for i in 0..split_result.len() {
             // Forming first two inputs. Note that the first input is the input script itself
@@ -624,7 +627,7 @@ mod tests {
 
         // Splitting the script into shards
         let split_result =
-            FriendlyU254MulScript::default_split(input.clone(), SplitType::ByInstructions);
+            FriendlyU254MulScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         for i in 0..split_result.len() {
             // Forming first two inputs. Note that the first input is the input script itself
@@ -129,7 +129,8 @@ mod tests {
         );
 
         // Splitting the script into shards
-        let split_result = U254MulScript::default_split(input.clone(), SplitType::ByInstructions);
+        let split_result =
+            U254MulScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         // Now, we are going to concatenate all the shards and verify that the script is also correct
         let verification_script = script! {
@@ -154,7 +155,7 @@ mod tests {
         let IOPair { input, output } = U254MulScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U254MulScript::default_split(input, SplitType::ByInstructions);
+        let split_result = U254MulScript::default_split(input, SplitType::ByInstructions).unwrap();
 
         for shard in split_result.shards.iter() {
             println!("Shard: {:?}", shard.len());
@@ -199,7 +200,8 @@ mod tests {
         let IOPair { input, output: _ } = U254MulScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U254MulScript::default_split(input.clone(), SplitType::ByInstructions);
+        let split_result =
+            U254MulScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         for i in 0..split_result.len() {
             // Forming first two inputs. Note that the first input is the input script itself
@@ -245,7 +247,8 @@ mod tests {
         let IOPair { input, output: _ } = U254MulScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U254MulScript::default_split(input.clone(), SplitType::ByInstructions);
+        let split_result =
+            U254MulScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         for i in 0..split_result.len() {
             // Forming first two inputs. Note that the first input is the input script itself
@@ -295,7 +298,7 @@ mod tests {
         let IOPair { input, output } = U254MulScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U254MulScript::fuzzy_split(input, SplitType::ByInstructions);
+        let split_result = U254MulScript::fuzzy_split(input, SplitType::ByInstructions).unwrap();
 
         for shard in split_result.shards.iter() {
             println!("Shard: {:?}", shard.len());
@@ -349,7 +352,8 @@ mod tests {
         );
 
         // Splitting the script into shards
-        let split_result = U32MulScript::default_split(input.clone(), SplitType::ByInstructions);
+        let split_result =
+            U32MulScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         // Now, we are going to concatenate all the shards and verify that the script is also correct
         let verification_script = script! {
@@ -379,7 +383,8 @@ mod tests {
         let IOPair { input, output } = U32MulScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U32MulScript::split(input, SplitType::ByInstructions, SPLIT_SIZE);
+        let split_result =
+            U32MulScript::split(input, SplitType::ByInstructions, SPLIT_SIZE).unwrap();
 
         for shard in split_result.shards.iter() {
             println!("Shard: {:?}", shard.len());
@@ -425,7 +430,7 @@ mod tests {
         let IOPair { input, output } = U32MulScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U32MulScript::fuzzy_split(input, SplitType::ByInstructions);
+        let split_result = U32MulScript::fuzzy_split(input, SplitType::ByInstructions).unwrap();
 
         for shard in split_result.shards.iter() {
             println!("Shard: {:?}", shard.len());
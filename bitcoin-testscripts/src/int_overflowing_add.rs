@@ -106,7 +106,8 @@ mod tests {
         );
 
         // Splitting the script into shards
-        let split_result = U254AddScript::default_split(input.clone(), SplitType::ByInstructions);
+        let split_result =
+            U254AddScript::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         // Now, we are going to concatenate all the shards and verify that the script is also correct
         let verification_script = script! {
@@ -131,7 +132,7 @@ mod tests {
         let IOPair { input, output } = U254AddScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U254AddScript::default_split(input, SplitType::ByInstructions);
+        let split_result = U254AddScript::default_split(input, SplitType::ByInstructions).unwrap();
 
         // Checking the last state (which must be equal to the result of the multiplication)
         let last_state = split_result.must_last_state();
@@ -97,7 +97,7 @@ mod tests {
 
         // Splitting the script into shards
         let split_result =
-            SHA256ScriptType::default_split(input.clone(), SplitType::ByInstructions);
+            SHA256ScriptType::default_split(input.clone(), SplitType::ByInstructions).unwrap();
 
         // Now, we are going to concatenate all the shards and verify that the script is also correct
         let verification_script = script! {
@@ -126,7 +126,8 @@ mod tests {
         let IOPair { input, output } = SHA256ScriptType::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = SHA256ScriptType::default_split(input, SplitType::ByInstructions);
+        let split_result =
+            SHA256ScriptType::default_split(input, SplitType::ByInstructions).unwrap();
         println!("Split result: {:?}", split_result);
 
         // Checking the last state (which must be equal to the result of the multiplication)
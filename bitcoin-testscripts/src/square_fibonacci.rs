@@ -5,6 +5,7 @@
 use crate::bitvm::bn254::{fp254impl::Fp254Impl, fq::Fq};
 use bitcoin_splitter::split::{
     core::{form_states_from_shards, SplitType},
+    error::SplitError,
     script::{IOPair, SplitResult, SplitableScript},
 };
 use bitcoin_utils::treepp::*;
@@ -95,7 +96,13 @@ impl<const STEPS: usize> SplitableScript for SquareFibonacciScript<STEPS> {
         }
     }
 
-    fn default_split(input: Script, _split_type: SplitType) -> SplitResult {
+    fn markers() -> Vec<usize> {
+        // Cutting after each transition
+        let transition_len = Self::transition_script().instructions().count();
+        (1..=STEPS).map(|step| step * transition_len).collect()
+    }
+
+    fn default_split(input: Script, _split_type: SplitType) -> Result<SplitResult, SplitError> {
         // First, form shards
         let mut shards = vec![Self::transition_script(); STEPS + 1];
         // Change last one to be { OP_ROLL, OP_DROP } to remove the element before last.
@@ -108,10 +115,10 @@ impl<const STEPS: usize> SplitableScript for SquareFibonacciScript<STEPS> {
 
         // Secondly, form the intermediate states and return
         let intermediate_states = form_states_from_shards(shards.clone(), input);
-        SplitResult {
+        Ok(SplitResult {
             shards,
             intermediate_states,
-        }
+        })
     }
 }
 
@@ -139,7 +146,10 @@ pub(super) fn square_fq(x: &BigUint) -> BigUint {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bitcoin_splitter::split::core::SplitType;
+    use bitcoin_splitter::split::{
+        analysis::{stack_heights, StackHeight},
+        core::SplitType,
+    };
     use bitcoin_utils::{comparison::OP_LONGEQUALVERIFY, stack_to_script};
 
     #[test]
@@ -188,7 +198,8 @@ mod tests {
 
         // Splitting the script into shards
         let split_result =
-            SquareFibonacciScript::<16>::default_split(input.clone(), SplitType::ByInstructions);
+            SquareFibonacciScript::<16>::default_split(input.clone(), SplitType::ByInstructions)
+                .unwrap();
 
         // Now, we are going to concatenate all the shards and verify that the script is also correct
         let verification_script = script! {
@@ -218,7 +229,8 @@ mod tests {
         let IOPair { input, output } = FibonacciScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = FibonacciScript::default_split(input, SplitType::ByInstructions);
+        let split_result =
+            FibonacciScript::default_split(input, SplitType::ByInstructions).unwrap();
 
         for i in 0..split_result.len() {
             let shard_size = split_result.shards[i].len();
@@ -246,6 +258,103 @@ mod tests {
         assert!(result.success, "verification has failed");
     }
 
+    #[test]
+    fn test_split_by_markers() {
+        type FibonacciScript = SquareFibonacciScript<16>;
+
+        let IOPair { input, output } = FibonacciScript::generate_valid_io_pair();
+
+        // Splitting by markers gives exactly the transitions
+        let split_result = FibonacciScript::split(input.clone(), SplitType::ByMarkers, 0).unwrap();
+        let default_result =
+            FibonacciScript::default_split(input.clone(), SplitType::ByMarkers).unwrap();
+        assert_eq!(split_result.shards, default_result.shards);
+
+        // Packing four transitions into one shard
+        let transition_size = SquareFibonacciScript::<16>::transition_script().len();
+        let split_result =
+            FibonacciScript::split(input, SplitType::ByPackedMarkers, 4 * transition_size).unwrap();
+        assert_eq!(split_result.len(), 5, "unexpected number of shards");
+
+        // The element of the mainstack must be equal to the actual output
+        let last_state = split_result.must_last_state();
+        let verification_script = script! {
+            { stack_to_script(&last_state.stack) }
+            { output }
+            { Fq::equal(0, 1) }
+        };
+
+        let result = execute_script(verification_script);
+        assert!(result.success, "verification has failed");
+    }
+
+    #[test]
+    fn test_stack_heights_match_states() {
+        type FibonacciScript = SquareFibonacciScript<16>;
+
+        let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+        let split_result =
+            FibonacciScript::default_split(input, SplitType::ByInstructions).unwrap();
+
+        // Input consists of two field elements
+        let initial = StackHeight {
+            stack: 2 * Fq::N_LIMBS as usize,
+            altstack: 0,
+        };
+        let heights = stack_heights(&FibonacciScript::script(), initial).unwrap();
+
+        // Comparing the heights at the end of each shard with the states
+        let mut instructions_before = 0;
+        for (shard, state) in split_result
+            .shards
+            .iter()
+            .zip(split_result.intermediate_states.iter())
+        {
+            instructions_before += shard.instructions().count();
+            let height = heights[instructions_before].expect("height must be known");
+            assert_eq!(height.size(), state.size(), "height mismatch");
+        }
+        assert_eq!(instructions_before + 1, heights.len());
+    }
+
+    #[test]
+    fn test_optimal_split() {
+        type FibonacciScript = SquareFibonacciScript<128>;
+
+        // First, we generate the pair of input and output scripts
+        let IOPair { input, output } = FibonacciScript::generate_valid_io_pair();
+
+        // Splitting the script into shards
+        let split_result = FibonacciScript::optimal_split(input.clone()).unwrap();
+        let default_result =
+            FibonacciScript::default_split(input, SplitType::ByInstructions).unwrap();
+        println!(
+            "Optimal split: {} shards, complexity index is {}",
+            split_result.len(),
+            split_result.complexity_index()
+        );
+        assert!(
+            split_result.complexity_index() <= default_result.complexity_index(),
+            "optimal split is worse than the default one"
+        );
+
+        // Checking the last state (which must be equal to the result of the computation)
+        let last_state = split_result.must_last_state();
+
+        // Altstack must be empty
+        assert!(last_state.altstack.is_empty(), "altstack is not empty!");
+
+        // The element of the mainstack must be equal to the actual output
+        let verification_script = script! {
+            { stack_to_script(&last_state.stack) }
+            { output }
+            { Fq::equal(0, 1) }
+        };
+
+        let result = execute_script(verification_script);
+        assert!(result.success, "verification has failed");
+    }
+
     #[test]
     #[ignore = "too-large computation, run separately"]
     fn test_fuzzy_split() {
@@ -254,7 +363,7 @@ mod tests {
 
         // Splitting the script into shards
         let split_result =
-            SquareFibonacciScript::<1024>::fuzzy_split(input, SplitType::ByInstructions);
+            SquareFibonacciScript::<1024>::fuzzy_split(input, SplitType::ByInstructions).unwrap();
 
         for shard in split_result.shards.iter() {
             println!("Shard: {:?}", shard.len());
@@ -21,7 +21,7 @@ fn test_split_mul() {
     );
 
     // Splitting the script into shards
-    let shards = split_into_shards(&test_script, CHUNK_SIZE, SplitType::ByInstructions);
+    let shards = split_into_shards(&test_script, CHUNK_SIZE, SplitType::ByInstructions).unwrap();
 
     // Now, we are going to concatenate all the shards and verify that the script is also correct
     let verification_script = script! {
@@ -94,7 +94,8 @@ mod tests {
 
         // Splitting the script into shards
         let split_result =
-            U261MulKaratsubaScript::default_split(input.clone(), SplitType::ByInstructions);
+            U261MulKaratsubaScript::default_split(input.clone(), SplitType::ByInstructions)
+                .unwrap();
 
         // Now, we are going to concatenate all the shards and verify that the script is also correct
         let verification_script = script! {
@@ -119,7 +120,8 @@ mod tests {
         let IOPair { input, output } = U261MulKaratsubaScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U261MulKaratsubaScript::default_split(input, SplitType::ByInstructions);
+        let split_result =
+            U261MulKaratsubaScript::default_split(input, SplitType::ByInstructions).unwrap();
 
         for shard in split_result.shards.iter() {
             println!("Shard: {:?}", shard.len());
@@ -165,7 +167,8 @@ mod tests {
         let IOPair { input, output } = U261MulKaratsubaScript::generate_valid_io_pair();
 
         // Splitting the script into shards
-        let split_result = U261MulKaratsubaScript::fuzzy_split(input, SplitType::ByInstructions);
+        let split_result =
+            U261MulKaratsubaScript::fuzzy_split(input, SplitType::ByInstructions).unwrap();
 
         for shard in split_result.shards.iter() {
             println!("Shard: {:?}", shard.len());
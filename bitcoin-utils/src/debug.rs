@@ -1,5 +1,6 @@
 use crate::treepp;
 use bitcoin::{hashes::Hash, ScriptBuf, TapLeafHash, Transaction};
+use bitcoin_scriptexec::profile::{ExecProfile, Region};
 use bitcoin_scriptexec::{Exec, ExecCtx, ExecError, ExecStats, Options, Stack, TxTemplate};
 use core::fmt;
 
@@ -42,10 +43,9 @@ impl fmt::Display for ExecuteInfo {
     }
 }
 
-/// Executes the given script and returns the result of the execution
-/// (success, error, stack, etc.)
-pub fn execute_script(script: ScriptBuf) -> ExecuteInfo {
-    let mut exec = Exec::new(
+/// Creates the execution of the script as [`execute_script`] runs it
+fn new_exec(script: ScriptBuf) -> Exec {
+    Exec::new(
         ExecCtx::Tapscript,
         Options {
             // TODO(ZamDimon): Figure our how to optimize stack_to_script function to avoid disabling require_minimal
@@ -68,16 +68,11 @@ pub fn execute_script(script: ScriptBuf) -> ExecuteInfo {
         script,
         vec![],
     )
-    .expect("error when creating the execution body");
-
-    // Execute all the opcodes while possible
-    loop {
-        if exec.exec_next().is_err() {
-            break;
-        }
-    }
+    .expect("error when creating the execution body")
+}
 
-    // Obtaining the result of the execution
+/// Forms the [`ExecuteInfo`] of the finished execution
+fn execute_info(exec: &Exec) -> ExecuteInfo {
     let result = exec.result().unwrap();
 
     ExecuteInfo {
@@ -89,6 +84,55 @@ pub fn execute_script(script: ScriptBuf) -> ExecuteInfo {
     }
 }
 
+/// Executes the given script and returns the result of the execution
+/// (success, error, stack, etc.)
+pub fn execute_script(script: ScriptBuf) -> ExecuteInfo {
+    let mut exec = new_exec(script);
+
+    // Execute all the opcodes while possible
+    loop {
+        if exec.exec_next().is_err() {
+            break;
+        }
+    }
+
+    execute_info(&exec)
+}
+
+/// Executes the given script as [`execute_script`] does, profiling the
+/// execution. The costs are attributed to the labelled `regions` (byte
+/// ranges of the script, possibly nested). Use [`ExecProfile::to_json`] and
+/// [`ExecProfile::folded`] to export the profile.
+pub fn profile_script_with_regions(
+    script: ScriptBuf,
+    regions: Vec<Region>,
+) -> (ExecuteInfo, ExecProfile) {
+    let mut exec = new_exec(script);
+    let profile = ExecProfile::run(&mut exec, regions);
+
+    (execute_info(&exec), profile)
+}
+
+/// Executes the script concatenated from the labelled parts, profiling the
+/// execution with a region per part, e.g.:
+///
+/// ```ignore
+/// let (info, profile) = profile_script(&[
+///     ("input", &input),
+///     ("mul", &U29x9::mul_karazuba()),
+/// ]);
+/// std::fs::write("mul.folded", profile.folded()).unwrap();
+/// ```
+pub fn profile_script(parts: &[(&str, &treepp::Script)]) -> (ExecuteInfo, ExecProfile) {
+    let (script, regions) = Region::concat(
+        parts
+            .iter()
+            .map(|(label, script)| (*label, script.as_script())),
+    );
+
+    profile_script_with_regions(script, regions)
+}
+
 pub fn run(script: bitcoin::ScriptBuf) {
     let exec_result = execute_script(script);
     if !exec_result.success {
@@ -164,7 +208,7 @@ pub fn run_and_assert(script: treepp::Script) {
 
 #[cfg(test)]
 mod test {
-    use super::execute_script_no_stack_limit;
+    use super::{execute_script_no_stack_limit, profile_script};
     use crate::treepp::*;
 
     #[test]
@@ -179,6 +223,19 @@ mod test {
         assert!(!exec_result.success);
     }
 
+    #[test]
+    fn test_profile_script() {
+        let input = script! { 5 6 };
+        let sum = script! { OP_ADD 11 OP_EQUAL };
+        let (info, profile) = profile_script(&[("input", &input), ("sum", &sum)]);
+        assert!(info.success);
+        assert_eq!(profile.total.steps, 5);
+        assert_eq!(profile.regions[1].region.label, "sum");
+        assert_eq!(profile.regions[1].costs.steps, 3);
+        assert_eq!(profile.regions[1].costs.peak_stack_items, 2);
+        assert!(profile.folded().contains("sum;OP_ADD 1\n"));
+    }
+
     #[test]
     fn test_script_execute_no_stack_limit() {
         let script = script! {
@@ -25,6 +25,9 @@ pub const N1: usize = ((D * N0).ilog(D + 1) + 1) as usize;
 /// The total number of partitions.
 pub const N: usize = N0 + N1;
 
+/// The number of witness elements pushed by [`Signature::to_script_sig`].
+pub const SCRIPT_SIG_ELEMENTS: usize = 2 * N;
+
 /// Secret key is array of $N$ chunks by $D$ bits, where the whole number
 /// of bits is equal to $v$.
 #[derive(Clone, Debug, Copy)]
@@ -94,7 +97,8 @@ impl SecretKey {
 
 /// Public key is a hashed $D$ times each of the $n$ parts of the
 /// [`SecretKey`].
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PublicKey([Hash160; N]);
 
 impl PublicKey {
@@ -123,7 +127,7 @@ impl PublicKey {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Message([u8; N]);
 
 impl Message {
@@ -250,6 +254,41 @@ pub struct Signature {
 }
 
 impl Signature {
+    /// Return the signed message.
+    pub const fn message(&self) -> Message {
+        self.msg
+    }
+
+    /// Parse signature from [`SCRIPT_SIG_ELEMENTS`] witness elements pushed
+    /// by [`Self::to_script_sig`], where the first element is the deepest
+    /// one on the stack.
+    ///
+    /// Returns `None` if number of elements, signature parts or limbs are
+    /// malformed. Note, that signature itself is not verified.
+    pub fn from_script_sig_elements(elements: &[Vec<u8>]) -> Option<Self> {
+        if elements.len() != SCRIPT_SIG_ELEMENTS {
+            return None;
+        }
+
+        let mut sig = [Hash160::all_zeros(); N];
+        let mut msg = [0u8; N];
+
+        let indexes = (N0..(N0 + N1)).rev().chain((0..N0).rev());
+        for (idx, pair) in indexes.zip(elements.chunks(2)) {
+            sig[idx] = Hash160::from_slice(&pair[0]).ok()?;
+            msg[idx] = match pair[1].as_slice() {
+                [] => 0,
+                [limb] if *limb as usize <= D => *limb,
+                _ => return None,
+            };
+        }
+
+        Some(Self {
+            sig,
+            msg: Message(msg),
+        })
+    }
+
     /// Creates bitcoin script with pushed to stack pairs of signature and
     /// number of times it was hashed.
     pub fn to_script_sig(self) -> Script {
@@ -284,6 +323,92 @@ impl Signature {
     }
 }
 
+/// Evidence that the owner of the [`PublicKey`] signed two different
+/// messages with it.
+///
+/// As each digit of the message is signed by revealing the hash chain
+/// element, signing a lower digit reveals the preimage of the element
+/// revealed for higher one. See [`Equivocation::leaked_preimages`].
+#[derive(Clone, Copy, Debug)]
+pub struct Equivocation {
+    public_key: PublicKey,
+    first: Signature,
+    second: Signature,
+}
+
+/// Hash chain element, which was not supposed to be revealed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeakedPreimage {
+    /// Index of the digit (including checksum ones).
+    pub digit: usize,
+    /// Number of times the secret key part was hashed to get `preimage`.
+    pub hashed_times: u8,
+    pub preimage: Hash160,
+}
+
+impl Equivocation {
+    /// Check that both `(message, signature)` pairs are valid for
+    /// `public_key`, but messages are different.
+    ///
+    /// Returns `None` if there is no equivocation.
+    pub fn detect(
+        public_key: &PublicKey,
+        (first_msg, first_sig): (&Message, &Signature),
+        (second_msg, second_sig): (&Message, &Signature),
+    ) -> Option<Self> {
+        if first_msg == second_msg
+            || !public_key.verify(first_msg, first_sig)
+            || !public_key.verify(second_msg, second_sig)
+        {
+            return None;
+        }
+
+        Some(Self {
+            public_key: *public_key,
+            first: Signature {
+                sig: first_sig.sig,
+                msg: *first_msg,
+            },
+            second: Signature {
+                sig: second_sig.sig,
+                msg: *second_msg,
+            },
+        })
+    }
+
+    pub const fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Both conflicting signatures.
+    pub const fn signatures(&self) -> (&Signature, &Signature) {
+        (&self.first, &self.second)
+    }
+
+    /// Return hash chain elements, which were revealed by one signature
+    /// for digits signed with the higher value in the other.
+    ///
+    /// As the checksum prevents all digits from growing, it's never empty.
+    pub fn leaked_preimages(&self) -> Vec<LeakedPreimage> {
+        (0..N)
+            .filter(|&idx| self.first.msg.0[idx] != self.second.msg.0[idx])
+            .map(|idx| {
+                let lower = if self.first.msg.0[idx] < self.second.msg.0[idx] {
+                    &self.first
+                } else {
+                    &self.second
+                };
+
+                LeakedPreimage {
+                    digit: idx,
+                    hashed_times: lower.msg.0[idx],
+                    preimage: lower.sig[idx],
+                }
+            })
+            .collect()
+    }
+}
+
 /// Returns the script which verifies the Winternitz signature (see
 /// [`Signature`]) from top of the stack.
 pub fn checksig_verify_script(public_key: &PublicKey) -> Script {
@@ -467,6 +592,78 @@ mod tests {
             assert!(public_key.verify(&message, &signature));
         }
 
+        #[test]
+        fn test_equivocation_detected() {
+            let secret_key = SecretKey::from_seed::<_, SmallRng>([2u8; 32]);
+            let public_key = secret_key.public_key();
+
+            let first_msg = Message::from_u32(0x12345678);
+            let second_msg = Message::from_u32(0x12345679);
+            let first_sig = secret_key.sign(first_msg);
+            let second_sig = secret_key.sign(second_msg);
+
+            assert!(Equivocation::detect(
+                &public_key,
+                (&first_msg, &first_sig),
+                (&first_msg, &first_sig)
+            )
+            .is_none());
+            assert!(Equivocation::detect(
+                &public_key,
+                (&first_msg, &first_sig),
+                (&first_msg, &second_sig)
+            )
+            .is_none());
+
+            let equivocation = Equivocation::detect(
+                &public_key,
+                (&first_msg, &first_sig),
+                (&second_msg, &second_sig),
+            )
+            .unwrap();
+
+            let leaked = equivocation.leaked_preimages();
+            assert!(!leaked.is_empty());
+            for LeakedPreimage {
+                digit,
+                hashed_times,
+                preimage,
+            } in leaked
+            {
+                let mut hash = preimage;
+                for _ in 0..(D - hashed_times as usize) {
+                    hash = Hash160::hash(hash.to_byte_array().as_slice());
+                }
+                assert_eq!(hash, public_key.0[digit]);
+            }
+        }
+
+        #[test]
+        fn test_signature_parsed_from_script_sig() {
+            use bitcoin::{opcodes::all::OP_PUSHNUM_1, script::Instruction};
+
+            const MSG: u32 = 0x2FEEDDC0;
+
+            let secret_key = SecretKey::from_seed::<_, SmallRng>([1u8; 32]);
+            let public_key = secret_key.public_key();
+            let signature = secret_key.sign(Message::from_u32(MSG));
+
+            let elements = signature
+                .to_script_sig()
+                .instructions()
+                .map(|instruction| match instruction.unwrap() {
+                    Instruction::PushBytes(bytes) => bytes.as_bytes().to_vec(),
+                    Instruction::Op(op) => vec![op.to_u8() - OP_PUSHNUM_1.to_u8() + 1],
+                })
+                .collect::<Vec<_>>();
+
+            let parsed = Signature::from_script_sig_elements(&elements).unwrap();
+
+            assert_eq!(parsed.message().into_u32(), MSG);
+            assert!(public_key.verify(&parsed.message(), &parsed));
+            assert!(Signature::from_script_sig_elements(&elements[1..]).is_none());
+        }
+
         #[test]
         fn test_signature_verification_in_script_works() {
             const MSG: u32 = 0x2FEEDDCC;
@@ -0,0 +1,380 @@
+//! Commitment of the signed intermediate states in the witness of the
+//! **Assert** transaction.
+//!
+//! The operator publishes all Winternitz signatures of the states (z values)
+//! in the Assert inputs, so challengers can take them from the confirmed
+//! transaction and reuse in the witness of disprove scripts. As the number
+//! of elements is limited by the stack size, the states are split across
+//! several inputs, each spending an output with its own commitment leaf.
+
+use bitcoin::{
+    absolute::LockTime,
+    key::{Keypair, Secp256k1},
+    secp256k1::{All, SecretKey},
+    sighash::{Prevouts, SighashCache},
+    taproot::{LeafVersion, TaprootBuilder, TaprootSpendInfo},
+    transaction::Version,
+    Amount, OutPoint, Sequence, TapSighashType, Transaction, TxIn, TxOut, Witness, XOnlyPublicKey,
+};
+use bitcoin_splitter::split::intermediate_state::{IntermediateState, IntermediateStateAsBytes};
+use bitcoin_winternitz::u32::{
+    checksig_verify_script, Message, PublicKey, Signature, SCRIPT_SIG_ELEMENTS,
+};
+
+use crate::{
+    disprove::{signing::SignedIntermediateState, witness_elements},
+    treepp::*,
+};
+
+/// Maximum number of signed elements committed in one input. Each element
+/// takes [`SCRIPT_SIG_ELEMENTS`] witness elements, and its verification
+/// needs additional `D+1` stack elements, so the whole witness fits into
+/// the stack limit of 1000 elements.
+pub const MAX_ELEMENTS_PER_INPUT: usize = 45;
+
+/// Number of stack and altstack elements of the committed state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateLayout {
+    pub stack: usize,
+    pub altstack: usize,
+}
+
+impl StateLayout {
+    pub fn len(&self) -> usize {
+        self.stack + self.altstack
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Public part of the states commitment, which is known to both operator
+/// and challengers.
+#[derive(Debug, Clone)]
+pub struct StatesCommitment {
+    operator_pubkey: XOnlyPublicKey,
+    layouts: Vec<StateLayout>,
+    /// Public keys of all elements of all states in order: state by state,
+    /// stack elements first, then altstack ones.
+    pubkeys: Vec<PublicKey>,
+}
+
+impl StatesCommitment {
+    pub fn new(operator_pubkey: XOnlyPublicKey, states: &[SignedIntermediateState]) -> Self {
+        let layouts = states
+            .iter()
+            .map(|state| StateLayout {
+                stack: state.stack.len(),
+                altstack: state.altstack.len(),
+            })
+            .collect();
+
+        let pubkeys = states
+            .iter()
+            .flat_map(|state| state.stack.iter().chain(state.altstack.iter()))
+            .map(|element| element.public_key)
+            .collect();
+
+        Self {
+            operator_pubkey,
+            layouts,
+            pubkeys,
+        }
+    }
+
+    /// Public key of the operator, who signs the commitment inputs.
+    pub fn operator_pubkey(&self) -> XOnlyPublicKey {
+        self.operator_pubkey
+    }
+
+    /// Layouts of the committed states.
+    pub fn layouts(&self) -> &[StateLayout] {
+        &self.layouts
+    }
+
+    /// Number of Assert inputs required to commit all the states.
+    pub fn inputs_num(&self) -> usize {
+        self.pubkeys.len().div_ceil(MAX_ELEMENTS_PER_INPUT)
+    }
+
+    /// Leaf scripts of the commitment outputs, one per each Assert input.
+    ///
+    /// ## Witness:
+    /// ```bitcoin_script
+    /// { Sig(e[0]) }
+    /// { Sig(e[1]) }
+    /// ...
+    /// { Sig(e[k]) }
+    /// { operator_signature }
+    /// ```
+    pub fn scripts(&self) -> Vec<Script> {
+        self.pubkeys
+            .chunks(MAX_ELEMENTS_PER_INPUT)
+            .map(|pubkeys| {
+                script! {
+                    { self.operator_pubkey.serialize().to_vec() }
+                    OP_CHECKSIGVERIFY
+                    for pubkey in pubkeys.iter().rev() {
+                        { checksig_verify_script(pubkey) }
+                        { Message::recovery_script() }
+                        OP_DROP
+                    }
+                    OP_TRUE
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn taptrees(
+        &self,
+        ctx: &Secp256k1<All>,
+        internal_key: XOnlyPublicKey,
+    ) -> Vec<TaprootSpendInfo> {
+        self.scripts()
+            .into_iter()
+            .map(|script| {
+                TaprootBuilder::new()
+                    .add_leaf(0, script)
+                    .expect("single leaf is always valid")
+                    .finalize(ctx, internal_key)
+                    .expect("Scripts and keys should be valid")
+            })
+            .collect()
+    }
+
+    /// Outputs, which should be spent by the Assert inputs.
+    pub fn txouts(
+        &self,
+        ctx: &Secp256k1<All>,
+        internal_key: XOnlyPublicKey,
+        amount: Amount,
+    ) -> Vec<TxOut> {
+        self.taptrees(ctx, internal_key)
+            .into_iter()
+            .map(|taptree| TxOut {
+                value: amount,
+                script_pubkey: Script::new_p2tr_tweaked(taptree.output_key()),
+            })
+            .collect()
+    }
+
+    /// Create transaction spending commitment outputs `prevouts` (in the
+    /// same order as [`Self::txouts`]) with the signed `states` published
+    /// in the witness.
+    pub fn spend_tx(
+        &self,
+        ctx: &Secp256k1<All>,
+        internal_key: XOnlyPublicKey,
+        states: &[SignedIntermediateState],
+        operator_seckey: &SecretKey,
+        prevouts: Vec<(OutPoint, TxOut)>,
+        output: TxOut,
+    ) -> eyre::Result<Transaction> {
+        eyre::ensure!(
+            prevouts.len() == self.inputs_num(),
+            "expected {} commitment outputs, got {}",
+            self.inputs_num(),
+            prevouts.len()
+        );
+
+        let inputs_elements = self.inputs_elements(states)?;
+
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: prevouts
+                .iter()
+                .map(|(outpoint, _)| TxIn {
+                    previous_output: *outpoint,
+                    script_sig: Script::new(),
+                    sequence: Sequence::ZERO,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![output],
+        };
+
+        let prev_txouts = prevouts
+            .into_iter()
+            .map(|(_, txout)| txout)
+            .collect::<Vec<_>>();
+        let keypair = Keypair::from_secret_key(ctx, operator_seckey);
+        let scripts = self.scripts();
+        let taptrees = self.taptrees(ctx, internal_key);
+
+        let mut cache = SighashCache::new(tx.clone());
+        for (idx, ((script, taptree), elements)) in scripts
+            .into_iter()
+            .zip(taptrees)
+            .zip(inputs_elements)
+            .enumerate()
+        {
+            let sighash = cache.taproot_script_spend_signature_hash(
+                idx,
+                &Prevouts::All(&prev_txouts),
+                script.tapscript_leaf_hash(),
+                TapSighashType::Default,
+            )?;
+            let operator_signature = ctx.sign_schnorr(&sighash.into(), &keypair);
+
+            let control_block = taptree
+                .control_block(&(script.clone(), LeafVersion::TapScript))
+                .expect("leaf should be in the taptree");
+
+            let mut witness = Witness::new();
+            for elem in elements {
+                witness.push(elem);
+            }
+            witness.push(&operator_signature[..]);
+            witness.push(script.as_bytes());
+            witness.push(control_block.serialize());
+
+            tx.input[idx].witness = witness;
+        }
+
+        Ok(tx)
+    }
+
+    /// Witness elements of the signed `states` in each commitment input,
+    /// which are followed by the operator signature in its witness.
+    pub(crate) fn inputs_elements(
+        &self,
+        states: &[SignedIntermediateState],
+    ) -> eyre::Result<Vec<Vec<Vec<u8>>>> {
+        let signatures = states
+            .iter()
+            .flat_map(|state| state.stack.iter().chain(state.altstack.iter()))
+            .map(|element| element.signature)
+            .collect::<Vec<_>>();
+        eyre::ensure!(
+            signatures.len() == self.pubkeys.len(),
+            "states don't match the commitment"
+        );
+
+        let inputs_elements = signatures
+            .chunks(MAX_ELEMENTS_PER_INPUT)
+            .map(|signatures| {
+                signatures
+                    .iter()
+                    .flat_map(|signature| witness_elements(&signature.to_script_sig()))
+                    .collect()
+            })
+            .collect();
+
+        Ok(inputs_elements)
+    }
+
+    /// Parse the committed states from the witness of the confirmed
+    /// Assert transaction, verifying each signature with the committed
+    /// public key. The commitment inputs should be the first ones.
+    pub fn parse(&self, tx: &Transaction) -> eyre::Result<Vec<CommittedState>> {
+        self.parse_inputs(&tx.input)
+    }
+
+    /// The same as [`Self::parse`], but the states are parsed from the
+    /// commitment `inputs` in the same order as [`Self::txouts`], which
+    /// may be only a part of the transaction inputs.
+    pub fn parse_inputs(&self, inputs: &[TxIn]) -> eyre::Result<Vec<CommittedState>> {
+        eyre::ensure!(
+            inputs.len() >= self.inputs_num(),
+            "expected at least {} inputs, got {}",
+            self.inputs_num(),
+            inputs.len()
+        );
+
+        let mut signatures = Vec::with_capacity(self.pubkeys.len());
+        for (idx, (input, pubkeys)) in inputs
+            .iter()
+            .zip(self.pubkeys.chunks(MAX_ELEMENTS_PER_INPUT))
+            .enumerate()
+        {
+            let elements = input.witness.iter().map(<[u8]>::to_vec).collect::<Vec<_>>();
+            let signatures_len = pubkeys.len() * SCRIPT_SIG_ELEMENTS;
+            // Signatures are followed by operator signature, script and
+            // control block.
+            eyre::ensure!(
+                elements.len() == signatures_len + 3,
+                "unexpected number of witness elements in input {idx}"
+            );
+
+            for (elements, pubkey) in elements[..signatures_len]
+                .chunks(SCRIPT_SIG_ELEMENTS)
+                .zip(pubkeys)
+            {
+                let signature = Signature::from_script_sig_elements(elements)
+                    .ok_or_else(|| eyre::eyre!("malformed signature in input {idx}"))?;
+                eyre::ensure!(
+                    pubkey.verify(&signature.message(), &signature),
+                    "invalid signature in input {idx}"
+                );
+
+                signatures.push(signature);
+            }
+        }
+
+        let mut signatures = signatures.into_iter();
+        let states = self
+            .layouts
+            .iter()
+            .map(|layout| CommittedState {
+                stack: signatures.by_ref().take(layout.stack).collect(),
+                altstack: signatures.by_ref().take(layout.altstack).collect(),
+            })
+            .collect();
+
+        Ok(states)
+    }
+}
+
+/// State parsed from the Assert transaction witness.
+#[derive(Debug, Clone)]
+pub struct CommittedState {
+    pub stack: Vec<Signature>,
+    pub altstack: Vec<Signature>,
+}
+
+impl CommittedState {
+    /// The same as [`SignedState::witness_script`] of the signed state, so
+    /// it can be used in the witness of disprove script.
+    ///
+    /// [`SignedState::witness_script`]: crate::disprove::signing::SignedState::witness_script
+    pub fn witness_script(&self) -> Script {
+        script! {
+            for signature in self.stack.iter() {
+                { signature.to_script_sig() }
+            }
+            for signature in self.altstack.iter().rev() {
+                { signature.to_script_sig() }
+            }
+        }
+    }
+
+    /// Values of the committed state. The state with byte strings is
+    /// committed in its unpacked form (see [`crate::disprove::byte_strings`]),
+    /// so that is what is returned for it.
+    pub fn to_intermediate_state(&self) -> IntermediateState {
+        let bytes = |signatures: &[Signature]| {
+            signatures
+                .iter()
+                .flat_map(|signature| signature.message().into_u32().to_le_bytes())
+                .collect()
+        };
+
+        let state = IntermediateStateAsBytes {
+            stack: bytes(&self.stack),
+            altstack: bytes(&self.altstack),
+        };
+
+        IntermediateState::from_inject_script(&state.inject_script())
+    }
+}
+
+/// Form the witness of the disprove script from the committed states, so
+/// challengers don't need to know the signatures in advance.
+pub fn disprove_witness(from: &CommittedState, to: &CommittedState) -> Script {
+    script! {
+        { from.witness_script() }
+        { to.witness_script() }
+    }
+}
@@ -6,26 +6,40 @@ use bitcoin::{
     key::{Keypair, Secp256k1},
     psbt::Input,
     relative::Height,
-    secp256k1::{All, SecretKey},
+    secp256k1::{schnorr, All, SecretKey},
     sighash::{Prevouts, SighashCache},
     taproot::{LeafVersion, TaprootBuilder, TaprootSpendInfo},
     transaction::Version,
-    Amount, EcdsaSighashType, OutPoint, Psbt, Sequence, TapSighashType, Transaction, TxIn, TxOut,
-    Witness, XOnlyPublicKey,
+    Amount, EcdsaSighashType, OutPoint, Psbt, Sequence, TapSighash, TapSighashType, Transaction,
+    TxIn, TxOut, Witness, XOnlyPublicKey,
 };
 use bitcoin_splitter::split::script::SplitableScript;
+use bitcoin_winternitz::u32::Equivocation;
+use eyre::OptionExt as _;
 
 use crate::{
-    assert::payout_script::PayoutScript, disprove::form_disprove_scripts_distorted_with_seed,
-    treepp::*, UNSPENDABLE_KEY,
+    assert::payout_script::PayoutScript,
+    committee::presigned_leaf,
+    disprove::{
+        derivation::KeyDerivation, form_disprove_scripts_derived,
+        form_disprove_scripts_distorted_by, form_disprove_scripts_distorted_with_seed, Distortion,
+    },
+    treepp::*,
+    UNSPENDABLE_KEY,
 };
 
-use crate::disprove::{form_disprove_scripts, DisproveScript};
+use crate::disprove::{
+    equivocation::EquivocationScript, signing::SignedIntermediateState, DisproveScript,
+};
+
+use self::commitment::StatesCommitment;
 
+pub mod commitment;
 pub mod payout_script;
 
 const DISPROVE_SCRIPT_WEIGHT: u32 = 1;
 const PAYOUT_SCRIPT_WEIGHT: u32 = 5;
+const EQUIVOCATION_SCRIPT_WEIGHT: u32 = 1;
 
 pub struct AssertTransaction<S: SplitableScript> {
     /// Operator's public key.
@@ -37,6 +51,13 @@ pub struct AssertTransaction<S: SplitableScript> {
     pub disprove_scripts: Vec<DisproveScript>,
     pub payout_script: PayoutScript,
 
+    /// Leaves spendable on proof of double-signing of the state elements by
+    /// anyone who detects it first, see [`crate::disprove::equivocation`].
+    pub equivocation_scripts: Vec<EquivocationScript>,
+
+    /// Internal key of the taproot output, see [`crate::nums`].
+    pub internal_key: XOnlyPublicKey,
+
     /// Program this transaction asserts.
     __program: PhantomData<S>,
 }
@@ -48,6 +69,8 @@ impl<S: SplitableScript> Clone for AssertTransaction<S> {
             amount: self.amount,
             disprove_scripts: self.disprove_scripts.clone(),
             payout_script: self.payout_script.clone(),
+            equivocation_scripts: self.equivocation_scripts.clone(),
+            internal_key: self.internal_key,
             __program: self.__program,
         }
     }
@@ -55,12 +78,35 @@ impl<S: SplitableScript> Clone for AssertTransaction<S> {
 
 pub struct Options {
     pub payout_locktime: Height,
+
+    /// Aggregated key of the committee which presigns Payout and Disprove
+    /// transactions. See [`crate::committee`].
+    pub committee_pubkey: Option<XOnlyPublicKey>,
+
+    /// Internal key of the taproot output, default is [`UNSPENDABLE_KEY`].
+    /// Use [`crate::nums::NumsKey`] to make it unique per instance.
+    pub internal_key: XOnlyPublicKey,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             payout_locktime: payout_script::LOCKTIME.into(),
+            committee_pubkey: None,
+            internal_key: *UNSPENDABLE_KEY,
+        }
+    }
+}
+
+impl Options {
+    fn payout_script(&self, operator_pubkey: XOnlyPublicKey) -> PayoutScript {
+        match self.committee_pubkey {
+            Some(committee_pubkey) => PayoutScript::with_committee(
+                operator_pubkey,
+                committee_pubkey,
+                self.payout_locktime,
+            ),
+            None => PayoutScript::with_locktime(operator_pubkey, self.payout_locktime),
         }
     }
 }
@@ -71,19 +117,37 @@ impl<S: SplitableScript> AssertTransaction<S> {
         payout: PayoutScript,
         disprove_scripts: Vec<DisproveScript>,
         amount: Amount,
+        internal_key: XOnlyPublicKey,
     ) -> Self {
         Self {
             operator_pubkey,
             amount,
             disprove_scripts,
             payout_script: payout,
+            equivocation_scripts: Vec::new(),
+            internal_key,
             __program: PhantomData,
         }
     }
 
-    /// Construct new Assert transaction.
-    pub fn new(input: Script, operator_pubkey: XOnlyPublicKey, amount: Amount) -> Self {
-        Self::with_options(input, operator_pubkey, amount, Default::default())
+    /// Construct new Assert transaction, where the Winternitz keys of the
+    /// intermediate states are derived from `derivation`.
+    ///
+    /// Fails if the disprove script of some shard would be invalid, see
+    /// [`check_disprove_costs`](crate::disprove::check_disprove_costs).
+    pub fn new(
+        input: Script,
+        operator_pubkey: XOnlyPublicKey,
+        amount: Amount,
+        derivation: &KeyDerivation,
+    ) -> eyre::Result<Self> {
+        Self::with_options(
+            input,
+            operator_pubkey,
+            amount,
+            Default::default(),
+            derivation,
+        )
     }
 
     pub fn with_options(
@@ -91,18 +155,24 @@ impl<S: SplitableScript> AssertTransaction<S> {
         operator_pubkey: XOnlyPublicKey,
         amount: Amount,
         options: Options,
-    ) -> Self {
-        let disprove_scripts = form_disprove_scripts::<S>(input.clone());
-        let payout_script = PayoutScript::with_locktime(operator_pubkey, options.payout_locktime);
-        Self {
+        derivation: &KeyDerivation,
+    ) -> eyre::Result<Self> {
+        let disprove_scripts = form_disprove_scripts_derived::<S>(input, derivation)?;
+        let payout_script = options.payout_script(operator_pubkey);
+        Ok(Self {
             operator_pubkey,
             amount,
             disprove_scripts,
             payout_script,
+            equivocation_scripts: Vec::new(),
+            internal_key: options.internal_key,
             __program: PhantomData,
-        }
+        })
     }
 
+    /// Construct new Assert transaction with the random intermediate state
+    /// distorted, see [`form_disprove_scripts_distorted_with_seed`]. Fails
+    /// if the script cannot be split.
     pub fn with_options_distorted<
         Seed: Sized + Default + AsMut<[u8]> + Copy,
         Rng: rand::SeedableRng<Seed = Seed> + rand::Rng,
@@ -112,20 +182,60 @@ impl<S: SplitableScript> AssertTransaction<S> {
         amount: Amount,
         options: Options,
         seed: Seed,
-    ) -> (Self, usize) {
+    ) -> eyre::Result<(Self, usize)> {
         let (disprove_scripts, idx) =
-            form_disprove_scripts_distorted_with_seed::<S, Seed, Rng>(input.clone(), seed);
-        let payout_script = PayoutScript::with_locktime(operator_pubkey, options.payout_locktime);
-        (
+            form_disprove_scripts_distorted_with_seed::<S, Seed, Rng>(input.clone(), seed)?;
+        let payout_script = options.payout_script(operator_pubkey);
+
+        Ok((
             Self {
                 operator_pubkey,
                 amount,
                 disprove_scripts,
                 payout_script,
+                equivocation_scripts: Vec::new(),
+                internal_key: options.internal_key,
                 __program: PhantomData,
             },
             idx,
-        )
+        ))
+    }
+
+    /// The same as [`Self::with_options_distorted`], but the distorted
+    /// shard is chosen by `distortion` and the keys are derived from
+    /// `derivation`. Fails if the shard index is out of bounds or the
+    /// disprove script of some shard would be invalid.
+    pub fn with_options_distorted_by(
+        input: Script,
+        operator_pubkey: XOnlyPublicKey,
+        amount: Amount,
+        options: Options,
+        distortion: Distortion,
+        derivation: &KeyDerivation,
+    ) -> eyre::Result<(Self, usize)> {
+        let (disprove_scripts, idx) =
+            form_disprove_scripts_distorted_by::<S>(input, distortion, derivation)?;
+        let payout_script = options.payout_script(operator_pubkey);
+
+        Ok((
+            Self {
+                operator_pubkey,
+                amount,
+                disprove_scripts,
+                payout_script,
+                equivocation_scripts: Vec::new(),
+                internal_key: options.internal_key,
+                __program: PhantomData,
+            },
+            idx,
+        ))
+    }
+
+    /// Add leaves spendable on proof of double-signing of the state
+    /// elements, see [`EquivocationScript::from_signed_states`].
+    pub fn with_equivocation_scripts(mut self, scripts: Vec<EquivocationScript>) -> Self {
+        self.equivocation_scripts = scripts;
+        self
     }
 
     /// Return partially signed transaction with P2TR output with all disprove
@@ -145,12 +255,22 @@ impl<S: SplitableScript> AssertTransaction<S> {
     }
 
     pub fn txout(&self, ctx: &Secp256k1<All>) -> TxOut {
-        let taptree =
-            Self::form_taptree(ctx, self.payout_script.to_script(), &self.disprove_scripts);
+        let taptree = self.taptree(ctx);
 
         self.assert_taproot_output(&taptree)
     }
 
+    pub(crate) fn taptree(&self, ctx: &Secp256k1<All>) -> TaprootSpendInfo {
+        Self::form_taptree(
+            ctx,
+            self.internal_key,
+            self.payout_script.to_script(),
+            &self.disprove_scripts,
+            &self.equivocation_scripts,
+            self.payout_script.committee_pubkey.as_ref(),
+        )
+    }
+
     fn assert_taproot_output(&self, taptree: &TaprootSpendInfo) -> TxOut {
         let script_pubkey = Script::new_p2tr_tweaked(taptree.output_key());
 
@@ -160,25 +280,44 @@ impl<S: SplitableScript> AssertTransaction<S> {
         }
     }
 
+    /// Form the taptree of the Assert output with `internal_key`, which
+    /// should be unspendable (see [`crate::nums`]). If `committee_pubkey` is
+    /// provided, each disprove leaf additionally requires the committee
+    /// signature (see [`presigned_leaf`]). Equivocation leaves are never
+    /// guarded, so anyone can spend them (see
+    /// [`crate::disprove::equivocation`]).
     pub fn form_taptree(
         ctx: &Secp256k1<All>,
+        internal_key: XOnlyPublicKey,
         payout_script: Script,
         disprove_scripts: &[DisproveScript],
+        equivocation_scripts: &[EquivocationScript],
+        committee_pubkey: Option<&XOnlyPublicKey>,
     ) -> TaprootSpendInfo {
-        let scripts_with_weights = iter::once((PAYOUT_SCRIPT_WEIGHT, payout_script)).chain(
-            disprove_scripts
-                .iter()
-                .map(|script| (DISPROVE_SCRIPT_WEIGHT, script.script_pubkey.clone())),
-        );
+        let scripts_with_weights = iter::once((PAYOUT_SCRIPT_WEIGHT, payout_script))
+            .chain(disprove_scripts.iter().map(|script| {
+                (
+                    DISPROVE_SCRIPT_WEIGHT,
+                    disprove_leaf(script, committee_pubkey),
+                )
+            }))
+            .chain(
+                equivocation_scripts
+                    .iter()
+                    .map(|script| (EQUIVOCATION_SCRIPT_WEIGHT, script.script_pubkey.clone())),
+            );
 
         TaprootBuilder::with_huffman_tree(scripts_with_weights)
             .expect("Weights are low, and number of scripts shoudn't create the tree greater than 128 in depth (I believe)")
-            .finalize(ctx, *UNSPENDABLE_KEY)
+            .finalize(ctx, internal_key)
             .expect("Scripts and keys should be valid")
     }
 
     /// Create Payout transaction which spends first output of Assert
     /// transaction using Payout script path.
+    ///
+    /// Fails if the payout script requires the committee signature, use
+    /// [`Self::payout_transaction_presigned`] in that case.
     pub fn payout_transaction(
         &self,
         ctx: &Secp256k1<All>,
@@ -186,12 +325,60 @@ impl<S: SplitableScript> AssertTransaction<S> {
         prev_out: OutPoint,
         operator_seckey: &SecretKey,
     ) -> eyre::Result<Transaction> {
-        let taptree =
-            Self::form_taptree(ctx, self.payout_script.to_script(), &self.disprove_scripts);
+        if self.payout_script.committee_pubkey.is_some() {
+            eyre::bail!("payout script requires the committee signature");
+        }
 
-        let script = self.payout_script.to_script();
+        self.sign_payout_transaction(ctx, txout, prev_out, operator_seckey, None)
+    }
+
+    /// Create Payout transaction the same way as
+    /// [`Self::payout_transaction`], but with the committee signature
+    /// over [`Self::payout_sighash`].
+    pub fn payout_transaction_presigned(
+        &self,
+        ctx: &Secp256k1<All>,
+        txout: TxOut,
+        prev_out: OutPoint,
+        operator_seckey: &SecretKey,
+        committee_signature: &schnorr::Signature,
+    ) -> eyre::Result<Transaction> {
+        if self.payout_script.committee_pubkey.is_none() {
+            eyre::bail!("payout script doesn't require the committee signature");
+        }
 
-        let mut tx = Transaction {
+        self.sign_payout_transaction(
+            ctx,
+            txout,
+            prev_out,
+            operator_seckey,
+            Some(committee_signature),
+        )
+    }
+
+    /// Return sighash of the Payout transaction, which is signed by both
+    /// operator and the committee.
+    pub fn payout_sighash(
+        &self,
+        ctx: &Secp256k1<All>,
+        txout: TxOut,
+        prev_out: OutPoint,
+    ) -> eyre::Result<TapSighash> {
+        let tx = self.unsigned_payout_transaction(txout, prev_out);
+        let prev_txout = self.txout(ctx);
+
+        SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[prev_txout]),
+                self.payout_script.to_script().tapscript_leaf_hash(),
+                TapSighashType::Default,
+            )
+            .map_err(Into::into)
+    }
+
+    fn unsigned_payout_transaction(&self, txout: TxOut, prev_out: OutPoint) -> Transaction {
+        Transaction {
             version: Version::TWO,
             lock_time: LockTime::ZERO,
             input: vec![TxIn {
@@ -201,17 +388,23 @@ impl<S: SplitableScript> AssertTransaction<S> {
                 witness: Witness::new(),
             }],
             output: vec![txout],
-        };
+        }
+    }
+
+    fn sign_payout_transaction(
+        &self,
+        ctx: &Secp256k1<All>,
+        txout: TxOut,
+        prev_out: OutPoint,
+        operator_seckey: &SecretKey,
+        committee_signature: Option<&schnorr::Signature>,
+    ) -> eyre::Result<Transaction> {
+        let taptree = self.taptree(ctx);
 
-        let leaf_hash = script.tapscript_leaf_hash();
-        let prev_txout = self.assert_taproot_output(&taptree);
+        let script = self.payout_script.to_script();
 
-        let sighash = SighashCache::new(&tx).taproot_script_spend_signature_hash(
-            0,
-            &Prevouts::All(&[prev_txout]),
-            leaf_hash,
-            TapSighashType::Default,
-        )?;
+        let sighash = self.payout_sighash(ctx, txout.clone(), prev_out)?;
+        let mut tx = self.unsigned_payout_transaction(txout, prev_out);
 
         let signature = ctx.sign_schnorr(
             &sighash.into(),
@@ -225,6 +418,9 @@ impl<S: SplitableScript> AssertTransaction<S> {
         let mut witness = Witness::new();
         witness.push(signature.as_ref());
         witness.push(self.operator_pubkey.serialize());
+        if let Some(committee_signature) = committee_signature {
+            witness.push(committee_signature.as_ref());
+        }
         witness.push(script.as_bytes());
         witness.push(control_block.serialize());
 
@@ -233,8 +429,11 @@ impl<S: SplitableScript> AssertTransaction<S> {
         Ok(tx)
     }
 
-    /// Create Payout transaction which spends first output of Assert
-    /// transaction using Payout script path.
+    /// Create Disprove transactions which spend first output of Assert
+    /// transaction using Disprove script paths.
+    ///
+    /// Fails if the disprove leaves require the committee signature, use
+    /// [`Self::disprove_transactions_presigned`] in that case.
     pub fn disprove_transactions(
         &self,
         ctx: &Secp256k1<All>,
@@ -242,52 +441,116 @@ impl<S: SplitableScript> AssertTransaction<S> {
         prev_out: OutPoint,
     ) -> eyre::Result<HashMap<DisproveScript, Transaction>> {
         Self::form_disprove_transactions(
+            self.internal_key,
             self.payout_script.to_script(),
             &self.disprove_scripts,
+            &self.equivocation_scripts,
+            self.payout_script.committee_pubkey.as_ref(),
             ctx,
             txout,
             prev_out,
         )
     }
 
+    /// Create Disprove transactions the same way as
+    /// [`Self::disprove_transactions`], but with committee signatures over
+    /// [`Self::disprove_sighashes`] in the same order as disprove scripts.
+    pub fn disprove_transactions_presigned(
+        &self,
+        ctx: &Secp256k1<All>,
+        txout: TxOut,
+        prev_out: OutPoint,
+        committee_signatures: &[schnorr::Signature],
+    ) -> eyre::Result<HashMap<DisproveScript, Transaction>> {
+        let Some(committee_pubkey) = self.payout_script.committee_pubkey.as_ref() else {
+            eyre::bail!("disprove scripts don't require the committee signature");
+        };
+        if committee_signatures.len() != self.disprove_scripts.len() {
+            eyre::bail!(
+                "expected {} committee signatures, got {}",
+                self.disprove_scripts.len(),
+                committee_signatures.len()
+            );
+        }
+
+        let taptree = self.taptree(ctx);
+        let mut map = HashMap::with_capacity(self.disprove_scripts.len());
+
+        for (disprove, signature) in self.disprove_scripts.iter().zip(committee_signatures) {
+            let tx = form_disprove_transaction(
+                &taptree,
+                disprove,
+                Some((committee_pubkey, signature)),
+                txout.clone(),
+                prev_out,
+            );
+
+            map.insert(disprove.clone(), tx);
+        }
+
+        Ok(map)
+    }
+
+    /// Return sighashes of Disprove transactions in the same order as
+    /// disprove scripts, which should be signed by the committee.
+    pub fn disprove_sighashes(
+        &self,
+        ctx: &Secp256k1<All>,
+        txout: TxOut,
+        prev_out: OutPoint,
+    ) -> eyre::Result<Vec<TapSighash>> {
+        let prev_txout = self.txout(ctx);
+        let committee_pubkey = self.payout_script.committee_pubkey.as_ref();
+
+        self.disprove_scripts
+            .iter()
+            .map(|disprove| {
+                let tx = unsigned_disprove_transaction(txout.clone(), prev_out);
+
+                SighashCache::new(&tx)
+                    .taproot_script_spend_signature_hash(
+                        0,
+                        &Prevouts::All(&[prev_txout.clone()]),
+                        disprove_leaf(disprove, committee_pubkey).tapscript_leaf_hash(),
+                        TapSighashType::Default,
+                    )
+                    .map_err(Into::into)
+            })
+            .collect()
+    }
+
     /// Create Disprove transaction which spends first output of Assert
     /// transaction using Payout script path.
+    ///
+    /// If `committee_pubkey` is provided, the transactions won't be
+    /// valid without committee signatures, see
+    /// [`Self::disprove_transactions_presigned`].
     pub fn form_disprove_transactions(
+        internal_key: XOnlyPublicKey,
         payout_script: Script,
         disprove_scripts: &[DisproveScript],
+        equivocation_scripts: &[EquivocationScript],
+        committee_pubkey: Option<&XOnlyPublicKey>,
         ctx: &Secp256k1<All>,
         txout: TxOut,
         prev_out: OutPoint,
     ) -> eyre::Result<HashMap<DisproveScript, Transaction>> {
-        let taptree = Self::form_taptree(ctx, payout_script, disprove_scripts);
+        if committee_pubkey.is_some() {
+            eyre::bail!("disprove scripts require the committee signature");
+        }
+
+        let taptree = Self::form_taptree(
+            ctx,
+            internal_key,
+            payout_script,
+            disprove_scripts,
+            equivocation_scripts,
+            committee_pubkey,
+        );
         let mut map = HashMap::with_capacity(disprove_scripts.len());
 
         for disprove in disprove_scripts {
-            let script = disprove.script_pubkey.clone();
-            let mut witness = Witness::new();
-
-            for elem in disprove.witness_elements() {
-                witness.push(elem);
-            }
-
-            let control_block = &taptree
-                .control_block(&(script.clone(), LeafVersion::TapScript))
-                .unwrap();
-
-            witness.push(script.as_bytes());
-            witness.push(control_block.serialize());
-
-            let tx = Transaction {
-                version: Version::ONE,
-                lock_time: LockTime::ZERO,
-                input: vec![TxIn {
-                    previous_output: prev_out,
-                    script_sig: Script::new(),
-                    sequence: Sequence::ZERO,
-                    witness,
-                }],
-                output: vec![txout.clone()],
-            };
+            let tx = form_disprove_transaction(&taptree, disprove, None, txout.clone(), prev_out);
 
             map.insert(disprove.clone(), tx);
         }
@@ -295,6 +558,64 @@ impl<S: SplitableScript> AssertTransaction<S> {
         Ok(map)
     }
 
+    /// Create transaction which spends first output of Assert transaction
+    /// using the equivocation leaf of the double-signed element.
+    pub fn equivocation_transaction(
+        &self,
+        ctx: &Secp256k1<All>,
+        equivocation: &Equivocation,
+        txout: TxOut,
+        prev_out: OutPoint,
+    ) -> eyre::Result<Transaction> {
+        let (script, witness_elements) = self
+            .equivocation_scripts
+            .iter()
+            .find_map(|script| {
+                script
+                    .witness_elements(equivocation)
+                    .map(|elements| (script, elements))
+            })
+            .ok_or_eyre("no equivocation leaf for the public key")?;
+
+        let taptree = self.taptree(ctx);
+        let control_block = taptree
+            .control_block(&(script.script_pubkey.clone(), LeafVersion::TapScript))
+            .ok_or_eyre("equivocation leaf should be in the taptree")?;
+
+        let mut witness = Witness::new();
+        for elem in witness_elements {
+            witness.push(elem);
+        }
+        witness.push(script.script_pubkey.as_bytes());
+        witness.push(control_block.serialize());
+
+        let mut tx = unsigned_disprove_transaction(txout, prev_out);
+        tx.input[0].witness = witness;
+
+        Ok(tx)
+    }
+
+    /// Create transaction which spends commitment outputs `prevouts` (see
+    /// [`StatesCommitment::txouts`]) and publishes signed `states` in its
+    /// witness.
+    pub fn spend_commitment_inputs_tx(
+        &self,
+        ctx: &Secp256k1<All>,
+        commitment: &StatesCommitment,
+        states: &[SignedIntermediateState],
+        operator_seckey: &SecretKey,
+        prevouts: Vec<(OutPoint, TxOut)>,
+    ) -> eyre::Result<Transaction> {
+        commitment.spend_tx(
+            ctx,
+            self.internal_key,
+            states,
+            operator_seckey,
+            prevouts,
+            self.txout(ctx),
+        )
+    }
+
     /// Create transaction which spends provided utxo (assuming that it's the
     /// P2WPKH one) signed with provided key.
     pub fn spend_p2wpkh_input_tx(
@@ -341,3 +662,59 @@ impl<S: SplitableScript> AssertTransaction<S> {
         psbt.extract_tx().map_err(Into::into)
     }
 }
+
+/// Return the leaf script of the disprove script, which is guarded by the
+/// committee signature if `committee_pubkey` is provided.
+fn disprove_leaf(disprove: &DisproveScript, committee_pubkey: Option<&XOnlyPublicKey>) -> Script {
+    match committee_pubkey {
+        Some(committee_pubkey) => presigned_leaf(committee_pubkey, disprove.script_pubkey.clone()),
+        None => disprove.script_pubkey.clone(),
+    }
+}
+
+fn unsigned_disprove_transaction(txout: TxOut, prev_out: OutPoint) -> Transaction {
+    Transaction {
+        version: Version::ONE,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: prev_out,
+            script_sig: Script::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![txout],
+    }
+}
+
+/// Form Disprove transaction with filled witness for disprove script spend
+/// path. The committee signature is pushed on top of the disprove witness.
+fn form_disprove_transaction(
+    taptree: &TaprootSpendInfo,
+    disprove: &DisproveScript,
+    committee: Option<(&XOnlyPublicKey, &schnorr::Signature)>,
+    txout: TxOut,
+    prev_out: OutPoint,
+) -> Transaction {
+    let script = disprove_leaf(disprove, committee.map(|(pubkey, _)| pubkey));
+    let mut witness = Witness::new();
+
+    for elem in disprove.witness_elements() {
+        witness.push(elem);
+    }
+
+    if let Some((_, signature)) = committee {
+        witness.push(signature.as_ref());
+    }
+
+    let control_block = &taptree
+        .control_block(&(script.clone(), LeafVersion::TapScript))
+        .unwrap();
+
+    witness.push(script.as_bytes());
+    witness.push(control_block.serialize());
+
+    let mut tx = unsigned_disprove_transaction(txout, prev_out);
+    tx.input[0].witness = witness;
+
+    tx
+}
@@ -3,6 +3,7 @@ use bitcoin::{
     relative::Height,
     XOnlyPublicKey,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::treepp::*;
 
@@ -10,21 +11,26 @@ use crate::treepp::*;
 pub const LOCKTIME: u16 =  6 /* hour */ * 24 /* day */ * 14 /* two weeks */;
 
 /// Script by which Operator spends the Assert transaction after timelock.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PayoutScript {
-    // TODO(Velnbur): add mutisig of the comittee.
-    // pub comittee_pubkeys: Vec<PublicKey>,
+    /// Aggregated public key of the committee, which presigns the Payout
+    /// transaction. If set, the operator can't spend the output to an
+    /// arbitrary address.
+    pub committee_pubkey: Option<XOnlyPublicKey>,
+
     /// Public key of the operator
     pub operator_pubkey: XOnlyPublicKey,
 
     /// Specified locktime after which assert transaction is spendable
     /// by payout script, default value is [`LOCKTIME`].
+    #[serde(with = "height_blocks")]
     pub locktime: Height,
 }
 
 impl PayoutScript {
     pub fn new(operator_pubkey: XOnlyPublicKey) -> Self {
         Self {
+            committee_pubkey: None,
             operator_pubkey,
             locktime: Height::from(LOCKTIME),
         }
@@ -32,16 +38,48 @@ impl PayoutScript {
 
     pub fn with_locktime(operator_pubkey: XOnlyPublicKey, locktime: Height) -> Self {
         Self {
+            committee_pubkey: None,
+            operator_pubkey,
+            locktime,
+        }
+    }
+
+    /// Construct payout script which additionally requires the signature
+    /// of the committee aggregated key.
+    pub fn with_committee(
+        operator_pubkey: XOnlyPublicKey,
+        committee_pubkey: XOnlyPublicKey,
+        locktime: Height,
+    ) -> Self {
+        Self {
+            committee_pubkey: Some(committee_pubkey),
             operator_pubkey,
             locktime,
         }
     }
 
+    /// Return the leaf script.
+    ///
+    /// ## Witness:
+    /// ```bitcoin_script
+    /// { operator_signature }
+    /// { operator_pubkey }
+    /// { committee_signature } // only if committee is set
+    /// ```
     pub fn to_script(&self) -> Script {
+        let committee_check = match self.committee_pubkey {
+            Some(committee_pubkey) => script! {
+                { committee_pubkey.serialize().to_vec() }
+                OP_CHECKSIGVERIFY
+            },
+            None => script! {},
+        };
+
         script! {
             { self.locktime.value() as u32 }
             OP_CSV
             OP_DROP
+            { committee_check }
             OP_DUP
             OP_HASH160
             {
@@ -54,3 +92,17 @@ impl PayoutScript {
         }
     }
 }
+
+/// (De)serialize [`Height`] as the number of blocks.
+mod height_blocks {
+    use bitcoin::relative::Height;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(height: &Height, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(height.value())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Height, D::Error> {
+        u16::deserialize(deserializer).map(Height::from)
+    }
+}
@@ -0,0 +1,289 @@
+//! Versioned on-disk format of the [`AssertTransaction`], which operator
+//! hands to challengers.
+//!
+//! The bundle can be encoded either as JSON (see [`AssertBundle::to_json`])
+//! or as compact binary (see [`AssertBundle::to_bytes`]). Both contain the
+//! hash of each disprove script and the digest of the whole bundle, which
+//! are checked on decoding.
+
+use std::{fs, path::Path};
+
+use bitcoin::{
+    hashes::{sha256, Hash, HashEngine},
+    Amount, XOnlyPublicKey,
+};
+use bitcoin_splitter::split::script::SplitableScript;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assert::{payout_script::PayoutScript, AssertTransaction},
+    disprove::{equivocation::EquivocationScript, DisproveScript},
+};
+
+/// Current version of the bundle format.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Identifier of the program, which is SHA-256 of its script.
+pub fn program_id<S: SplitableScript>() -> sha256::Hash {
+    sha256::Hash::hash(S::script().as_bytes())
+}
+
+/// Hash of the disprove script, committing to both its script and witness.
+pub fn disprove_script_hash(disprove: &DisproveScript) -> sha256::Hash {
+    let mut engine = sha256::Hash::engine();
+    for script in [&disprove.script_pubkey, &disprove.script_witness] {
+        engine.input(&(script.len() as u64).to_le_bytes());
+        engine.input(script.as_bytes());
+    }
+
+    sha256::Hash::from_engine(engine)
+}
+
+/// Serializable form of [`AssertTransaction`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertBundle {
+    pub version: u32,
+
+    /// See [`program_id`].
+    pub program_id: sha256::Hash,
+
+    pub operator_pubkey: XOnlyPublicKey,
+    pub amount: Amount,
+    pub internal_key: XOnlyPublicKey,
+
+    pub payout_script: PayoutScript,
+    pub disprove_scripts: Vec<DisproveScript>,
+    pub equivocation_scripts: Vec<EquivocationScript>,
+
+    /// Hashes of disprove scripts in the same order, see
+    /// [`disprove_script_hash`].
+    pub disprove_hashes: Vec<sha256::Hash>,
+
+    /// Hash of all the fields above.
+    pub digest: sha256::Hash,
+}
+
+impl AssertBundle {
+    pub fn new<S: SplitableScript>(assert: &AssertTransaction<S>) -> Self {
+        let mut bundle = Self {
+            version: BUNDLE_VERSION,
+            program_id: program_id::<S>(),
+            operator_pubkey: assert.operator_pubkey,
+            amount: assert.amount,
+            internal_key: assert.internal_key,
+            payout_script: assert.payout_script.clone(),
+            disprove_scripts: assert.disprove_scripts.clone(),
+            equivocation_scripts: assert.equivocation_scripts.clone(),
+            disprove_hashes: assert
+                .disprove_scripts
+                .iter()
+                .map(disprove_script_hash)
+                .collect(),
+            digest: sha256::Hash::all_zeros(),
+        };
+        bundle.digest = bundle.compute_digest();
+
+        bundle
+    }
+
+    fn compute_digest(&self) -> sha256::Hash {
+        let content = bincode::serialize(&(
+            self.version,
+            &self.program_id,
+            &self.operator_pubkey,
+            &self.amount,
+            &self.internal_key,
+            &self.payout_script,
+            &self.disprove_scripts,
+            &self.equivocation_scripts,
+            &self.disprove_hashes,
+        ))
+        .expect("serialization into vector never fails");
+
+        sha256::Hash::hash(&content)
+    }
+
+    /// Check version and integrity hashes of the bundle.
+    pub fn verify(&self) -> eyre::Result<()> {
+        eyre::ensure!(
+            self.version == BUNDLE_VERSION,
+            "unsupported bundle version {}, expected {}",
+            self.version,
+            BUNDLE_VERSION
+        );
+        eyre::ensure!(
+            self.disprove_hashes.len() == self.disprove_scripts.len(),
+            "expected {} disprove hashes, got {}",
+            self.disprove_scripts.len(),
+            self.disprove_hashes.len()
+        );
+        for (idx, (disprove, hash)) in self
+            .disprove_scripts
+            .iter()
+            .zip(&self.disprove_hashes)
+            .enumerate()
+        {
+            eyre::ensure!(
+                disprove_script_hash(disprove) == *hash,
+                "hash of disprove script {idx} mismatch"
+            );
+        }
+        eyre::ensure!(
+            self.compute_digest() == self.digest,
+            "bundle digest mismatch"
+        );
+
+        Ok(())
+    }
+
+    /// Verify the bundle and convert it into [`AssertTransaction`] of
+    /// program `S`.
+    pub fn into_assert<S: SplitableScript>(self) -> eyre::Result<AssertTransaction<S>> {
+        self.verify()?;
+        eyre::ensure!(
+            self.program_id == program_id::<S>(),
+            "bundle is for another program {}",
+            self.program_id
+        );
+
+        Ok(AssertTransaction::from_scripts(
+            self.operator_pubkey,
+            self.payout_script,
+            self.disprove_scripts,
+            self.amount,
+            self.internal_key,
+        )
+        .with_equivocation_scripts(self.equivocation_scripts))
+    }
+
+    pub fn to_json(&self) -> eyre::Result<String> {
+        serde_json::to_string_pretty(self).map_err(Into::into)
+    }
+
+    pub fn from_json(json: &str) -> eyre::Result<Self> {
+        let bundle: Self = serde_json::from_str(json)?;
+        bundle.verify()?;
+
+        Ok(bundle)
+    }
+
+    pub fn to_bytes(&self) -> eyre::Result<Vec<u8>> {
+        bincode::serialize(self).map_err(Into::into)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> eyre::Result<Self> {
+        let bundle: Self = bincode::deserialize(bytes)?;
+        bundle.verify()?;
+
+        Ok(bundle)
+    }
+
+    /// Write bundle to `path` as JSON if its extension is `json`, and as
+    /// binary otherwise.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let path = path.as_ref();
+        if is_json(path) {
+            fs::write(path, self.to_json()?)?;
+        } else {
+            fs::write(path, self.to_bytes()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read bundle written by [`Self::write_to_file`].
+    pub fn read_from_file(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        if is_json(path) {
+            Self::from_json(&fs::read_to_string(path)?)
+        } else {
+            Self::from_bytes(&fs::read(path)?)
+        }
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+impl<S: SplitableScript> Serialize for AssertTransaction<S> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        AssertBundle::new(self).serialize(serializer)
+    }
+}
+
+impl<'de, S: SplitableScript> Deserialize<'de> for AssertTransaction<S> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        AssertBundle::deserialize(deserializer)?
+            .into_assert()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{key::Secp256k1, secp256k1::SecretKey, OutPoint};
+    use bitcoin_splitter::split::script::IOPair;
+    use bitcoin_testscripts::square_fibonacci::SquareFibonacciScript;
+
+    use super::*;
+    use crate::disprove::derivation::KeyDerivation;
+
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    fn fibonacci_bundle() -> AssertBundle {
+        let ctx = Secp256k1::new();
+        let operator_pubkey = SecretKey::new(&mut bitcoin::key::rand::thread_rng())
+            .public_key(&ctx)
+            .x_only_public_key()
+            .0;
+        let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+
+        let assert = AssertTransaction::<FibonacciScript>::new(
+            input,
+            operator_pubkey,
+            Amount::ONE_BTC,
+            &KeyDerivation::for_program::<FibonacciScript>([42; 32], OutPoint::null()),
+        )
+        .unwrap();
+
+        AssertBundle::new(&assert)
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let bundle = fibonacci_bundle();
+
+        let from_json = AssertBundle::from_json(&bundle.to_json().unwrap()).unwrap();
+        let from_bytes = AssertBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(from_json.digest, bundle.digest);
+        assert_eq!(from_bytes.digest, bundle.digest);
+
+        let ctx = Secp256k1::new();
+        let assert = from_json.into_assert::<FibonacciScript>().unwrap();
+        let restored = from_bytes.into_assert::<FibonacciScript>().unwrap();
+        assert_eq!(assert.txout(&ctx), restored.txout(&ctx));
+        assert_eq!(assert.disprove_scripts, bundle.disprove_scripts);
+    }
+
+    #[test]
+    fn test_bundle_integrity_is_checked() {
+        let mut bundle = fibonacci_bundle();
+        bundle.disprove_scripts.swap(0, 1);
+        assert!(bundle.verify().is_err());
+
+        let mut bundle = fibonacci_bundle();
+        bundle.amount = Amount::ONE_SAT;
+        assert!(AssertBundle::from_bytes(&bundle.to_bytes().unwrap()).is_err());
+
+        let mut bundle = fibonacci_bundle();
+        bundle.version += 1;
+        bundle.digest = bundle.compute_digest();
+        assert!(bundle.verify().is_err());
+
+        assert!(fibonacci_bundle()
+            .into_assert::<SquareFibonacciScript<128>>()
+            .is_err());
+    }
+}
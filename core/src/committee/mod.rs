@@ -0,0 +1,101 @@
+//! Module containing the logic of the n-of-n committee, which presigns the
+//! transactions spending the **Assert** output, so the operator can't
+//! spend it to an arbitrary address.
+//!
+//! The committee members aggregate their keys using MuSig2 (see
+//! [`musig2`]), and the aggregated key is placed into every leaf which
+//! should be presigned.
+
+use bitcoin::{
+    hashes::Hash as _,
+    key::Secp256k1,
+    secp256k1::{schnorr, PublicKey, Verification},
+    TapSighash, XOnlyPublicKey,
+};
+
+use crate::treepp::*;
+
+use self::musig2::{AggNonce, Error, KeyAggContext, PubNonce, SigningSession};
+
+pub mod musig2;
+
+/// The n-of-n committee which presigns the transactions of the protocol.
+#[derive(Clone, Debug)]
+pub struct Committee {
+    key_agg: KeyAggContext,
+}
+
+impl Committee {
+    /// Construct committee from public keys of its members.
+    ///
+    /// Keys are sorted, so the members don't need to agree on the order
+    /// of their keys.
+    pub fn new<C: Verification>(
+        ctx: &Secp256k1<C>,
+        mut members: Vec<PublicKey>,
+    ) -> Result<Self, Error> {
+        members.sort();
+
+        Ok(Self {
+            key_agg: KeyAggContext::new(ctx, members)?,
+        })
+    }
+
+    /// Public keys of the committee members.
+    pub fn members(&self) -> &[PublicKey] {
+        self.key_agg.pubkeys()
+    }
+
+    /// Aggregated key of the committee, which is used in leaf scripts.
+    pub fn aggregated_pubkey(&self) -> XOnlyPublicKey {
+        self.key_agg.aggregated_xonly()
+    }
+
+    /// Start signing session of transaction `sighash`, after all the members
+    /// shared their public nonces.
+    pub fn signing_session<C: Verification>(
+        &self,
+        ctx: &Secp256k1<C>,
+        pubnonces: &[PubNonce],
+        sighash: TapSighash,
+    ) -> Result<SigningSession, Error> {
+        if pubnonces.len() != self.members().len() {
+            return Err(Error::NonceCountMismatch {
+                expected: self.members().len(),
+                got: pubnonces.len(),
+            });
+        }
+
+        let agg_nonce = AggNonce::aggregate(pubnonces)?;
+
+        SigningSession::new(
+            ctx,
+            self.key_agg.clone(),
+            &agg_nonce,
+            sighash.to_byte_array(),
+        )
+    }
+}
+
+/// Prepend the leaf `script` with the check of the committee signature, so
+/// the leaf can be spent only by a transaction presigned by the committee.
+///
+/// The committee signature is expected to be on top of the witness stack.
+pub fn presigned_leaf(committee_pubkey: &XOnlyPublicKey, script: Script) -> Script {
+    script! {
+        { committee_pubkey.serialize().to_vec() }
+        OP_CHECKSIGVERIFY
+        { script }
+    }
+}
+
+/// Verify the committee signature over `sighash`.
+pub fn verify_signature<C: Verification>(
+    ctx: &Secp256k1<C>,
+    committee_pubkey: &XOnlyPublicKey,
+    sighash: TapSighash,
+    signature: &schnorr::Signature,
+) -> bool {
+    ctx.verify_schnorr(signature, &sighash.into(), committee_pubkey)
+        .is_ok()
+}
@@ -0,0 +1,786 @@
+//! Implementation of the MuSig2 multi-signature scheme as specified in
+//! [BIP-327](https://github.com/bitcoin/bips/blob/master/bip-0327.mediawiki).
+//!
+//! Only the subset needed by the committee is implemented: key aggregation,
+//! nonce generation and aggregation, partial signing (with verification) and
+//! partial signatures aggregation. Tweaking of the aggregated key is not
+//! supported, as the committee key is used only inside tapscript leaves.
+//! Secret nonces are drawn uniformly from a CSPRNG instead of the `NonceGen`
+//! algorithm of the BIP. The rest is checked against the test vectors of the
+//! BIP.
+
+use core::fmt;
+
+use bitcoin::{
+    hashes::{sha256, Hash, HashEngine},
+    key::{
+        rand::{CryptoRng, Rng},
+        Parity, Secp256k1,
+    },
+    secp256k1::{self, constants::CURVE_ORDER, schnorr, PublicKey, Scalar, SecretKey},
+    secp256k1::{Signing, Verification},
+    XOnlyPublicKey,
+};
+
+/// Tag of the hash which commits to the list of the aggregated keys.
+const KEYAGG_LIST_TAG: &[u8] = b"KeyAgg list";
+/// Tag of the hash from which key aggregation coefficients are derived.
+const KEYAGG_COEFF_TAG: &[u8] = b"KeyAgg coefficient";
+/// Tag of the hash from which nonce coefficient is derived.
+const NONCE_COEFF_TAG: &[u8] = b"MuSig/noncecoef";
+/// Tag of the BIP-340 challenge hash.
+const CHALLENGE_TAG: &[u8] = b"BIP0340/challenge";
+
+/// Errors which could occur during MuSig2 protocol execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// No public keys were provided for aggregation.
+    NoKeys,
+    /// No nonces were provided for aggregation.
+    NoNonces,
+    /// Number of the nonces differs from the number of the signers.
+    NonceCountMismatch { expected: usize, got: usize },
+    /// Signer's public key is not a part of aggregated ones.
+    UnknownSigner,
+    /// Partial signature or signature is invalid.
+    InvalidSignature,
+    /// Error from underlying secp256k1 library.
+    Secp256k1(secp256k1::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoKeys => write!(f, "no public keys were provided for aggregation"),
+            Error::NoNonces => write!(f, "no public nonces were provided for aggregation"),
+            Error::NonceCountMismatch { expected, got } => {
+                write!(f, "expected {expected} public nonces, got {got}")
+            }
+            Error::UnknownSigner => write!(f, "signer is not a member of the aggregated key"),
+            Error::InvalidSignature => write!(f, "invalid signature"),
+            Error::Secp256k1(err) => write!(f, "secp256k1: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<secp256k1::Error> for Error {
+    fn from(value: secp256k1::Error) -> Self {
+        Self::Secp256k1(value)
+    }
+}
+
+/// Context of aggregated public keys, which holds the keys, their
+/// coefficients and the aggregated key itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyAggContext {
+    pubkeys: Vec<PublicKey>,
+    coefficients: Vec<Scalar>,
+    aggregated: PublicKey,
+}
+
+impl KeyAggContext {
+    /// Aggregate public keys as specified in `KeyAgg` algorithm of BIP-327.
+    ///
+    /// Note that the order of keys matters, so all the signers should agree
+    /// on it (for example, by sorting them).
+    pub fn new<C: Verification>(
+        ctx: &Secp256k1<C>,
+        pubkeys: Vec<PublicKey>,
+    ) -> Result<Self, Error> {
+        let first = *pubkeys.first().ok_or(Error::NoKeys)?;
+
+        let serialized = pubkeys.iter().map(PublicKey::serialize).collect::<Vec<_>>();
+        let list_hash = tagged_hash(
+            KEYAGG_LIST_TAG,
+            &serialized
+                .iter()
+                .map(|pk| pk.as_slice())
+                .collect::<Vec<_>>(),
+        );
+
+        // The second unique key in the list gets the coefficient equal to one
+        // for optimization purposes.
+        let second = pubkeys.iter().find(|pk| **pk != first).copied();
+
+        let coefficients = pubkeys
+            .iter()
+            .zip(serialized.iter())
+            .map(|(pk, bytes)| {
+                if Some(*pk) == second {
+                    Scalar::ONE
+                } else {
+                    scalar_from_hash(tagged_hash(KEYAGG_COEFF_TAG, &[&list_hash, bytes]))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let weighted = pubkeys
+            .iter()
+            .zip(coefficients.iter())
+            .map(|(pk, coeff)| pk.mul_tweak(ctx, coeff))
+            .collect::<Result<Vec<_>, _>>()?;
+        let aggregated = PublicKey::combine_keys(&weighted.iter().collect::<Vec<_>>())?;
+
+        Ok(Self {
+            pubkeys,
+            coefficients,
+            aggregated,
+        })
+    }
+
+    /// Public keys which were aggregated.
+    pub fn pubkeys(&self) -> &[PublicKey] {
+        &self.pubkeys
+    }
+
+    /// Aggregated public key with its parity.
+    pub fn aggregated_pubkey(&self) -> PublicKey {
+        self.aggregated
+    }
+
+    /// Aggregated public key in the x-only form, which is used in
+    /// tapscripts.
+    pub fn aggregated_xonly(&self) -> XOnlyPublicKey {
+        self.aggregated.x_only_public_key().0
+    }
+
+    /// Return key aggregation coefficient of the signer.
+    pub fn coefficient(&self, pubkey: &PublicKey) -> Option<Scalar> {
+        self.pubkeys
+            .iter()
+            .position(|pk| pk == pubkey)
+            .map(|idx| self.coefficients[idx])
+    }
+
+    fn has_even_y(&self) -> bool {
+        self.aggregated.x_only_public_key().1 == Parity::Even
+    }
+}
+
+/// Secret nonce of the signer. Can be used only once, that's why it's
+/// consumed by [`SigningSession::partial_sign`] and can't be cloned.
+pub struct SecNonce {
+    k1: SecretKey,
+    k2: SecretKey,
+}
+
+impl SecNonce {
+    /// Generate a pair of secret and public nonces from provided randomness.
+    pub fn generate<C, R>(ctx: &Secp256k1<C>, rng: &mut R) -> (SecNonce, PubNonce)
+    where
+        C: Signing,
+        R: Rng + CryptoRng + ?Sized,
+    {
+        let k1 = SecretKey::new(rng);
+        let k2 = SecretKey::new(rng);
+
+        let pubnonce = PubNonce {
+            r1: k1.public_key(ctx),
+            r2: k2.public_key(ctx),
+        };
+
+        (Self { k1, k2 }, pubnonce)
+    }
+}
+
+/// Public nonce of the signer, which is shared with other signers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PubNonce {
+    r1: PublicKey,
+    r2: PublicKey,
+}
+
+impl PubNonce {
+    /// Serialize public nonce into 66 bytes.
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut buf = [0u8; 66];
+        buf[..33].copy_from_slice(&self.r1.serialize());
+        buf[33..].copy_from_slice(&self.r2.serialize());
+        buf
+    }
+
+    /// Deserialize public nonce from 66 bytes.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 66 {
+            return Err(Error::Secp256k1(secp256k1::Error::InvalidPublicKey));
+        }
+
+        Ok(Self {
+            r1: PublicKey::from_slice(&bytes[..33])?,
+            r2: PublicKey::from_slice(&bytes[33..])?,
+        })
+    }
+}
+
+/// Aggregated public nonce of all signers. `None` stands for the point at
+/// infinity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AggNonce {
+    r1: Option<PublicKey>,
+    r2: Option<PublicKey>,
+}
+
+impl AggNonce {
+    /// Aggregate public nonces of all the signers as specified in
+    /// `NonceAgg` algorithm of BIP-327.
+    pub fn aggregate(nonces: &[PubNonce]) -> Result<Self, Error> {
+        if nonces.is_empty() {
+            return Err(Error::NoNonces);
+        }
+
+        let r1 = nonces.iter().map(|nonce| &nonce.r1).collect::<Vec<_>>();
+        let r2 = nonces.iter().map(|nonce| &nonce.r2).collect::<Vec<_>>();
+
+        Ok(Self {
+            r1: PublicKey::combine_keys(&r1).ok(),
+            r2: PublicKey::combine_keys(&r2).ok(),
+        })
+    }
+
+    /// Serialize aggregated nonce into 66 bytes, where point at infinity
+    /// is encoded as 33 zero bytes.
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut buf = [0u8; 66];
+        if let Some(r1) = self.r1 {
+            buf[..33].copy_from_slice(&r1.serialize());
+        }
+        if let Some(r2) = self.r2 {
+            buf[33..].copy_from_slice(&r2.serialize());
+        }
+        buf
+    }
+}
+
+/// Partial signature of single signer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialSignature(Scalar);
+
+impl PartialSignature {
+    pub fn serialize(&self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        let bytes: [u8; 32] = bytes.try_into().map_err(|_| Error::InvalidSignature)?;
+
+        Scalar::from_be_bytes(bytes)
+            .map(Self)
+            .map_err(|_| Error::InvalidSignature)
+    }
+}
+
+/// Signing session for a single message, which is formed after all signers
+/// exchanged their public nonces.
+#[derive(Clone, Debug)]
+pub struct SigningSession {
+    key_agg: KeyAggContext,
+    msg: [u8; 32],
+    /// Nonce coefficient.
+    b: Scalar,
+    /// Final nonce.
+    r: PublicKey,
+    /// BIP-340 challenge.
+    e: Scalar,
+}
+
+impl SigningSession {
+    pub fn new<C: Verification>(
+        ctx: &Secp256k1<C>,
+        key_agg: KeyAggContext,
+        agg_nonce: &AggNonce,
+        msg: [u8; 32],
+    ) -> Result<Self, Error> {
+        let agg_xonly = key_agg.aggregated_xonly().serialize();
+
+        let b = scalar_from_hash(tagged_hash(
+            NONCE_COEFF_TAG,
+            &[&agg_nonce.serialize(), &agg_xonly, &msg],
+        ));
+
+        // R = R1 + b * R2, where the point at infinity is replaced by the
+        // generator.
+        let r2 = agg_nonce.r2.map(|r2| r2.mul_tweak(ctx, &b)).transpose()?;
+        let r = match (agg_nonce.r1, r2) {
+            (Some(r1), Some(r2)) => r1.combine(&r2).ok(),
+            (Some(r), None) | (None, Some(r)) => Some(r),
+            (None, None) => None,
+        }
+        .unwrap_or_else(|| generator(ctx));
+
+        let e = scalar_from_hash(tagged_hash(
+            CHALLENGE_TAG,
+            &[&r.x_only_public_key().0.serialize(), &agg_xonly, &msg],
+        ));
+
+        Ok(Self {
+            key_agg,
+            msg,
+            b,
+            r,
+            e,
+        })
+    }
+
+    /// Message which is signed in this session.
+    pub fn message(&self) -> [u8; 32] {
+        self.msg
+    }
+
+    /// Produce partial signature of the signer, consuming its secret nonce.
+    pub fn partial_sign<C: Signing>(
+        &self,
+        ctx: &Secp256k1<C>,
+        seckey: &SecretKey,
+        secnonce: SecNonce,
+    ) -> Result<PartialSignature, Error> {
+        let coeff = self
+            .key_agg
+            .coefficient(&seckey.public_key(ctx))
+            .ok_or(Error::UnknownSigner)?;
+
+        let SecNonce { mut k1, mut k2 } = secnonce;
+        if !self.has_even_r() {
+            k1 = k1.negate();
+            k2 = k2.negate();
+        }
+
+        let d = if self.key_agg.has_even_y() {
+            *seckey
+        } else {
+            seckey.negate()
+        };
+
+        // s = k1 + b * k2 + e * a * d
+        let ea = SecretKey::from_slice(&self.e.to_be_bytes())?.mul_tweak(&coeff)?;
+        let ead = d.mul_tweak(&Scalar::from(ea))?;
+        let bk2 = k2.mul_tweak(&self.b)?;
+        let s = k1
+            .add_tweak(&Scalar::from(bk2))?
+            .add_tweak(&Scalar::from(ead))?;
+
+        Ok(PartialSignature(Scalar::from(s)))
+    }
+
+    /// Verify partial signature of the signer with `pubkey` and `pubnonce`.
+    pub fn verify_partial<C: Signing + Verification>(
+        &self,
+        ctx: &Secp256k1<C>,
+        pubkey: &PublicKey,
+        pubnonce: &PubNonce,
+        partial: &PartialSignature,
+    ) -> Result<(), Error> {
+        let coeff = self
+            .key_agg
+            .coefficient(pubkey)
+            .ok_or(Error::UnknownSigner)?;
+
+        // s * G
+        let lhs = SecretKey::from_slice(&partial.0.to_be_bytes())
+            .map_err(|_| Error::InvalidSignature)?
+            .public_key(ctx);
+
+        // R1 + b * R2, negated if the final nonce has odd y
+        let mut re = pubnonce.r1.combine(&pubnonce.r2.mul_tweak(ctx, &self.b)?)?;
+        if !self.has_even_r() {
+            re = re.negate(ctx);
+        }
+
+        // e * a * g * P
+        let p = if self.key_agg.has_even_y() {
+            *pubkey
+        } else {
+            pubkey.negate(ctx)
+        };
+        let ea = SecretKey::from_slice(&self.e.to_be_bytes())?.mul_tweak(&coeff)?;
+        let rhs = re.combine(&p.mul_tweak(ctx, &Scalar::from(ea))?)?;
+
+        if lhs != rhs {
+            return Err(Error::InvalidSignature);
+        }
+
+        Ok(())
+    }
+
+    /// Aggregate partial signatures of all signers into the final BIP-340
+    /// signature valid for the aggregated key.
+    pub fn aggregate(&self, partials: &[PartialSignature]) -> Result<schnorr::Signature, Error> {
+        let (first, rest) = partials.split_first().ok_or(Error::InvalidSignature)?;
+
+        let mut s =
+            SecretKey::from_slice(&first.0.to_be_bytes()).map_err(|_| Error::InvalidSignature)?;
+        for partial in rest {
+            s = s
+                .add_tweak(&partial.0)
+                .map_err(|_| Error::InvalidSignature)?;
+        }
+
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&self.r.x_only_public_key().0.serialize());
+        buf[32..].copy_from_slice(&s.secret_bytes());
+
+        schnorr::Signature::from_slice(&buf).map_err(Into::into)
+    }
+
+    fn has_even_r(&self) -> bool {
+        self.r.x_only_public_key().1 == Parity::Even
+    }
+}
+
+/// Return generator point of secp256k1 curve.
+fn generator<C: Signing>(ctx: &Secp256k1<C>) -> PublicKey {
+    SecretKey::from_slice(&Scalar::ONE.to_be_bytes())
+        .expect("one is a valid secret key")
+        .public_key(ctx)
+}
+
+/// Tagged hash as specified in BIP-340.
+pub(crate) fn tagged_hash(tag: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(tag);
+
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag_hash.as_byte_array());
+    engine.input(tag_hash.as_byte_array());
+    for part in parts {
+        engine.input(part);
+    }
+
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Interpret hash as an integer modulo curve order.
+fn scalar_from_hash(hash: [u8; 32]) -> Scalar {
+    Scalar::from_be_bytes(reduce_mod_order(hash)).expect("value is reduced modulo curve order")
+}
+
+/// Reduce big-endian 256-bit integer modulo curve order. As the order is
+/// greater than 2^255, a single subtraction is enough.
+fn reduce_mod_order(mut value: [u8; 32]) -> [u8; 32] {
+    if value < CURVE_ORDER {
+        return value;
+    }
+
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let mut diff = value[i] as i16 - CURVE_ORDER[i] as i16 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 256;
+            borrow = 1;
+        }
+        value[i] = diff as u8;
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{key::rand::thread_rng, secp256k1::Message};
+
+    use super::*;
+
+    #[test]
+    fn test_musig2_signature_is_valid_for_aggregated_key() {
+        const SIGNERS: usize = 3;
+
+        let ctx = Secp256k1::new();
+        let mut rng = thread_rng();
+
+        let seckeys = (0..SIGNERS)
+            .map(|_| SecretKey::new(&mut rng))
+            .collect::<Vec<_>>();
+        let pubkeys = seckeys.iter().map(|sk| sk.public_key(&ctx)).collect();
+
+        let key_agg = KeyAggContext::new(&ctx, pubkeys).unwrap();
+
+        let (secnonces, pubnonces): (Vec<_>, Vec<_>) = (0..SIGNERS)
+            .map(|_| SecNonce::generate(&ctx, &mut rng))
+            .unzip();
+        let agg_nonce = AggNonce::aggregate(&pubnonces).unwrap();
+
+        let msg = [7u8; 32];
+        let session = SigningSession::new(&ctx, key_agg.clone(), &agg_nonce, msg).unwrap();
+
+        let partials = seckeys
+            .iter()
+            .zip(secnonces)
+            .map(|(sk, secnonce)| session.partial_sign(&ctx, sk, secnonce).unwrap())
+            .collect::<Vec<_>>();
+
+        for ((partial, pubnonce), pubkey) in
+            partials.iter().zip(pubnonces.iter()).zip(key_agg.pubkeys())
+        {
+            session
+                .verify_partial(&ctx, pubkey, pubnonce, partial)
+                .expect("partial signature should be valid");
+        }
+
+        let signature = session.aggregate(&partials).unwrap();
+
+        ctx.verify_schnorr(
+            &signature,
+            &Message::from_digest(msg),
+            &key_agg.aggregated_xonly(),
+        )
+        .expect("aggregated signature should be valid");
+    }
+
+    /// Public keys of `key_agg_vectors.json` of BIP-327.
+    const KEY_AGG_PUBKEYS: [&str; 3] = [
+        "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+        "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66",
+    ];
+
+    /// Public nonces of `nonce_agg_vectors.json` of BIP-327.
+    const NONCE_AGG_PNONCES: [&str; 4] = [
+        "020151C80F435648DF67A22B749CD798CE54E0321D034B92B709B567D60A42E666\
+         03BA47FBC1834437B3212E89A84D8425E7BF12E0245D98262268EBDCB385D50641",
+        "03FF406FFD8ADB9CD29877E4985014F66A59F6CD01C0E88CAA8E5F3166B1F676A6\
+         0248C264CDD57D3C24D79990B0F865674EB62A0F9018277A95011B41BFC193B833",
+        "020151C80F435648DF67A22B749CD798CE54E0321D034B92B709B567D60A42E666\
+         0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+        "03FF406FFD8ADB9CD29877E4985014F66A59F6CD01C0E88CAA8E5F3166B1F676A6\
+         0379BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+    ];
+
+    /// Secret key, secret nonce, public keys, public nonces and message of
+    /// `sign_verify_vectors.json` of BIP-327. The first public key and nonce
+    /// belong to the signer.
+    const SIGN_SECKEY: &str = "7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671";
+    const SIGN_SECNONCE: [&str; 2] = [
+        "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61",
+        "FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F7",
+    ];
+    const SIGN_PUBKEYS: [&str; 3] = [
+        "03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+        "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        "02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA661",
+    ];
+    const SIGN_PNONCES: [&str; 4] = [
+        "0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA\
+         0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+        "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798\
+         0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+        "032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE93\
+         03E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046",
+        "0237C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA\
+         0387BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+    ];
+    const SIGN_MSG: &str = "F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF";
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    fn pubkey(hex: &str) -> PublicKey {
+        PublicKey::from_slice(&bytes::<33>(hex)).unwrap()
+    }
+
+    fn pubnonce(hex: &str) -> PubNonce {
+        PubNonce::from_slice(&bytes::<66>(hex)).unwrap()
+    }
+
+    fn sign_secnonce() -> SecNonce {
+        SecNonce {
+            k1: SecretKey::from_slice(&bytes::<32>(SIGN_SECNONCE[0])).unwrap(),
+            k2: SecretKey::from_slice(&bytes::<32>(SIGN_SECNONCE[1])).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_key_agg_vectors() {
+        let ctx = Secp256k1::new();
+
+        let cases: [(&[usize], &str); 4] = [
+            (
+                &[0, 1, 2],
+                "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C",
+            ),
+            (
+                &[2, 1, 0],
+                "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B",
+            ),
+            (
+                &[0, 0, 0],
+                "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935",
+            ),
+            (
+                &[0, 0, 1, 1],
+                "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E",
+            ),
+        ];
+
+        for (key_indices, expected) in cases {
+            let pubkeys = key_indices
+                .iter()
+                .map(|&idx| pubkey(KEY_AGG_PUBKEYS[idx]))
+                .collect();
+            let key_agg = KeyAggContext::new(&ctx, pubkeys).unwrap();
+
+            assert_eq!(
+                key_agg.aggregated_xonly().serialize(),
+                bytes::<32>(expected),
+                "key indices {key_indices:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_nonce_agg_vectors() {
+        let cases: [(&[usize], &str); 2] = [
+            (
+                &[0, 1],
+                "035FE1873B4F2967F52FEA4A06AD5A8ECCBE9D0FD73068012C894E2E87CCB5804B\
+                 024725377345BDE0E9C33AF3C43C0A29A9249F2F2956FA8CFEB55C8573D0262DC8",
+            ),
+            // The second nonce is the point at infinity
+            (
+                &[2, 3],
+                "035FE1873B4F2967F52FEA4A06AD5A8ECCBE9D0FD73068012C894E2E87CCB5804B\
+                 000000000000000000000000000000000000000000000000000000000000000000",
+            ),
+        ];
+
+        for (nonce_indices, expected) in cases {
+            let pubnonces = nonce_indices
+                .iter()
+                .map(|&idx| pubnonce(NONCE_AGG_PNONCES[idx]))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                AggNonce::aggregate(&pubnonces).unwrap().serialize(),
+                bytes::<66>(expected),
+                "nonce indices {nonce_indices:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_verify_vectors() {
+        let ctx = Secp256k1::new();
+        let seckey = SecretKey::from_slice(&bytes::<32>(SIGN_SECKEY)).unwrap();
+        let msg = bytes::<32>(SIGN_MSG);
+
+        assert_eq!(seckey.public_key(&ctx), pubkey(SIGN_PUBKEYS[0]));
+
+        // Key indices, nonce indices, aggregated nonce and the expected
+        // partial signature of the signer.
+        let cases: [(&[usize], &[usize], &str, &str); 4] = [
+            (
+                &[0, 1, 2],
+                &[0, 1, 2],
+                "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61\
+                 037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9",
+                "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB",
+            ),
+            (
+                &[1, 0, 2],
+                &[1, 0, 2],
+                "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61\
+                 037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9",
+                "9FF2F7AAA856150CC8819254218D3ADEEB0535269051897724F9DB3789513A52",
+            ),
+            (
+                &[1, 2, 0],
+                &[1, 2, 0],
+                "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61\
+                 037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9",
+                "FA23C359F6FAC4E7796BB93BC9F0532A95468C539BA20FF86D7C76ED92227900",
+            ),
+            // Both aggregated nonces are the points at infinity
+            (
+                &[0, 1],
+                &[0, 3],
+                "000000000000000000000000000000000000000000000000000000000000000000\
+                 000000000000000000000000000000000000000000000000000000000000000000",
+                "AE386064B26105404798F75DE2EB9AF5EDA5387B064B83D049CB7C5E08879531",
+            ),
+        ];
+
+        for (key_indices, nonce_indices, expected_aggnonce, expected) in cases {
+            let pubkeys = key_indices
+                .iter()
+                .map(|&idx| pubkey(SIGN_PUBKEYS[idx]))
+                .collect();
+            let key_agg = KeyAggContext::new(&ctx, pubkeys).unwrap();
+
+            let pubnonces = nonce_indices
+                .iter()
+                .map(|&idx| pubnonce(SIGN_PNONCES[idx]))
+                .collect::<Vec<_>>();
+            let agg_nonce = AggNonce::aggregate(&pubnonces).unwrap();
+            assert_eq!(agg_nonce.serialize(), bytes::<66>(expected_aggnonce));
+
+            let session = SigningSession::new(&ctx, key_agg, &agg_nonce, msg).unwrap();
+            let partial = session
+                .partial_sign(&ctx, &seckey, sign_secnonce())
+                .unwrap();
+            assert_eq!(
+                partial.serialize(),
+                bytes::<32>(expected),
+                "key indices {key_indices:?}"
+            );
+
+            session
+                .verify_partial(
+                    &ctx,
+                    &pubkey(SIGN_PUBKEYS[0]),
+                    &pubnonce(SIGN_PNONCES[0]),
+                    &partial,
+                )
+                .expect("partial signature should be valid");
+        }
+    }
+
+    #[test]
+    fn test_verify_fail_vectors() {
+        let ctx = Secp256k1::new();
+        let pubkeys = SIGN_PUBKEYS.map(pubkey);
+        let key_agg = KeyAggContext::new(&ctx, pubkeys.to_vec()).unwrap();
+        let pubnonces = SIGN_PNONCES.map(pubnonce);
+        let agg_nonce = AggNonce::aggregate(&pubnonces[..3]).unwrap();
+        let session = SigningSession::new(&ctx, key_agg, &agg_nonce, bytes(SIGN_MSG)).unwrap();
+
+        let valid = PartialSignature::from_slice(&bytes::<32>(
+            "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB",
+        ))
+        .unwrap();
+        session
+            .verify_partial(&ctx, &pubkeys[0], &pubnonces[0], &valid)
+            .unwrap();
+
+        // Negation of the valid signature
+        let negated = PartialSignature::from_slice(&bytes::<32>(
+            "FED54434AD4CFE953FC527DC6A5E5BE8F6234907B7C187559557CE87A0541C46",
+        ))
+        .unwrap();
+        assert!(matches!(
+            session.verify_partial(&ctx, &pubkeys[0], &pubnonces[0], &negated),
+            Err(Error::InvalidSignature)
+        ));
+
+        // Valid signature attributed to another signer
+        assert!(matches!(
+            session.verify_partial(&ctx, &pubkeys[1], &pubnonces[1], &valid),
+            Err(Error::InvalidSignature)
+        ));
+
+        // Signature exceeding the group size
+        assert!(PartialSignature::from_slice(&CURVE_ORDER).is_err());
+    }
+
+    #[test]
+    fn test_reduce_mod_order() {
+        let mut value = CURVE_ORDER;
+        value[31] += 5;
+
+        let mut expected = [0u8; 32];
+        expected[31] = 5;
+
+        assert_eq!(reduce_mod_order(value), expected);
+        assert_eq!(reduce_mod_order(expected), expected);
+    }
+}
@@ -0,0 +1,119 @@
+//! Intermediate states with byte-string elements (e.g. hashes).
+//!
+//! [`SignedIntermediateState`] signs each element as a number below `2^31`,
+//! so the states keeping arbitrary byte strings on the stack cannot be
+//! committed by it. Here such a state is committed in its unpacked form
+//! (see [`IntermediateState::unpacked`]), where each byte string is
+//! replaced by its bytes, and each byte is signed as a separate number.
+//! [`DisproveScript::new`](super::DisproveScript::new) and the functions
+//! forming disprove scripts of the whole program switch to it whenever
+//! the state has byte strings.
+//!
+//! Tapscript can neither concatenate nor split byte strings, so the
+//! verification script leaves the byte strings unpacked, with the first
+//! byte on top, and the disprove script executes the shard on the unpacked
+//! state. This is sound only if the shard is byte-oriented, that is, the
+//! unpacked state is transformed the same way as the state itself (e.g.
+//! the byte strings are kept untouched below the elements the shard works
+//! with). Otherwise, e.g. for the shard applying `OP_SHA256` or `OP_EQUAL`
+//! to a byte string, even the honest transition would be disprovable, so
+//! the states are signed only together with the shard, see
+//! [`DisproveScript::new_with_byte_strings`](super::DisproveScript::new_with_byte_strings).
+//!
+//! The [`StateLayout`] of the state is known from the honest execution,
+//! so the committed state is recovered exactly, including the lengths of
+//! its byte strings (see [`SignedByteStringState::committed_state`]).
+
+use bitcoin_splitter::split::intermediate_state::{IntermediateState, StateLayout};
+use bitcoin_utils::treepp::*;
+
+use super::{
+    derivation::KeyDerivation,
+    signing::{SignedIntermediateState, SignedStackElement, SignedState},
+};
+
+/// Intermediate state with byte-string elements committed in its unpacked
+/// form, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct SignedByteStringState {
+    /// Kinds of the elements of the original state
+    pub layout: StateLayout,
+    /// Signed unpacked state
+    pub signed: SignedIntermediateState,
+}
+
+impl SignedByteStringState {
+    /// Creates a new [`SignedByteStringState`] from the given intermediate state
+    pub(super) fn sign(state: &IntermediateState) -> eyre::Result<Self> {
+        Ok(Self {
+            layout: state.layout(),
+            signed: SignedIntermediateState::sign(&state.unpacked())?,
+        })
+    }
+
+    /// Creates a new [`SignedByteStringState`] from the given intermediate state,
+    /// deriving the key of each element of the unpacked state from `derivation`
+    /// as the element of the state with index `state_idx`.
+    pub(super) fn sign_derived(
+        state: &IntermediateState,
+        derivation: &KeyDerivation,
+        state_idx: usize,
+    ) -> eyre::Result<Self> {
+        Ok(Self {
+            layout: state.layout(),
+            signed: SignedIntermediateState::sign_derived(
+                &state.unpacked(),
+                derivation,
+                state_idx,
+            )?,
+        })
+    }
+
+    /// Recovers the committed state from the signatures, or returns `None`
+    /// if the signed values do not fit the layout (e.g. a "byte" above 255).
+    pub fn committed_state(&self) -> Option<IntermediateState> {
+        let values = |elements: &[SignedStackElement]| -> Vec<u32> {
+            elements
+                .iter()
+                .map(|element| element.signature.message().into_u32())
+                .collect()
+        };
+
+        self.layout
+            .pack(&values(&self.signed.stack), &values(&self.signed.altstack))
+    }
+}
+
+impl SignedState for SignedByteStringState {
+    fn stack_len(&self) -> usize {
+        self.signed.stack.len()
+    }
+
+    fn altstack_len(&self) -> usize {
+        self.signed.altstack.len()
+    }
+
+    fn witness_script(&self) -> Script {
+        self.signed.witness_script()
+    }
+
+    fn verification_script_toaltstack(&self) -> Script {
+        self.signed.verification_script_toaltstack()
+    }
+}
+
+/// Checks that `function` is byte-oriented on the `from` state: executing
+/// it on the unpacked state results in the unpacked result of executing
+/// it on the state itself. See the [module documentation](self).
+pub fn check_byte_oriented(from: &IntermediateState, function: &Script) -> eyre::Result<()> {
+    let expected = IntermediateState::from_intermediate_result(from, function).unpacked();
+    let actual = IntermediateState::from_intermediate_result(&from.unpacked(), function);
+
+    eyre::ensure!(
+        actual.stack.iter_str().eq(expected.stack.iter_str())
+            && actual.altstack.iter_str().eq(expected.altstack.iter_str()),
+        "shard is not byte-oriented, so the states with byte strings cannot be committed"
+    );
+
+    Ok(())
+}
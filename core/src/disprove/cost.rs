@@ -0,0 +1,161 @@
+//! Cost model of the splitter (see [`CostModel`]) based on the actual
+//! [`DisproveScript`] of the shard.
+//!
+//! The signed states dominate the disprove script, and its size and peak
+//! stack usage depend only on the heights of the states, so they are
+//! computed once per pair of heights from the disprove script with
+//! dummy states and the empty shard.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use bitcoin::{taproot::TAPROOT_CONTROL_MAX_SIZE, Weight};
+use bitcoin_splitter::split::{
+    analysis::StackHeight,
+    core::MAX_STACK_ITEMS,
+    cost::{CostModel, ShardShape},
+    intermediate_state::IntermediateState,
+};
+use bitcoin_utils::treepp::*;
+
+use super::{signing::SignedIntermediateState, witness_elements, DisproveScript};
+use crate::{committee::presigned_leaf, UNSPENDABLE_KEY};
+
+/// Size of the committee signature in the witness, see [`presigned_leaf`]
+const COMMITTEE_SIGNATURE_SIZE: usize = 64;
+
+/// Size and peak stack usage of the disprove script without the shard.
+#[derive(Debug, Clone, Copy)]
+struct Overhead {
+    /// Size of the disprove leaf in bytes
+    script_size: usize,
+    /// Weight of the rest of the witness spending the leaf
+    witness_weight: usize,
+    /// Maximal number of stack and altstack elements during the
+    /// verification of the signed states
+    peak_items: usize,
+}
+
+/// Cost of the shard is the weight of the witness spending the leaf of its
+/// [`DisproveScript`]: the witness elements, the leaf script and the control
+/// block. The leaf is assumed to be guarded by the committee (see
+/// [`presigned_leaf`]) and to be at the maximal depth of the taptree. The
+/// shard is rejected if the disprove script exceeds [`MAX_STACK_ITEMS`]
+/// elements at any point of the execution or the witness weighs more than
+/// the block.
+#[derive(Debug, Default)]
+pub struct DisproveCost {
+    overheads: Mutex<HashMap<(StackHeight, StackHeight), Overhead>>,
+}
+
+impl DisproveCost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Weight of the witness spending the disprove leaf of the shard.
+    pub fn weight(&self, shape: &ShardShape) -> Weight {
+        let overhead = self.overhead(shape.from, shape.to);
+        let script_size = overhead.script_size + shape.shard_size;
+
+        Weight::from_wu(
+            (overhead.witness_weight + compact_size_len(script_size) + script_size) as u64,
+        )
+    }
+
+    /// Maximal number of stack and altstack elements during the execution
+    /// of the disprove script of the shard. During the shard, the state
+    /// after it is kept on the altstack, and the comparison of the states
+    /// needs one more element.
+    pub fn peak_items(&self, shape: &ShardShape) -> usize {
+        self.overhead(shape.from, shape.to)
+            .peak_items
+            .max(shape.to.size() + shape.peak_items)
+            .max(2 * shape.to.size() + 1)
+    }
+
+    fn overhead(&self, from: StackHeight, to: StackHeight) -> Overhead {
+        if let Some(overhead) = self
+            .overheads
+            .lock()
+            .expect("lock is not poisoned")
+            .get(&(from, to))
+        {
+            return *overhead;
+        }
+
+        // Computing without the lock, since it takes a while
+        let from_signed = SignedIntermediateState::sign(&dummy_state(from))
+            .expect("dummy state has no byte strings");
+        let to_signed = SignedIntermediateState::sign(&dummy_state(to))
+            .expect("dummy state has no byte strings");
+        let disprove =
+            DisproveScript::new_from_signed_states(&from_signed, &to_signed, &Script::new());
+        let verification = execute_script(script! {
+            { disprove.script_witness.clone() }
+            { to_signed.verification_script_toaltstack() }
+            { from_signed.verification_script() }
+        });
+
+        // Witness elements are followed by the committee signature, the
+        // leaf script and the control block, each prefixed by its size.
+        // Elements of the dummy states may be empty where the actual ones
+        // are not, so each of them is counted as at least one byte.
+        let elements = witness_elements(&disprove.script_witness);
+        let witness_weight = compact_size_len(elements.len() + 3)
+            + elements
+                .iter()
+                .map(|element| element.len().max(1))
+                .map(|len| compact_size_len(len) + len)
+                .sum::<usize>()
+            + compact_size_len(COMMITTEE_SIGNATURE_SIZE)
+            + COMMITTEE_SIGNATURE_SIZE
+            + compact_size_len(TAPROOT_CONTROL_MAX_SIZE)
+            + TAPROOT_CONTROL_MAX_SIZE;
+
+        let overhead = Overhead {
+            script_size: presigned_leaf(&UNSPENDABLE_KEY, disprove.script_pubkey).len(),
+            witness_weight,
+            peak_items: verification.stats.max_nb_stack_items,
+        };
+        self.overheads
+            .lock()
+            .expect("lock is not poisoned")
+            .insert((from, to), overhead);
+
+        overhead
+    }
+}
+
+impl CostModel for DisproveCost {
+    fn cost(&self, shape: &ShardShape) -> Option<usize> {
+        let weight = self.weight(shape);
+        let valid = self.peak_items(shape) <= MAX_STACK_ITEMS && weight <= Weight::MAX_BLOCK;
+
+        valid.then_some(weight.to_wu() as usize)
+    }
+}
+
+/// Size of the prefix encoding the length `len` in the witness
+fn compact_size_len(len: usize) -> usize {
+    match len {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x10000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+/// State of the given heights consisting of zeros
+fn dummy_state(height: StackHeight) -> IntermediateState {
+    IntermediateState::from_input_script(
+        &script! {
+            for _ in 0..height.altstack {
+                0 OP_TOALTSTACK
+            }
+            for _ in 0..height.stack {
+                0
+            }
+        },
+        &Script::new(),
+    )
+}
@@ -0,0 +1,184 @@
+//! Deterministic derivation of Winternitz keys of the intermediate states.
+//!
+//! Winternitz signatures are one-time, so each element of each state must
+//! be signed with its own key. Keys are derived from a single operator's
+//! master seed as
+//!
+//! ```text
+//! seed = HMAC-SHA256(master_seed, TAG || program_id || instance || state || kind || position)
+//! sk   = SecretKey::from_seed::<_, ChaCha20Rng>(seed)
+//! ```
+//!
+//! where `instance` is the outpoint of the Kickoff connector spent by the
+//! Assert transaction. So the operator has to back up only the master seed
+//! to regenerate all the keys, while the keys of different programs,
+//! Asserts, states and positions never coincide. Signing two different
+//! Asserts with the same keys would reveal the double-signed elements (see
+//! [`super::equivocation`]), so each Assert must have its own instance.
+
+use bitcoin::{
+    hashes::{hmac, sha256, Hash, HashEngine},
+    OutPoint, Txid,
+};
+use bitcoin_splitter::split::script::SplitableScript;
+use bitcoin_winternitz::u32::SecretKey;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::bundle::program_id;
+
+/// Domain separation tag of the derivation.
+const DERIVATION_TAG: &[u8] = b"nero/winternitz-u32/v2";
+
+/// Which stack the element belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackKind {
+    Stack = 0,
+    Altstack = 1,
+}
+
+/// Position of the element in the sequence of states, where state `0` is
+/// the program input and state `i + 1` is the output of shard `i`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElementPath {
+    pub state: usize,
+    pub kind: StackKind,
+    pub position: usize,
+}
+
+/// Derives Winternitz keys of the state elements of one Assert of one
+/// program.
+#[derive(Clone, Copy)]
+pub struct KeyDerivation {
+    master_seed: [u8; 32],
+    program_id: [u8; 32],
+    instance: OutPoint,
+}
+
+impl KeyDerivation {
+    pub fn new(master_seed: [u8; 32], program_id: [u8; 32], instance: OutPoint) -> Self {
+        Self {
+            master_seed,
+            program_id,
+            instance,
+        }
+    }
+
+    /// Construct derivation with program id of `S`, see [`program_id`],
+    /// for the Assert spending the Kickoff connector `instance`.
+    pub fn for_program<S: SplitableScript>(master_seed: [u8; 32], instance: OutPoint) -> Self {
+        Self::new(master_seed, program_id::<S>().to_byte_array(), instance)
+    }
+
+    /// Construct derivation with master seed and instance generated by
+    /// `Rng` from provided `seed`.
+    pub fn from_rng_seed<S, Seed, R>(seed: Seed) -> Self
+    where
+        S: SplitableScript,
+        Seed: Sized + Default + AsMut<[u8]>,
+        R: SeedableRng<Seed = Seed> + Rng,
+    {
+        let mut rng = R::from_seed(seed);
+        let master_seed = rng.gen();
+        let instance = OutPoint::new(Txid::from_byte_array(rng.gen()), 0);
+
+        Self::for_program::<S>(master_seed, instance)
+    }
+
+    pub fn program_id(&self) -> [u8; 32] {
+        self.program_id
+    }
+
+    /// Outpoint of the Kickoff connector the keys are derived for.
+    pub fn instance(&self) -> OutPoint {
+        self.instance
+    }
+
+    /// Seed of the element key at `path`.
+    pub fn element_seed(&self, path: ElementPath) -> [u8; 32] {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(&self.master_seed);
+        engine.input(DERIVATION_TAG);
+        engine.input(&self.program_id);
+        engine.input(self.instance.txid.as_byte_array());
+        engine.input(&self.instance.vout.to_le_bytes());
+        engine.input(&(path.state as u64).to_le_bytes());
+        engine.input(&[path.kind as u8]);
+        engine.input(&(path.position as u64).to_le_bytes());
+
+        hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+    }
+
+    /// Secret key of the element at `path`.
+    pub fn secret_key(&self, path: ElementPath) -> SecretKey {
+        SecretKey::from_seed::<_, ChaCha20Rng>(self.element_seed(path))
+    }
+}
+
+impl std::fmt::Debug for KeyDerivation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the master seed.
+        f.debug_struct("KeyDerivation")
+            .field("program_id", &self.program_id)
+            .field("instance", &self.instance)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use bitcoin_winternitz::u32::Message;
+
+    use super::*;
+
+    #[test]
+    fn test_derived_keys_are_unique_and_reproducible() {
+        let derivation = KeyDerivation::new([7; 32], [1; 32], OutPoint::null());
+
+        let paths = (0..4).flat_map(|state| {
+            [StackKind::Stack, StackKind::Altstack]
+                .into_iter()
+                .flat_map(move |kind| {
+                    (0..8).map(move |position| ElementPath {
+                        state,
+                        kind,
+                        position,
+                    })
+                })
+        });
+
+        let mut seeds = HashSet::new();
+        for path in paths {
+            let seed = derivation.element_seed(path);
+            assert!(seeds.insert(seed), "seed of {path:?} is reused");
+            assert_eq!(seed, derivation.element_seed(path));
+        }
+
+        let path = ElementPath {
+            state: 0,
+            kind: StackKind::Stack,
+            position: 0,
+        };
+        let other_program = KeyDerivation::new([7; 32], [2; 32], OutPoint::null());
+        assert_ne!(
+            derivation.element_seed(path),
+            other_program.element_seed(path)
+        );
+
+        // Another Assert of the same program never reuses the keys
+        let other_instance =
+            KeyDerivation::new([7; 32], [1; 32], OutPoint::new(Txid::all_zeros(), 1));
+        assert_ne!(
+            derivation.element_seed(path),
+            other_instance.element_seed(path)
+        );
+
+        let message = Message::from_u32(42);
+        let signature = derivation.secret_key(path).sign(message);
+        assert!(derivation
+            .secret_key(path)
+            .public_key()
+            .verify(&message, &signature));
+    }
+}
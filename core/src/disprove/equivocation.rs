@@ -0,0 +1,98 @@
+//! Leaf letting anyone spend the **Assert** transaction output if the
+//! operator signed two different values of the same state element.
+//!
+//! Unlike [`super::DisproveScript`], the witness is not known in advance,
+//! as it consists of two conflicting signatures which honest operator never
+//! produces.
+//!
+//! For the same reason the committee cannot presign the spending transaction,
+//! so, unlike the disprove leaves, the equivocation leaves are not guarded by
+//! [`crate::committee::presigned_leaf`]. Both signatures are published by the
+//! operator, so the output is a race: whoever detects the equivocation first
+//! and publishes the spend (e.g. with
+//! [`crate::assert::AssertTransaction::equivocation_transaction`]) takes the
+//! whole stake, wherever it sends it.
+
+use bitcoin_utils::treepp::*;
+use bitcoin_winternitz::u32::{checksig_verify_script, Equivocation, Message, PublicKey};
+use serde::{Deserialize, Serialize};
+
+use super::{signing::SignedIntermediateState, witness_elements};
+
+/// Script spendable with two valid signatures of different messages under
+/// the same Winternitz public key.
+///
+/// ## Witness:
+/// ```bitcoin_script
+/// { Sig(m1) }
+/// { Sig(m2) }
+/// ```
+///
+/// ## Script:
+/// ```bitcoin_script
+/// { pk }
+/// { OP_WINTERNITZVERIFY } // { Sig(m1), Enc(m2) }
+/// { OP_RESTORE }          // { Sig(m1), m2 }
+/// { OP_TOALTSTACK }       // { Sig(m1) }
+/// { pk }
+/// { OP_WINTERNITZVERIFY } // { Enc(m1) }
+/// { OP_RESTORE }          // { m1 }
+/// { OP_FROMALTSTACK }     // { m1, m2 }
+/// { OP_NUMNOTEQUAL }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquivocationScript {
+    pub public_key: PublicKey,
+    pub script_pubkey: Script,
+}
+
+impl EquivocationScript {
+    pub fn new(public_key: PublicKey) -> Self {
+        let script_pubkey = script! {
+            { checksig_verify_script(&public_key) }
+            { Message::recovery_script() }
+            OP_TOALTSTACK
+            { checksig_verify_script(&public_key) }
+            { Message::recovery_script() }
+            OP_FROMALTSTACK
+            OP_NUMNOTEQUAL
+        };
+
+        Self {
+            public_key,
+            script_pubkey,
+        }
+    }
+
+    /// Create script for each element of each state.
+    pub fn from_signed_states(states: &[SignedIntermediateState]) -> Vec<Self> {
+        states
+            .iter()
+            .flat_map(|state| state.stack.iter().chain(state.altstack.iter()))
+            .map(|element| Self::new(element.public_key))
+            .collect()
+    }
+
+    /// Witness of the script from the `equivocation` evidence.
+    ///
+    /// Returns `None` if evidence is for another public key.
+    pub fn witness_script(&self, equivocation: &Equivocation) -> Option<Script> {
+        if *equivocation.public_key() != self.public_key {
+            return None;
+        }
+
+        let (first, second) = equivocation.signatures();
+
+        Some(script! {
+            { first.to_script_sig() }
+            { second.to_script_sig() }
+        })
+    }
+
+    /// The same as [`Self::witness_script`], but returns elements to push
+    /// into transaction witness.
+    pub fn witness_elements(&self, equivocation: &Equivocation) -> Option<Vec<Vec<u8>>> {
+        self.witness_script(equivocation)
+            .map(|script| witness_elements(&script))
+    }
+}
@@ -1,14 +1,25 @@
 use bitcoin::{opcodes::ClassifyContext, script::Instruction};
+use bitcoin_scriptexec::Stack;
 use bitcoin_utils::{comparison::OP_LONGNOTEQUAL, pseudo::OP_LONGFROMALTSTACK, treepp::*};
+use bitcoin_winternitz::u32::{Signature, SCRIPT_SIG_ELEMENTS};
+use serde::{Deserialize, Serialize};
 
-use signing::SignedIntermediateState;
+use byte_strings::SignedByteStringState;
+use cost::DisproveCost;
+use derivation::KeyDerivation;
+use signing::{SignedIntermediateState, SignedState};
 
 use bitcoin_splitter::split::{
     core::SplitType,
+    cost::CostModel,
     intermediate_state::IntermediateState,
     script::{SplitResult, SplitableScript},
 };
 
+pub mod byte_strings;
+pub mod cost;
+pub mod derivation;
+pub mod equivocation;
 pub mod signing;
 
 #[cfg(test)]
@@ -40,7 +51,7 @@ mod tests;
 /// { OP_EQUAL }             // { z[i+1] == fn[i](z[i]) }
 /// { OP_NOT }               // { z[i+1] != fn[i](z[i]) }
 /// ```
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DisproveScript {
     pub script_witness: Script,
     pub script_pubkey: Script,
@@ -49,42 +60,103 @@ pub struct DisproveScript {
 impl DisproveScript {
     /// Given the previous and current states, and the function that was executed,
     /// creates a new DisproveScript according to the BitVM2 protocol.
-    pub fn new(from: &IntermediateState, to: &IntermediateState, function: &Script) -> Self {
-        // Sign the states with the regular entropy randomness
-        let from_signed = SignedIntermediateState::sign(from);
-        let to_signed = SignedIntermediateState::sign(to);
+    ///
+    /// If either state keeps byte strings, the states are committed in their
+    /// unpacked form, see [`Self::new_with_byte_strings`].
+    pub fn new(
+        from: &IntermediateState,
+        to: &IntermediateState,
+        function: &Script,
+    ) -> eyre::Result<Self> {
+        if from.has_byte_strings() || to.has_byte_strings() {
+            return Self::new_with_byte_strings(from, to, function);
+        }
 
-        Self::new_from_signed_states(&from_signed, &to_signed, function)
+        // Sign the states with the regular entropy randomness
+        let from_signed = SignedIntermediateState::sign(from)?;
+        let to_signed = SignedIntermediateState::sign(to)?;
+
+        Ok(Self::new_from_signed_states(
+            &from_signed,
+            &to_signed,
+            function,
+        ))
     }
 
     /// Given the previous and current states, and the function that was executed,
     /// creates a new DisproveScript according to the BitVM2 protocol.
     ///
-    /// The randomness is derived from the `seed`.
-    pub fn new_with_seed<Seed, Rng>(
+    /// The keys are derived from `derivation` for the shard with index
+    /// `shard_idx`, so states shared by adjacent shards are signed with
+    /// the same keys. The states with byte strings are committed the same
+    /// way as in [`Self::new`].
+    pub fn new_derived(
         from: &IntermediateState,
         to: &IntermediateState,
         function: &Script,
-        seed: Seed,
-    ) -> Self
-    where
-        Seed: Sized + Default + AsMut<[u8]> + Copy,
-        Rng: rand::SeedableRng<Seed = Seed> + rand::Rng,
-    {
-        // Sign the states with the seed randomness
-        let from_signed = SignedIntermediateState::sign_with_seed::<Seed, Rng>(from, seed);
-        let to_signed = SignedIntermediateState::sign_with_seed::<Seed, Rng>(to, seed);
+        derivation: &KeyDerivation,
+        shard_idx: usize,
+    ) -> eyre::Result<Self> {
+        if from.has_byte_strings() || to.has_byte_strings() {
+            return Self::new_with_byte_strings_derived(from, to, function, derivation, shard_idx);
+        }
+
+        let from_signed = SignedIntermediateState::sign_derived(from, derivation, shard_idx)?;
+        let to_signed = SignedIntermediateState::sign_derived(to, derivation, shard_idx + 1)?;
+
+        Ok(Self::new_from_signed_states(
+            &from_signed,
+            &to_signed,
+            function,
+        ))
+    }
+
+    /// The same as [`Self::new`], but the states are always committed in
+    /// their unpacked form, which allows byte strings, see [`byte_strings`].
+    ///
+    /// Fails if `function` is not byte-oriented on the `from` state (see
+    /// [`byte_strings::check_byte_oriented`]), since then even the honest
+    /// transition would be disprovable.
+    pub fn new_with_byte_strings(
+        from: &IntermediateState,
+        to: &IntermediateState,
+        function: &Script,
+    ) -> eyre::Result<Self> {
+        byte_strings::check_byte_oriented(from, function)?;
+
+        Ok(Self::new_from_signed_states(
+            &SignedByteStringState::sign(from)?,
+            &SignedByteStringState::sign(to)?,
+            function,
+        ))
+    }
 
-        Self::new_from_signed_states(&from_signed, &to_signed, function)
+    /// The same as [`Self::new_with_byte_strings`], but the keys are derived
+    /// from `derivation` for the shard with index `shard_idx`, see
+    /// [`Self::new_derived`].
+    pub fn new_with_byte_strings_derived(
+        from: &IntermediateState,
+        to: &IntermediateState,
+        function: &Script,
+        derivation: &KeyDerivation,
+        shard_idx: usize,
+    ) -> eyre::Result<Self> {
+        byte_strings::check_byte_oriented(from, function)?;
+
+        Ok(Self::new_from_signed_states(
+            &SignedByteStringState::sign_derived(from, derivation, shard_idx)?,
+            &SignedByteStringState::sign_derived(to, derivation, shard_idx + 1)?,
+            function,
+        ))
     }
 
     /// Given the previous and current states signed, and the function that was executed,
     /// creates a new DisproveScript according to the BitVM2 protocol.
-    fn new_from_signed_states(
-        from: &SignedIntermediateState,
-        to: &SignedIntermediateState,
-        function: &Script,
-    ) -> Self {
+    pub fn new_from_signed_states<F, T>(from: &F, to: &T, function: &Script) -> Self
+    where
+        F: SignedState,
+        T: SignedState,
+    {
         // Step 1.
         // We form the witness script. Just pushing all
         // signatures + messages to the witness script
@@ -102,7 +174,7 @@ impl DisproveScript {
 
             // Step 3.2. Applying function and popping "to" state
             { function.clone() } // This leaves f[i](z[i]).mainstack in the mainstack and { z[i+1].altstack, f[i](z[i]).altstack } in the altstack
-            { OP_LONGFROMALTSTACK(to.altstack.len()) }
+            { OP_LONGFROMALTSTACK(to.altstack_len()) }
             { to.verification_script_fromaltstack() } // This leaves z[i+1].mainstack and f[i](z[i]).mainstack in the mainstack, while f[i](z[i]).altstack and z[i+1].alstack is in the altstack
 
             // Step 3.3.
@@ -110,8 +182,8 @@ impl DisproveScript {
             // { f[i](z[i]).mainstack, f[i](z[i]).altstack, z[i+1].mainstack }
             // while the altstack has z[i+1].altstack.
             // Thus, we have to pick f[i](z[i]).mainstack to the top of the stack
-            for _ in (0..to.stack.len()).rev() {
-                { to.total_len() + to.stack.len() - 1 } OP_ROLL
+            for _ in (0..to.stack_len()).rev() {
+                { to.total_len() + to.stack_len() - 1 } OP_ROLL
             }
 
             // At this point, we should have
@@ -119,19 +191,19 @@ impl DisproveScript {
 
             // Step 3.4. Checking if z[i+1] == f(z[i])
             // a) Mainstack verification
-            { OP_LONGNOTEQUAL(to.stack.len()) }
+            { OP_LONGNOTEQUAL(to.stack_len()) }
 
             // b) Altstack verification
-            { OP_LONGFROMALTSTACK(to.altstack.len()) }
+            { OP_LONGFROMALTSTACK(to.altstack_len()) }
 
             // Since currently our stack looks like:
             // { f[i](z[i]).altstack, {bit}, z[i+1].altstack, },
             // we need to push f[i](z[i]).altstack to the top of the stack
-            for _ in 0..to.altstack.len() {
-                { 2*to.altstack.len() } OP_ROLL
+            for _ in 0..to.altstack_len() {
+                { 2*to.altstack_len() } OP_ROLL
             }
 
-            { OP_LONGNOTEQUAL(to.altstack.len()) }
+            { OP_LONGNOTEQUAL(to.altstack_len()) }
             OP_BOOLOR
         };
 
@@ -143,41 +215,47 @@ impl DisproveScript {
 
     /// Returns the elements of the witness script
     pub fn witness_elements(&self) -> Vec<Vec<u8>> {
-        let mut elements = Vec::with_capacity(self.script_witness.len());
-
-        for instruction in self.script_witness.instructions() {
-            match instruction.unwrap() {
-                Instruction::PushBytes(bytes) => {
-                    elements.push(bytes.as_bytes().to_vec());
-                }
-                Instruction::Op(opcode) => {
-                    match opcode.classify(ClassifyContext::TapScript) {
-                        bitcoin::opcodes::Class::PushNum(num) => {
-                            let buf = num.to_le_bytes().into_iter().filter(|b| *b != 0).collect();
-                            elements.push(buf);
-                        }
-                        _ => {
-                            unreachable!("script witness shouldn't have opcodes, got {opcode}")
-                        }
-                    };
-                }
+        witness_elements(&self.script_witness)
+    }
+}
+
+/// Converts the script consisting only of pushes into the witness elements.
+pub(crate) fn witness_elements(script: &Script) -> Vec<Vec<u8>> {
+    let mut elements = Vec::with_capacity(script.len());
+
+    for instruction in script.instructions() {
+        match instruction.unwrap() {
+            Instruction::PushBytes(bytes) => {
+                elements.push(bytes.as_bytes().to_vec());
+            }
+            Instruction::Op(opcode) => {
+                match opcode.classify(ClassifyContext::TapScript) {
+                    bitcoin::opcodes::Class::PushNum(num) => {
+                        let buf = num.to_le_bytes().into_iter().filter(|b| *b != 0).collect();
+                        elements.push(buf);
+                    }
+                    _ => {
+                        unreachable!("script witness shouldn't have opcodes, got {opcode}")
+                    }
+                };
             }
         }
-
-        elements
     }
+
+    elements
 }
 
 /// Given the `input` script, [`SplitResult`] and `constructor`, does the following:
-/// - For each shard, creates a DisproveScript using `constructor`
-/// - Returns the list of [`DisproveScript`]s.
+/// - For each shard, creates a DisproveScript using `constructor`, which
+///   also receives the index of the shard
+/// - Returns the list of [`DisproveScript`]s, or the first error of `constructor`.
 fn disprove_scripts_with_constructor<F>(
     input: Script,
     split_result: SplitResult,
     constructor: F,
-) -> Vec<DisproveScript>
+) -> eyre::Result<Vec<DisproveScript>>
 where
-    F: Fn(&IntermediateState, &IntermediateState, &Script) -> DisproveScript + Clone,
+    F: Fn(usize, &IntermediateState, &IntermediateState, &Script) -> eyre::Result<DisproveScript>,
 {
     assert_eq!(
         split_result.shards.len(),
@@ -193,6 +271,7 @@ where
             };
 
             constructor(
+                i,
                 &from_state,
                 &split_result.intermediate_states[i],
                 &split_result.shards[i],
@@ -205,9 +284,17 @@ where
 /// - Splits the script into shards
 /// - For each shard, creates a [`DisproveScript`]
 /// - Returns the list of [`DisproveScript`]s
-pub fn form_disprove_scripts<S: SplitableScript>(input: Script) -> Vec<DisproveScript> {
-    let split_result = S::default_split(input.clone(), SplitType::default());
-    disprove_scripts_with_constructor(input, split_result, DisproveScript::new)
+///
+/// Fails if the script cannot be split or some disprove script would be
+/// invalid, see [`check_disprove_costs`].
+pub fn form_disprove_scripts<S: SplitableScript>(
+    input: Script,
+) -> eyre::Result<Vec<DisproveScript>> {
+    let split_result = split_checked::<S>(&input)?;
+
+    disprove_scripts_with_constructor(input, split_result, |_, from, to, shard| {
+        DisproveScript::new(from, to, shard)
+    })
 }
 
 /// Given the script and its input, does the following:
@@ -216,21 +303,26 @@ pub fn form_disprove_scripts<S: SplitableScript>(input: Script) -> Vec<DisproveS
 ///   two state transitions incorrect
 /// - For each shard, creates a [`DisproveScript`]
 /// - Returns the list of [`DisproveScript`]s and the index of distorted shard
+///
+/// Fails if the script cannot be split or some disprove script would be
+/// invalid, see [`check_disprove_costs`].
 pub fn form_disprove_scripts_distorted<S: SplitableScript>(
     input: Script,
-) -> (Vec<DisproveScript>, usize) {
+) -> eyre::Result<(Vec<DisproveScript>, usize)> {
     // Splitting the script into shards
-    let split_result = S::default_split(input.clone(), SplitType::default());
+    let split_result = split_checked::<S>(&input)?;
 
     // Distorting the output of the random shard
     let (distorted_split_result, distorted_shard_id) = split_result.distort();
 
     // Creating the disprove scripts
     let disprove_scripts =
-        disprove_scripts_with_constructor(input, distorted_split_result, DisproveScript::new);
+        disprove_scripts_with_constructor(input, distorted_split_result, |_, from, to, shard| {
+            DisproveScript::new(from, to, shard)
+        })?;
 
     // Returning the result
-    (disprove_scripts, distorted_shard_id)
+    Ok((disprove_scripts, distorted_shard_id))
 }
 
 /// Given the script and its input, does the following:
@@ -238,22 +330,71 @@ pub fn form_disprove_scripts_distorted<S: SplitableScript>(
 /// - For each shard, creates a [`DisproveScript`]
 /// - Returns the list of [`DisproveScript`]s
 ///
-/// The randomness is derived from the `seed`.
+/// The keys are derived from the master seed and instance generated by `Rng`
+/// from `seed` (see [`KeyDerivation::from_rng_seed`]).
+///
+/// Fails if some disprove script would be invalid, see [`check_disprove_costs`].
 pub fn form_disprove_scripts_with_seed<S, Seed, Rng>(
     input: Script,
     seed: Seed,
-) -> Vec<DisproveScript>
+) -> eyre::Result<Vec<DisproveScript>>
 where
     S: SplitableScript,
     Seed: Sized + Default + AsMut<[u8]> + Copy,
     Rng: rand::SeedableRng<Seed = Seed> + rand::Rng,
 {
-    let split_result = S::default_split(input.clone(), SplitType::default());
-    disprove_scripts_with_constructor(input, split_result, |from, to, shard| {
-        DisproveScript::new_with_seed::<Seed, Rng>(from, to, shard, seed)
+    form_disprove_scripts_derived::<S>(input, &KeyDerivation::from_rng_seed::<S, Seed, Rng>(seed))
+}
+
+/// Given the script and its input, does the following:
+/// - Splits the script into shards
+/// - For each shard, creates a [`DisproveScript`]
+/// - Returns the list of [`DisproveScript`]s
+///
+/// The keys are derived from `derivation`.
+///
+/// Fails if some disprove script would be invalid, see [`check_disprove_costs`].
+pub fn form_disprove_scripts_derived<S: SplitableScript>(
+    input: Script,
+    derivation: &KeyDerivation,
+) -> eyre::Result<Vec<DisproveScript>> {
+    let split_result = split_checked::<S>(&input)?;
+
+    disprove_scripts_with_constructor(input, split_result, |idx, from, to, shard| {
+        DisproveScript::new_derived(from, to, shard, derivation, idx)
     })
 }
 
+/// Checks that the disprove script of each shard of `split_result`
+/// executed on `input` fits into [`MAX_STACK_ITEMS`] and its witness fits
+/// into the block, see [`DisproveCost`].
+///
+/// [`MAX_STACK_ITEMS`]: bitcoin_splitter::split::core::MAX_STACK_ITEMS
+pub fn check_disprove_costs(input: &Script, split_result: &SplitResult) -> eyre::Result<()> {
+    let cost_model = DisproveCost::new();
+
+    for shape in split_result.shapes(input) {
+        eyre::ensure!(
+            cost_model.cost(&shape).is_some(),
+            "disprove script of shard {} is invalid: its witness weighs {} WU and it takes up to {} stack items",
+            shape.index,
+            cost_model.weight(&shape).to_wu(),
+            cost_model.peak_items(&shape)
+        );
+    }
+
+    Ok(())
+}
+
+/// Splits the script with [`SplitableScript::default_split`] and checks
+/// the disprove scripts of the shards, see [`check_disprove_costs`].
+fn split_checked<S: SplitableScript>(input: &Script) -> eyre::Result<SplitResult> {
+    let split_result = S::default_split(input.clone(), SplitType::default())?;
+    check_disprove_costs(input, &split_result)?;
+
+    Ok(split_result)
+}
+
 /// Given the script and its input, does the following:
 /// - Splits the script into shards
 /// - Distorts the random intermediate state, making
@@ -261,28 +402,312 @@ where
 /// - For each shard, creates a [`DisproveScript`]
 /// - Returns the list of [`DisproveScript`]s and the index of distorted shard
 ///
-/// The randomness is derived from the `seed`.
+/// The keys are derived from the master seed and instance generated by `Rng`
+/// from `seed` (see [`KeyDerivation::from_rng_seed`]).
+///
+/// Fails if the script cannot be split or some disprove script would be
+/// invalid, see [`check_disprove_costs`].
 pub fn form_disprove_scripts_distorted_with_seed<S, Seed, Rng>(
     input: Script,
     seed: Seed,
-) -> (Vec<DisproveScript>, usize)
+) -> eyre::Result<(Vec<DisproveScript>, usize)>
 where
     S: SplitableScript,
     Seed: Sized + Default + AsMut<[u8]> + Copy,
     Rng: rand::SeedableRng<Seed = Seed> + rand::Rng,
 {
     // Splitting the script into shards
-    let split_result = S::default_split(input.clone(), SplitType::default());
+    let split_result = split_checked::<S>(&input)?;
 
     // Distorting the output of the random shard
     let (distorted_split_result, distorted_shard_id) = split_result.distort();
 
     // Creating the disprove scripts
     let disprove_scripts =
-        disprove_scripts_with_constructor(input, distorted_split_result, |from, to, shard| {
-            DisproveScript::new_with_seed::<Seed, Rng>(from, to, shard, seed)
-        });
+        derived_disprove_scripts::<S, Seed, Rng>(input, distorted_split_result, seed)?;
 
     // Returning the result
-    (disprove_scripts, distorted_shard_id)
+    Ok((disprove_scripts, distorted_shard_id))
+}
+
+/// Which intermediate state to distort in
+/// [`form_disprove_scripts_distorted_by`].
+///
+/// **WARNING**: Distortion is used for testing purposes only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distortion {
+    /// Distort the output state of the shard with provided index.
+    Shard(usize),
+    /// Distort the output state of the shard chosen randomly from the seed.
+    Seed(u64),
+}
+
+/// The same as [`form_disprove_scripts_distorted_with_seed`], but the
+/// distorted shard is chosen by `distortion` and the keys are derived from
+/// `derivation`.
+///
+/// Fails if the shard index is out of bounds or some disprove script would
+/// be invalid, see [`check_disprove_costs`].
+pub fn form_disprove_scripts_distorted_by<S: SplitableScript>(
+    input: Script,
+    distortion: Distortion,
+    derivation: &KeyDerivation,
+) -> eyre::Result<(Vec<DisproveScript>, usize)> {
+    let split_result = split_checked::<S>(&input)?;
+
+    let (distorted_split_result, distorted_shard_id) = match distortion {
+        Distortion::Shard(idx) => {
+            eyre::ensure!(
+                idx < split_result.len(),
+                "shard index {idx} is out of bounds, there are {} shards",
+                split_result.len()
+            );
+
+            (split_result.distort_at(idx), idx)
+        }
+        Distortion::Seed(distortion_seed) => {
+            let mut rng =
+                <rand::rngs::SmallRng as rand::SeedableRng>::seed_from_u64(distortion_seed);
+            split_result.distort_with_rng(&mut rng)
+        }
+    };
+
+    let disprove_scripts = disprove_scripts_with_constructor(
+        input,
+        distorted_split_result,
+        |idx, from, to, shard| DisproveScript::new_derived(from, to, shard, derivation, idx),
+    )?;
+
+    Ok((disprove_scripts, distorted_shard_id))
+}
+
+fn derived_disprove_scripts<S, Seed, Rng>(
+    input: Script,
+    split_result: SplitResult,
+    seed: Seed,
+) -> eyre::Result<Vec<DisproveScript>>
+where
+    S: SplitableScript,
+    Seed: Sized + Default + AsMut<[u8]> + Copy,
+    Rng: rand::SeedableRng<Seed = Seed> + rand::Rng,
+{
+    let derivation = KeyDerivation::from_rng_seed::<S, Seed, Rng>(seed);
+
+    disprove_scripts_with_constructor(input, split_result, |idx, from, to, shard| {
+        DisproveScript::new_derived(from, to, shard, &derivation, idx)
+    })
+}
+
+/// Given the script, its input and disprove scripts published by the
+/// operator, recovers the intermediate states (z values) committed in the
+/// witnesses of disprove scripts.
+///
+/// The number of stack and altstack elements of each state and the lengths
+/// of its byte strings (see [`byte_strings`]) are taken from the honest
+/// execution of the script.
+pub fn committed_states<S: SplitableScript>(
+    input: Script,
+    disprove_scripts: &[DisproveScript],
+) -> eyre::Result<Vec<IntermediateState>> {
+    let split_result = S::default_split(input, SplitType::default())?;
+
+    committed_values_of(&split_result, disprove_scripts)?
+        .into_iter()
+        .zip(split_result.intermediate_states.iter())
+        .enumerate()
+        .map(|(idx, ((stack, altstack), honest_state))| {
+            honest_state
+                .layout()
+                .pack(&stack, &altstack)
+                .ok_or_else(|| {
+                    eyre::eyre!("state committed in disprove script {idx} does not fit its layout")
+                })
+        })
+        .collect()
+}
+
+/// Recovers the values of the unpacked state (see
+/// [`IntermediateState::unpacked`]) committed in the witness of each
+/// disprove script as the stack and altstack values.
+fn committed_values_of(
+    split_result: &SplitResult,
+    disprove_scripts: &[DisproveScript],
+) -> eyre::Result<Vec<(Vec<u32>, Vec<u32>)>> {
+    eyre::ensure!(
+        split_result.len() == disprove_scripts.len(),
+        "expected {} disprove scripts, got {}",
+        split_result.len(),
+        disprove_scripts.len()
+    );
+
+    disprove_scripts
+        .iter()
+        .zip(split_result.intermediate_states.iter())
+        .enumerate()
+        .map(|(idx, (disprove, honest_state))| {
+            let layout = honest_state.layout();
+            let stack_len = layout.unpacked_stack_len();
+            let altstack_len = layout.unpacked_altstack_len();
+
+            // The "to" state is pushed the last one, so it's at the end
+            // of the witness.
+            let elements = disprove.witness_elements();
+            let to_len = (stack_len + altstack_len) * SCRIPT_SIG_ELEMENTS;
+            eyre::ensure!(
+                elements.len() >= to_len,
+                "witness of disprove script {idx} is too short"
+            );
+
+            let values = elements[elements.len() - to_len..]
+                .chunks(SCRIPT_SIG_ELEMENTS)
+                .map(|elements| {
+                    Signature::from_script_sig_elements(elements)
+                        .map(|signature| signature.message().into_u32())
+                        .ok_or_else(|| eyre::eyre!("malformed signature in disprove script {idx}"))
+                })
+                .collect::<eyre::Result<Vec<_>>>()?;
+
+            // Altstack elements are pushed in reversed order.
+            let (stack, altstack) = values.split_at(stack_len);
+
+            Ok((stack.to_vec(), altstack.iter().rev().copied().collect()))
+        })
+        .collect()
+}
+
+/// Given the script, its input and disprove scripts published by the
+/// operator, does the following:
+/// - Recovers the committed intermediate states in their unpacked form,
+///   which is what the disprove scripts work with (see [`byte_strings`])
+/// - Executes each shard starting from the previous committed state
+/// - Compares the result with the next committed state
+/// - Executes the disprove scripts of the incorrect transitions with their
+///   witnesses
+/// - Returns indexes of all disprove scripts whose execution succeeds, thus
+///   which are spendable.
+pub fn find_disprovable_shards<S: SplitableScript>(
+    input: Script,
+    disprove_scripts: &[DisproveScript],
+) -> eyre::Result<Vec<usize>> {
+    let split_result = S::default_split(input.clone(), SplitType::default())?;
+    let states = committed_values_of(&split_result, disprove_scripts)?
+        .into_iter()
+        .map(|(stack, altstack)| unpacked_state(&stack, &altstack))
+        .collect::<Vec<_>>();
+
+    let input_state = IntermediateState::from_inject_script(&input).unpacked();
+
+    let disprovable = (0..split_result.shards.len())
+        .filter(|&idx| {
+            let from_state = match idx {
+                0 => &input_state,
+                _ => &states[idx - 1],
+            };
+
+            let expected =
+                IntermediateState::from_intermediate_result(from_state, &split_result.shards[idx]);
+            let committed = &states[idx];
+
+            !expected.stack.iter_str().eq(committed.stack.iter_str())
+                || !expected
+                    .altstack
+                    .iter_str()
+                    .eq(committed.altstack.iter_str())
+        })
+        .filter(|&idx| {
+            let disprove = &disprove_scripts[idx];
+            execute_script(script! {
+                { disprove.script_witness.clone() }
+                { disprove.script_pubkey.clone() }
+            })
+            .success
+        })
+        .collect();
+
+    Ok(disprovable)
+}
+
+/// The unpacked state (see [`IntermediateState::unpacked`]) with the
+/// given values.
+fn unpacked_state(stack: &[u32], altstack: &[u32]) -> IntermediateState {
+    let push = |values: &[u32]| {
+        let mut stack = Stack::new();
+        for value in values {
+            stack.pushnum((*value).into());
+        }
+        stack
+    };
+
+    IntermediateState {
+        stack: push(stack),
+        altstack: push(altstack),
+    }
+}
+
+/// Signs the `input` state and each intermediate state of `split_result`
+/// with the keys derived from `derivation`, where the first one is the input
+/// state. The states with byte strings are signed in their unpacked form,
+/// see [`byte_strings`].
+///
+/// Fails if some shard with byte strings in its states is not byte-oriented,
+/// see [`byte_strings::check_byte_oriented`].
+pub fn sign_states_derived(
+    input: &Script,
+    split_result: &SplitResult,
+    derivation: &KeyDerivation,
+) -> eyre::Result<Vec<SignedIntermediateState>> {
+    let states = std::iter::once(IntermediateState::from_inject_script(input))
+        .chain(split_result.intermediate_states.iter().cloned())
+        .collect::<Vec<_>>();
+
+    for (states, shard) in states.windows(2).zip(split_result.shards.iter()) {
+        if states[0].has_byte_strings() || states[1].has_byte_strings() {
+            byte_strings::check_byte_oriented(&states[0], shard)?;
+        }
+    }
+
+    states
+        .iter()
+        .enumerate()
+        .map(|(idx, state)| {
+            if state.has_byte_strings() {
+                SignedByteStringState::sign_derived(state, derivation, idx)
+                    .map(|state| state.signed)
+            } else {
+                SignedIntermediateState::sign_derived(state, derivation, idx)
+            }
+        })
+        .collect()
+}
+
+/// Given the script and its input, does the following:
+/// - Splits the script into shards
+/// - Signs the input state and each intermediate state once, so the same
+///   signatures can be committed in the **Assert** transaction witness (see
+///   [`crate::assert::commitment`])
+/// - For each shard, creates a [`DisproveScript`]
+/// - Returns the list of [`DisproveScript`]s and the signed states, where
+///   the first one is the input state
+///
+/// The states are signed with the keys derived from `derivation`, see
+/// [`sign_states_derived`].
+///
+/// Fails if the script cannot be split, the states cannot be signed or
+/// some disprove script would be invalid, see [`check_disprove_costs`].
+pub fn form_disprove_scripts_with_signed_states<S: SplitableScript>(
+    input: Script,
+    derivation: &KeyDerivation,
+) -> eyre::Result<(Vec<DisproveScript>, Vec<SignedIntermediateState>)> {
+    let split_result = split_checked::<S>(&input)?;
+    let signed_states = sign_states_derived(&input, &split_result, derivation)?;
+
+    let disprove_scripts = signed_states
+        .windows(2)
+        .zip(split_result.shards.iter())
+        .map(|(states, shard)| {
+            DisproveScript::new_from_signed_states(&states[0], &states[1], shard)
+        })
+        .collect();
+
+    Ok((disprove_scripts, signed_states))
 }
@@ -4,6 +4,8 @@ use bitcoin_splitter::split::intermediate_state::IntermediateState;
 use bitcoin_winternitz::u32::{checksig_verify_script, Message, PublicKey, SecretKey, Signature};
 use rand::{rngs::SmallRng, SeedableRng};
 
+use super::derivation::{ElementPath, KeyDerivation, StackKind};
+
 /// Maximum value of the stack element
 const MAX_STACK_ELEMENT_VALUE: u32 = (1 << 31) - 1;
 
@@ -21,7 +23,7 @@ pub struct SignedStackElement {
 
 impl SignedStackElement {
     /// Creates a new [`SignedStackElement`] by signing the given `u32` stack element
-    fn sign(stack_element: u32) -> Self {
+    pub(super) fn sign(stack_element: u32) -> Self {
         // Initializing a random secret key from the entropy
         // TODO(@ZamDimon): Reconsider rng usage
         let mut rng = SmallRng::from_entropy();
@@ -30,19 +32,7 @@ impl SignedStackElement {
         Self::sign_with_secret_key(secret_key, stack_element)
     }
 
-    /// Creates a new [`SignedStackElement`] by signing the given `u32` stack element
-    /// using the `Rng` and `Seed` provided as the generic parameter.
-    pub fn sign_with_seed<Seed, Rng>(stack_element: u32, seed: Seed) -> Self
-    where
-        Seed: Sized + Default + AsMut<[u8]>,
-        Rng: rand::SeedableRng<Seed = Seed> + rand::Rng,
-    {
-        // Initializing a random secret key from the seed
-        let secret_key = SecretKey::from_seed::<Seed, Rng>(seed);
-        Self::sign_with_secret_key(secret_key, stack_element)
-    }
-
-    fn sign_with_secret_key(secret_key: SecretKey, stack_element: u32) -> Self {
+    pub(super) fn sign_with_secret_key(secret_key: SecretKey, stack_element: u32) -> Self {
         // Deriving a public key
         let public_key = secret_key.public_key();
 
@@ -66,6 +56,14 @@ impl SignedStackElement {
 /// u32 values (both in mainstack and altstack), but this struct
 /// also contains the public keys, secret keys, and signatures
 /// of the elements in the state array.
+///
+/// Intermediate states are always committed element by element. Committing
+/// only the hash of the state is not supported: checking the revealed state
+/// against the signed digest with the SHA-256 script from
+/// `bitcoin-testscripts` takes hundreds of kilobytes per 64-byte block, which
+/// is more than the per-element checks of any state under the stack limit,
+/// and the challengers could not recover such a state from the Assert witness
+/// (see [`crate::assert::commitment`]).
 #[derive(Clone, Debug)]
 pub struct SignedIntermediateState {
     pub stack: Vec<SignedStackElement>,
@@ -73,43 +71,70 @@ pub struct SignedIntermediateState {
 }
 
 impl SignedIntermediateState {
-    /// Creates a new [`SignedIntermediateState`] from the given intermediate state
-    pub fn sign(state: &IntermediateState) -> Self {
-        Self::sign_fn(state, SignedStackElement::sign)
+    /// Creates a new [`SignedIntermediateState`] from the given intermediate state.
+    ///
+    /// Fails if the state keeps byte strings, which are committed by
+    /// [`SignedByteStringState`](super::byte_strings::SignedByteStringState).
+    pub fn sign(state: &IntermediateState) -> eyre::Result<Self> {
+        Self::sign_fn(state, |_, _, element| SignedStackElement::sign(element))
     }
 
-    /// Creates a new [`SignedIntermediateState`] from the given intermediate state
-    pub fn sign_with_seed<Seed, Rng>(state: &IntermediateState, seed: Seed) -> Self
-    where
-        Seed: Sized + Default + AsMut<[u8]> + Copy,
-        Rng: rand::SeedableRng<Seed = Seed> + rand::Rng,
-    {
-        Self::sign_fn(state, |element| {
-            SignedStackElement::sign_with_seed::<Seed, Rng>(element, seed)
+    /// Creates a new [`SignedIntermediateState`] from the given intermediate state,
+    /// deriving the key of each element from `derivation` as the element of the
+    /// state with index `state_idx`. Fails the same way as [`Self::sign`].
+    pub fn sign_derived(
+        state: &IntermediateState,
+        derivation: &KeyDerivation,
+        state_idx: usize,
+    ) -> eyre::Result<Self> {
+        Self::sign_fn(state, |kind, position, element| {
+            let secret_key = derivation.secret_key(ElementPath {
+                state: state_idx,
+                kind,
+                position,
+            });
+            SignedStackElement::sign_with_secret_key(secret_key, element)
         })
     }
 
     /// Creates a new [`SignedIntermediateState`] based on the signing function provided.
     ///
     /// The function takes the mainstack and altstack, converts them to the array of
-    /// `u32` elements and applies the signing function to each element.
-    fn sign_fn<F>(state: &IntermediateState, sign_fn: F) -> Self
+    /// `u32` elements and applies the signing function to each element together
+    /// with its stack kind and position.
+    fn sign_fn<F>(state: &IntermediateState, sign_fn: F) -> eyre::Result<Self>
     where
-        F: Fn(u32) -> SignedStackElement + Clone,
+        F: Fn(StackKind, usize, u32) -> SignedStackElement,
     {
+        eyre::ensure!(
+            !state.has_byte_strings(),
+            "state has byte strings, which are committed by SignedByteStringState"
+        );
+
         let stack = state.to_bytes().stack_as_u32();
         let altstack = state.to_bytes().altstack_as_u32();
 
         // Now, verifying that all elements are below 1<<31 - 1
         for element in stack.iter().chain(altstack.iter()) {
-            assert!(*element <= MAX_STACK_ELEMENT_VALUE, "element is too large");
+            eyre::ensure!(
+                *element <= MAX_STACK_ELEMENT_VALUE,
+                "element {element} is too large to be signed"
+            );
         }
 
         // Signing each element
-        let stack = stack.into_iter().map(sign_fn.clone()).collect();
-        let altstack = altstack.into_iter().map(sign_fn).collect();
+        let stack = stack
+            .into_iter()
+            .enumerate()
+            .map(|(position, element)| sign_fn(StackKind::Stack, position, element))
+            .collect();
+        let altstack = altstack
+            .into_iter()
+            .enumerate()
+            .map(|(position, element)| sign_fn(StackKind::Altstack, position, element))
+            .collect();
 
-        Self { stack, altstack }
+        Ok(Self { stack, altstack })
     }
 
     /// Returns the total length of the stack and altstack
@@ -178,3 +203,75 @@ impl SignedIntermediateState {
         }
     }
 }
+
+/// State committed in the witness of the disprove script, see
+/// [`super::DisproveScript`].
+pub trait SignedState {
+    /// Number of the mainstack elements of the state
+    fn stack_len(&self) -> usize;
+
+    /// Number of the altstack elements of the state
+    fn altstack_len(&self) -> usize;
+
+    /// Returns the total length of the stack and altstack
+    fn total_len(&self) -> usize {
+        self.stack_len() + self.altstack_len()
+    }
+
+    /// Script pushing the commitment of the state to the stack.
+    fn witness_script(&self) -> Script;
+
+    /// Script verifying the witness script, which leaves the altstack
+    /// elements of the state followed by its stack elements in the
+    /// reversed order in the altstack.
+    fn verification_script_toaltstack(&self) -> Script;
+
+    /// Script that pops the stack elements of the state from the
+    /// altstack after [`Self::verification_script_toaltstack`].
+    fn verification_script_fromaltstack(&self) -> Script {
+        script! {
+            for _ in 0..self.stack_len() {
+                OP_FROMALTSTACK
+            }
+        }
+    }
+
+    /// Script that verifies the witness script, leaving the original
+    /// stack and altstack of the state.
+    fn verification_script(&self) -> Script {
+        script! {
+            { self.verification_script_toaltstack() }
+            { self.verification_script_fromaltstack() }
+        }
+    }
+}
+
+impl SignedState for SignedIntermediateState {
+    fn stack_len(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn altstack_len(&self) -> usize {
+        self.altstack.len()
+    }
+
+    fn total_len(&self) -> usize {
+        SignedIntermediateState::total_len(self)
+    }
+
+    fn witness_script(&self) -> Script {
+        SignedIntermediateState::witness_script(self)
+    }
+
+    fn verification_script_toaltstack(&self) -> Script {
+        SignedIntermediateState::verification_script_toaltstack(self)
+    }
+
+    fn verification_script_fromaltstack(&self) -> Script {
+        SignedIntermediateState::verification_script_fromaltstack(self)
+    }
+
+    fn verification_script(&self) -> Script {
+        SignedIntermediateState::verification_script(self)
+    }
+}
@@ -1,13 +1,25 @@
 use std::{fs, path::Path, str::FromStr as _};
 
-use crate::disprove::{form_disprove_scripts_distorted, DisproveScript};
+use crate::disprove::{
+    byte_strings::SignedByteStringState, check_disprove_costs, committed_states,
+    cost::DisproveCost, derivation::KeyDerivation, equivocation::EquivocationScript,
+    find_disprovable_shards, form_disprove_scripts_derived, form_disprove_scripts_distorted,
+    form_disprove_scripts_distorted_by, form_disprove_scripts_with_signed_states,
+    signing::SignedState, DisproveScript, Distortion,
+};
 
 use bitcoin::{
-    consensus::Encodable as _, hashes::Hash as _, key::Secp256k1, secp256k1::SecretKey, Amount,
-    OutPoint, TxOut, WPubkeyHash,
+    consensus::Encodable as _,
+    hashes::{sha256, Hash as _},
+    key::{rand::thread_rng, Secp256k1},
+    secp256k1::SecretKey,
+    taproot::TAPROOT_CONTROL_MAX_SIZE,
+    Amount, OutPoint, TapSighash, TxOut, Txid, WPubkeyHash, Witness,
 };
+use bitcoin_scriptexec::ExecError;
 use bitcoin_splitter::split::{
-    core::SplitType,
+    core::{naive_split, SplitType, MAX_STACK_ITEMS},
+    cost::CostModel,
     intermediate_state::IntermediateState,
     script::{IOPair, SplitableScript},
 };
@@ -19,11 +31,24 @@ use bitcoin_testscripts::{
 use bitcoin_utils::stack_to_script;
 use bitcoin_utils::{comparison::OP_LONGEQUALVERIFY, treepp::*};
 use bitcoin_window_mul::{bigint::U508, traits::comparable::Comparable};
+use bitcoin_winternitz::u32::{Equivocation, Message, SCRIPT_SIG_ELEMENTS};
 use once_cell::sync::Lazy;
+use rand::rngs::SmallRng;
 
 use crate::{
-    assert::AssertTransaction, disprove::form_disprove_scripts,
+    assert::{
+        commitment::{disprove_witness, StatesCommitment, MAX_ELEMENTS_PER_INPUT},
+        payout_script::PayoutScript,
+        AssertTransaction, Options,
+    },
+    committee::{
+        musig2::{self, SecNonce},
+        presigned_leaf, verify_signature, Committee,
+    },
+    disprove::form_disprove_scripts,
     disprove::signing::SignedIntermediateState,
+    test_utils::{committee_sign, execute_script_spend},
+    UNSPENDABLE_KEY,
 };
 
 #[test]
@@ -38,7 +63,7 @@ pub fn test_stack_sign_and_verify() {
     );
 
     // Now, we sign the state
-    let signed_state = SignedIntermediateState::sign(&state);
+    let signed_state = SignedIntermediateState::sign(&state).unwrap();
 
     // Check that witness + verification scripts are correct
     let verify_script = script! {
@@ -67,7 +92,7 @@ pub fn test_zero_stack_sign_and_verify() {
     );
 
     // Now, we sign the state
-    let signed_state = SignedIntermediateState::sign(&state);
+    let signed_state = SignedIntermediateState::sign(&state).unwrap();
 
     // Check that witness + verification scripts are correct
     let verify_script = script! {
@@ -97,7 +122,7 @@ pub fn test_stack_sign_and_verify_with_altstack() {
     );
 
     // Now, we sign the state
-    let signed_state = SignedIntermediateState::sign(&state);
+    let signed_state = SignedIntermediateState::sign(&state).unwrap();
 
     // Check that witness + verification scripts are correct
     let verify_script = script! {
@@ -122,11 +147,11 @@ pub fn test_stack_sign_and_verify_bigint() {
     let IOPair { input, output: _ } = U254MulScript::generate_valid_io_pair();
 
     // Splitting the script into shards
-    let split_result = U254MulScript::default_split(input, SplitType::ByInstructions);
+    let split_result = U254MulScript::default_split(input, SplitType::ByInstructions).unwrap();
 
     for (i, intermediate_state) in split_result.intermediate_states.into_iter().enumerate() {
         // Now, we sign the state
-        let signed_state = SignedIntermediateState::sign(&intermediate_state.clone());
+        let signed_state = SignedIntermediateState::sign(&intermediate_state.clone()).unwrap();
 
         // Check that witness + verification scripts are correct
         let verify_script = script! {
@@ -165,7 +190,7 @@ pub fn test_trivial_disprove_script_success() {
     };
 
     // Now, form the disprove script
-    let disprove_script = DisproveScript::new(&state_from, &state_to, &function);
+    let disprove_script = DisproveScript::new(&state_from, &state_to, &function).unwrap();
 
     // Check that witness + verification scripts are satisfied
     let verify_script = script! {
@@ -200,7 +225,7 @@ pub fn test_trivial_disprove_script_should_fail() {
     };
 
     // Now, form the disprove script
-    let disprove_script = DisproveScript::new(&state_from, &state_to, &function);
+    let disprove_script = DisproveScript::new(&state_from, &state_to, &function).unwrap();
 
     // Check that witness + verification scripts are satisfied
     let verify_script = script! {
@@ -236,7 +261,7 @@ pub fn test_disprove_script_with_altstack_should_fail() {
     };
 
     // Now, form the disprove script
-    let disprove_script = DisproveScript::new(&state_from, &state_to, &function);
+    let disprove_script = DisproveScript::new(&state_from, &state_to, &function).unwrap();
 
     // Check that witness + verification scripts are satisfied
     let verify_script = script! {
@@ -272,7 +297,7 @@ pub fn test_disprove_script_with_altstack_success() {
     };
 
     // Now, form the disprove script
-    let disprove_script = DisproveScript::new(&state_from, &state_to, &function);
+    let disprove_script = DisproveScript::new(&state_from, &state_to, &function).unwrap();
 
     // Check that witness + verification scripts are satisfied
     let verify_script = script! {
@@ -308,7 +333,7 @@ pub fn test_disprove_script_with_altstack_2() {
     };
 
     // Now, form the disprove script
-    let disprove_script = DisproveScript::new(&state_from, &state_to, &function);
+    let disprove_script = DisproveScript::new(&state_from, &state_to, &function).unwrap();
 
     // Check that witness + verification scripts are satisfied
     let verify_script = script! {
@@ -327,7 +352,7 @@ pub fn test_disprove_script_mul_script() {
     let IOPair { input, output } = U254MulScript::generate_invalid_io_pair();
 
     // Splitting the script into shards
-    let split_result = U254MulScript::default_split(input, SplitType::ByInstructions);
+    let split_result = U254MulScript::default_split(input, SplitType::ByInstructions).unwrap();
 
     // Checking the last state (which must be equal to the result of the multiplication)
     let last_state = split_result.must_last_state();
@@ -348,7 +373,8 @@ pub fn test_disprove_script_mul_script() {
             &split_result.intermediate_states[i],
             &split_result.intermediate_states[i + 1],
             &split_result.shards[i + 1],
-        );
+        )
+        .unwrap();
 
         // Check that witness + verification scripts are satisfied
         let verify_script = script! {
@@ -371,7 +397,7 @@ pub fn test_disprove_script_fibonacci_script_invalid_input() {
 
     // Splitting the script into shards
     let split_result =
-        SquareFibonacciScript::<STEPS>::default_split(input, SplitType::ByInstructions);
+        SquareFibonacciScript::<STEPS>::default_split(input, SplitType::ByInstructions).unwrap();
 
     // Checking the last state (which must be equal to the result of the multiplication)
     let last_state = split_result.must_last_state();
@@ -392,7 +418,8 @@ pub fn test_disprove_script_fibonacci_script_invalid_input() {
             &split_result.intermediate_states[i],
             &split_result.intermediate_states[i + 1],
             &split_result.shards[i + 1],
-        );
+        )
+        .unwrap();
 
         // Check that witness + verification scripts are satisfied
         let verify_script = script! {
@@ -415,7 +442,7 @@ pub fn test_disprove_script_fibonacci_script_valid_input() {
 
     // Splitting the script into shards
     let split_result =
-        SquareFibonacciScript::<STEPS>::default_split(input, SplitType::ByInstructions);
+        SquareFibonacciScript::<STEPS>::default_split(input, SplitType::ByInstructions).unwrap();
 
     // Now, we form the disprove script for each shard
     for i in 0..(split_result.shards.len() - 1) {
@@ -423,7 +450,8 @@ pub fn test_disprove_script_fibonacci_script_valid_input() {
             &split_result.intermediate_states[i],
             &split_result.intermediate_states[i + 1],
             &split_result.shards[i + 1],
-        );
+        )
+        .unwrap();
 
         // Check that witness + verification scripts are satisfied
         let verify_script = script! {
@@ -436,6 +464,345 @@ pub fn test_disprove_script_fibonacci_script_valid_input() {
     }
 }
 
+#[test]
+pub fn test_disprove_cost_matches_disprove_scripts() {
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    let IOPair { input, output: _ } = FibonacciScript::generate_valid_io_pair();
+
+    let split_result = FibonacciScript::default_split(input.clone(), SplitType::default()).unwrap();
+    let shapes = split_result.shapes(&input);
+    let disprove_scripts = form_disprove_scripts::<FibonacciScript>(input).unwrap();
+
+    let cost_model = DisproveCost::new();
+    for (shape, disprove_script) in shapes.iter().zip(disprove_scripts.iter()) {
+        // Witness spending the leaf guarded by the committee at the
+        // maximal depth of the taptree
+        let elements = disprove_script.witness_elements();
+        let mut witness = Witness::new();
+        for element in elements.iter() {
+            witness.push(element);
+        }
+        witness.push([0; 64]);
+        witness.push(
+            presigned_leaf(&UNSPENDABLE_KEY, disprove_script.script_pubkey.clone()).as_bytes(),
+        );
+        witness.push([0; TAPROOT_CONTROL_MAX_SIZE]);
+
+        let mut buf = Vec::new();
+        witness.consensus_encode(&mut buf).unwrap();
+        let weight = buf.len() as u64;
+
+        // Empty elements of the actual witness are counted as one byte
+        let estimated = cost_model.weight(shape).to_wu();
+        assert!(
+            weight <= estimated && estimated - weight <= elements.len() as u64,
+            "weight of shard {} is {} WU, estimated {} WU",
+            shape.index,
+            weight,
+            estimated
+        );
+
+        let result = execute_script(script! {
+            { disprove_script.script_witness.clone() }
+            { disprove_script.script_pubkey.clone() }
+        });
+        assert!(
+            result.stats.max_nb_stack_items <= cost_model.peak_items(shape),
+            "peak stack usage of shard {} is underestimated",
+            shape.index
+        );
+        assert_eq!(
+            cost_model.cost(shape).is_some(),
+            cost_model.peak_items(shape) <= MAX_STACK_ITEMS
+        );
+    }
+}
+
+#[test]
+pub fn test_disprove_costs_are_checked() {
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    let IOPair { input, output: _ } = FibonacciScript::generate_valid_io_pair();
+    let split_result = FibonacciScript::default_split(input.clone(), SplitType::default()).unwrap();
+    check_disprove_costs(&input, &split_result).unwrap();
+
+    // The signatures of the wide state alone exceed the stack limit
+    let input = script! { OP_1 };
+    let script = script! {
+        for _ in 0..60 { OP_DUP }
+        for _ in 0..60 { OP_DROP }
+    };
+    let split_result =
+        naive_split(input.clone(), script.clone(), SplitType::ByInstructions, 60).unwrap();
+    assert!(check_disprove_costs(&input, &split_result).is_err());
+}
+
+/// Script with the state exceeding the stack limit when signed at the
+/// boundary of the default split
+struct WideStateScript;
+
+impl SplitableScript for WideStateScript {
+    const INPUT_SIZE: usize = 1;
+    const OUTPUT_SIZE: usize = 1;
+
+    fn script() -> Script {
+        script! {
+            for _ in 0..60 { OP_DUP }
+            for _ in 0..6940 { OP_NOP }
+            for _ in 0..60 { OP_DROP }
+        }
+    }
+
+    fn generate_valid_io_pair() -> IOPair {
+        IOPair {
+            input: script! { OP_1 },
+            output: script! { OP_1 },
+        }
+    }
+
+    fn generate_invalid_io_pair() -> IOPair {
+        IOPair {
+            input: script! { OP_1 },
+            output: script! { OP_0 },
+        }
+    }
+}
+
+#[test]
+pub fn test_disprove_costs_are_checked_on_every_path() {
+    let IOPair { input, output: _ } = WideStateScript::generate_valid_io_pair();
+    let derivation = KeyDerivation::for_program::<WideStateScript>([42; 32], OutPoint::null());
+
+    assert!(form_disprove_scripts::<WideStateScript>(input.clone()).is_err());
+    assert!(form_disprove_scripts_distorted::<WideStateScript>(input.clone()).is_err());
+    assert!(form_disprove_scripts_derived::<WideStateScript>(input.clone(), &derivation).is_err());
+    assert!(form_disprove_scripts_distorted_by::<WideStateScript>(
+        input.clone(),
+        Distortion::Shard(0),
+        &derivation
+    )
+    .is_err());
+    assert!(form_disprove_scripts_with_signed_states::<WideStateScript>(
+        input.clone(),
+        &derivation
+    )
+    .is_err());
+
+    let operator_xonly = SECKEY.public_key(&Secp256k1::new()).x_only_public_key().0;
+    assert!(
+        AssertTransaction::<WideStateScript>::with_options_distorted::<[u8; 32], SmallRng>(
+            input,
+            operator_xonly,
+            Amount::from_sat(1),
+            Options::default(),
+            [1; 32],
+        )
+        .is_err()
+    );
+}
+
+#[test]
+pub fn test_byte_string_state_sign_and_verify() {
+    // The state keeps a hash and a non-minimally encoded number
+    let state = IntermediateState::from_input_script(
+        &script! {},
+        &script! {
+            { 1636 } { vec![0x12; 32] } OP_SHA256
+            { vec![5, 0] } OP_TOALTSTACK
+        },
+    );
+    assert!(state.has_byte_strings());
+
+    let signed_state = SignedByteStringState::sign(&state).unwrap();
+    let unpacked = state.unpacked();
+    assert_eq!(signed_state.stack_len(), 33);
+    assert_eq!(signed_state.altstack_len(), 2);
+
+    // The verification leaves the unpacked state
+    let verify_script = script! {
+        { signed_state.witness_script() }
+        { signed_state.verification_script() }
+        { stack_to_script(&unpacked.stack) }
+        { OP_LONGEQUALVERIFY(unpacked.stack.len()) }
+        OP_FROMALTSTACK OP_5 OP_EQUALVERIFY
+        OP_FROMALTSTACK OP_0 OP_EQUALVERIFY
+        OP_TRUE
+    };
+    let result = execute_script(verify_script);
+    assert!(result.success, "Verification failed");
+
+    // The committed state round-trips exactly
+    let committed = signed_state
+        .committed_state()
+        .expect("signed values fit the layout");
+    assert!(committed.stack.iter_str().eq(state.stack.iter_str()));
+    assert!(committed.altstack.iter_str().eq(state.altstack.iter_str()));
+}
+
+#[test]
+pub fn test_disprove_script_with_byte_string_states() {
+    // Transition function: incrementing the number kept above the byte
+    // string, which leaves the byte string untouched
+    let state_from = IntermediateState::from_input_script(
+        &script! {},
+        &script! {
+            { vec![1, 2, 3, 4, 0x80] } { 5 }
+        },
+    );
+    let function = script! {
+        OP_1ADD
+    };
+
+    for (to, spendable) in [
+        (vec![1, 2, 3, 4, 0x80], false),
+        (vec![1, 2, 3, 4, 0x81], true),
+    ] {
+        let state_to = IntermediateState::from_input_script(
+            &script! {},
+            &script! {
+                { to.clone() } { 6 }
+            },
+        );
+
+        let disprove_script =
+            DisproveScript::new_with_byte_strings(&state_from, &state_to, &function)
+                .expect("shard is byte-oriented");
+
+        let result = execute_script(script! {
+            { disprove_script.script_witness }
+            { disprove_script.script_pubkey }
+        });
+        assert_eq!(result.success, spendable, "Transition to {to:?}");
+    }
+}
+
+#[test]
+pub fn test_byte_string_states_require_byte_oriented_shard() {
+    let preimage = vec![0x12; 32];
+    let hash = sha256::Hash::hash(&preimage).to_byte_array().to_vec();
+
+    // The shard checks the committed hash, which honestly holds
+    let state_from = IntermediateState::from_input_script(
+        &script! {},
+        &script! {
+            { preimage.clone() } OP_SHA256
+        },
+    );
+    let function = script! {
+        { hash.clone() } OP_EQUAL
+    };
+    let state_to = IntermediateState::from_intermediate_result(&state_from, &function);
+    assert!(state_to.stack.iter_str().eq([vec![1]]));
+
+    // On the unpacked bytes, which the disprove script executes the shard
+    // on, the check fails, so the honest transition would be disprovable
+    let unpacked_to =
+        IntermediateState::from_intermediate_result(&state_from.unpacked(), &function);
+    assert_eq!(unpacked_to.stack.last().unwrap(), Vec::<u8>::new());
+    assert!(DisproveScript::new_with_byte_strings(&state_from, &state_to, &function).is_err());
+    assert!(DisproveScript::new_with_byte_strings_derived(
+        &state_from,
+        &state_to,
+        &function,
+        &KeyDerivation::new([7; 32], [8; 32], OutPoint::null()),
+        0,
+    )
+    .is_err());
+
+    // The same for the shard hashing the committed preimage
+    let state_from = IntermediateState::from_input_script(
+        &script! {},
+        &script! {
+            { preimage }
+        },
+    );
+    let function = script! {
+        OP_SHA256 { hash } OP_EQUAL
+    };
+    let state_to = IntermediateState::from_intermediate_result(&state_from, &function);
+    assert!(state_to.stack.iter_str().eq([vec![1]]));
+    assert!(DisproveScript::new_with_byte_strings(&state_from, &state_to, &function).is_err());
+}
+
+/// Script incrementing the number kept above the hash, so its states keep
+/// a byte string while the shards are byte-oriented
+struct ByteStringStateScript;
+
+impl SplitableScript for ByteStringStateScript {
+    const INPUT_SIZE: usize = 2;
+    const OUTPUT_SIZE: usize = 2;
+
+    fn script() -> Script {
+        script! {
+            for _ in 0..15000 { OP_1ADD }
+        }
+    }
+
+    fn generate_valid_io_pair() -> IOPair {
+        IOPair {
+            input: script! { { vec![0x12; 32] } { 0 } },
+            output: script! { { vec![0x12; 32] } { 15000 } },
+        }
+    }
+
+    fn generate_invalid_io_pair() -> IOPair {
+        IOPair {
+            input: script! { { vec![0x12; 32] } { 0 } },
+            output: script! { { vec![0x12; 32] } { 15001 } },
+        }
+    }
+}
+
+#[test]
+pub fn test_disprove_scripts_with_byte_string_states() {
+    let IOPair { input, output: _ } = ByteStringStateScript::generate_valid_io_pair();
+    let derivation =
+        KeyDerivation::for_program::<ByteStringStateScript>([42; 32], OutPoint::null());
+
+    // Signing the state element by element fails instead of panicking
+    let input_state = IntermediateState::from_inject_script(&input);
+    assert!(input_state.has_byte_strings());
+    assert!(SignedIntermediateState::sign(&input_state).is_err());
+
+    // The honest states are recovered exactly and nothing is disprovable
+    let disprove_scripts =
+        form_disprove_scripts_derived::<ByteStringStateScript>(input.clone(), &derivation).unwrap();
+    let split_result =
+        ByteStringStateScript::default_split(input.clone(), SplitType::default()).unwrap();
+    assert!(split_result.len() > 1);
+
+    let states =
+        committed_states::<ByteStringStateScript>(input.clone(), &disprove_scripts).unwrap();
+    for (committed, honest) in states.iter().zip(split_result.intermediate_states.iter()) {
+        assert!(committed.stack.iter_str().eq(honest.stack.iter_str()));
+        assert!(committed.altstack.iter_str().eq(honest.altstack.iter_str()));
+    }
+    assert!(
+        find_disprovable_shards::<ByteStringStateScript>(input.clone(), &disprove_scripts)
+            .unwrap()
+            .is_empty()
+    );
+
+    // The distorted state is found and disproved
+    let (distorted, distorted_idx) = form_disprove_scripts_distorted_by::<ByteStringStateScript>(
+        input.clone(),
+        Distortion::Shard(0),
+        &derivation,
+    )
+    .unwrap();
+    let disprovable =
+        find_disprovable_shards::<ByteStringStateScript>(input.clone(), &distorted).unwrap();
+    assert_eq!(disprovable.first(), Some(&distorted_idx));
+
+    // The states committed in the Assert are signed in the unpacked form
+    let (disprove_scripts, signed_states) =
+        form_disprove_scripts_with_signed_states::<ByteStringStateScript>(input, &derivation)
+            .unwrap();
+    assert_eq!(disprove_scripts.len(), split_result.len());
+    assert_eq!(signed_states[0].total_len(), 33);
+}
+
 #[test]
 pub fn test_distorted_disprove_script_fibonacci_sequence() {
     // The number of steps for the Fibonacci script
@@ -447,7 +814,7 @@ pub fn test_distorted_disprove_script_fibonacci_sequence() {
 
     // Splitting the script into shards
     let (disprove_scripts, distorted_id) =
-        form_disprove_scripts_distorted::<FibonacciScript>(input.clone());
+        form_disprove_scripts_distorted::<FibonacciScript>(input.clone()).unwrap();
 
     println!("Distorted ID: {:?}", distorted_id);
 
@@ -469,13 +836,122 @@ pub fn test_distorted_disprove_script_fibonacci_sequence() {
     }
 }
 
+#[test]
+pub fn test_find_disprovable_shards_fibonacci_sequence() {
+    const STEPS: usize = 64;
+    type FibonacciScript = SquareFibonacciScript<STEPS>;
+
+    let IOPair { input, output: _ } = FibonacciScript::generate_valid_io_pair();
+
+    let (disprove_scripts, distorted_id) =
+        form_disprove_scripts_distorted::<FibonacciScript>(input.clone()).unwrap();
+
+    let disprovable = find_disprovable_shards::<FibonacciScript>(input, &disprove_scripts).unwrap();
+
+    // Distortion of the state makes incorrect both transitions to and from it
+    let expected = [distorted_id, distorted_id + 1]
+        .into_iter()
+        .filter(|idx| *idx < disprove_scripts.len())
+        .collect::<Vec<_>>();
+    assert_eq!(disprovable, expected);
+
+    for idx in disprovable {
+        let disprove_script = &disprove_scripts[idx];
+        let result = execute_script(script! {
+            { disprove_script.script_witness.clone() }
+            { disprove_script.script_pubkey.clone() }
+        });
+        assert!(result.success, "Disprove script {idx} should be spendable");
+    }
+}
+
+#[test]
+pub fn test_derived_disprove_scripts_are_reproducible() {
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    let IOPair { input, output: _ } = FibonacciScript::generate_valid_io_pair();
+    let derivation = KeyDerivation::for_program::<FibonacciScript>([42; 32], OutPoint::null());
+
+    let disprove_scripts =
+        form_disprove_scripts_derived::<FibonacciScript>(input.clone(), &derivation).unwrap();
+    let regenerated =
+        form_disprove_scripts_derived::<FibonacciScript>(input.clone(), &derivation).unwrap();
+    assert_eq!(disprove_scripts, regenerated);
+
+    let other = KeyDerivation::for_program::<FibonacciScript>([43; 32], OutPoint::null());
+    let other_scripts =
+        form_disprove_scripts_derived::<FibonacciScript>(input.clone(), &other).unwrap();
+    assert_ne!(
+        disprove_scripts[0].script_pubkey,
+        other_scripts[0].script_pubkey
+    );
+
+    for (idx, disprove_script) in disprove_scripts.iter().enumerate() {
+        let result = execute_script(script! {
+            { disprove_script.script_witness.clone() }
+            { disprove_script.script_pubkey.clone() }
+        });
+        assert!(
+            !result.success,
+            "Disprove script {idx} should not be spendable"
+        );
+    }
+
+    let states = committed_states::<FibonacciScript>(input.clone(), &disprove_scripts).unwrap();
+    assert_eq!(states.len(), disprove_scripts.len());
+    assert!(
+        find_disprovable_shards::<FibonacciScript>(input, &disprove_scripts)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+pub fn test_disprove_scripts_distorted_at_chosen_shard() {
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    let IOPair { input, output: _ } = FibonacciScript::generate_valid_io_pair();
+
+    let derivation = KeyDerivation::for_program::<FibonacciScript>([1; 32], OutPoint::null());
+
+    let (disprove_scripts, distorted_id) = form_disprove_scripts_distorted_by::<FibonacciScript>(
+        input.clone(),
+        Distortion::Shard(1),
+        &derivation,
+    )
+    .unwrap();
+    assert_eq!(distorted_id, 1);
+
+    let disprovable =
+        find_disprovable_shards::<FibonacciScript>(input.clone(), &disprove_scripts).unwrap();
+    assert_eq!(disprovable, [1, 2]);
+
+    // The transition to the distorted state is incorrect, but the witness
+    // with an extra element doesn't satisfy its disprove script
+    let mut tampered = disprove_scripts.clone();
+    tampered[1].script_witness = script! {
+        OP_1
+        { disprove_scripts[1].script_witness.clone() }
+    };
+    let disprovable = find_disprovable_shards::<FibonacciScript>(input.clone(), &tampered).unwrap();
+    assert_eq!(disprovable, [2]);
+
+    let shards_num = disprove_scripts.len();
+    assert!(form_disprove_scripts_distorted_by::<FibonacciScript>(
+        input,
+        Distortion::Shard(shards_num),
+        &derivation
+    )
+    .is_err());
+}
+
 #[test]
 pub fn test_disprove_script_batch_correctness() {
     // First, we generate the pair of input and output scripts
     let IOPair { input, output: _ } = U254MulScript::generate_valid_io_pair();
 
     // Splitting the script into shards
-    let disprove_scripts = form_disprove_scripts::<U254MulScript>(input.clone());
+    let disprove_scripts = form_disprove_scripts::<U254MulScript>(input.clone()).unwrap();
 
     // Now, we form the disprove script for each shard
     for (i, disprove_script) in disprove_scripts.into_iter().enumerate() {
@@ -498,15 +974,22 @@ static SECKEY: Lazy<SecretKey> = Lazy::new(|| {
 
 #[test]
 fn test_assert_tx_signing() {
-    let IOPair { input, .. } = U254MulScript::generate_invalid_io_pair();
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    let IOPair { input, .. } = FibonacciScript::generate_invalid_io_pair();
 
     let ctx = Secp256k1::new();
 
     let operator_pubkey = SECKEY.public_key(&ctx);
     let operator_xonly = operator_pubkey.x_only_public_key().0;
 
-    let assert_tx =
-        AssertTransaction::<U254MulScript>::new(input, operator_xonly, Amount::from_sat(70_000));
+    let assert_tx = AssertTransaction::<FibonacciScript>::new(
+        input,
+        operator_xonly,
+        Amount::from_sat(70_000),
+        &KeyDerivation::for_program::<FibonacciScript>([42; 32], OutPoint::null()),
+    )
+    .unwrap();
 
     let operator_script_pubkey =
         Script::new_p2wpkh(&WPubkeyHash::hash(&operator_pubkey.serialize()));
@@ -563,6 +1046,368 @@ fn test_assert_tx_signing() {
     }
 }
 
+#[test]
+fn test_assert_tx_presigned_by_committee() {
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    let IOPair { input, .. } = FibonacciScript::generate_invalid_io_pair();
+
+    let ctx = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let operator_xonly = SECKEY.public_key(&ctx).x_only_public_key().0;
+
+    let seckeys = (0..3).map(|_| SecretKey::new(&mut rng)).collect::<Vec<_>>();
+    let committee =
+        Committee::new(&ctx, seckeys.iter().map(|sk| sk.public_key(&ctx)).collect()).unwrap();
+    let committee_pubkey = committee.aggregated_pubkey();
+
+    let assert_tx = AssertTransaction::<FibonacciScript>::with_options(
+        input,
+        operator_xonly,
+        Amount::from_sat(70_000),
+        Options {
+            committee_pubkey: Some(committee_pubkey),
+            ..Default::default()
+        },
+        &KeyDerivation::for_program::<FibonacciScript>([42; 32], OutPoint::null()),
+    )
+    .unwrap();
+
+    let txout = TxOut {
+        value: Amount::from_sat(69_000),
+        script_pubkey: Script::new_p2tr(&ctx, operator_xonly, None),
+    };
+    let outpoint =
+        OutPoint::from_str("a85d89b4666fed622281d3589474aa1f87971b54bd5d9c1899ed2e8e0447cc06:0")
+            .unwrap();
+
+    assert!(assert_tx
+        .payout_transaction(&ctx, txout.clone(), outpoint, &SECKEY)
+        .is_err());
+
+    let sighash = assert_tx
+        .payout_sighash(&ctx, txout.clone(), outpoint)
+        .unwrap();
+
+    let (secnonces, pubnonces): (Vec<_>, Vec<_>) = seckeys
+        .iter()
+        .map(|_| SecNonce::generate(&ctx, &mut rng))
+        .unzip();
+    assert!(matches!(
+        committee.signing_session(&ctx, &pubnonces[1..], sighash),
+        Err(musig2::Error::NonceCountMismatch {
+            expected: 3,
+            got: 2
+        })
+    ));
+    let session = committee
+        .signing_session(&ctx, &pubnonces, sighash)
+        .unwrap();
+    let partials = seckeys
+        .iter()
+        .zip(secnonces)
+        .map(|(sk, secnonce)| session.partial_sign(&ctx, sk, secnonce).unwrap())
+        .collect::<Vec<_>>();
+    let signature = session.aggregate(&partials).unwrap();
+
+    assert!(verify_signature(
+        &ctx,
+        &committee_pubkey,
+        sighash,
+        &signature
+    ));
+
+    let payout = assert_tx
+        .payout_transaction_presigned(&ctx, txout, outpoint, &SECKEY, &signature)
+        .unwrap();
+
+    let witness = &payout.input[0].witness;
+    assert_eq!(witness.len(), 5);
+    assert_eq!(witness.nth(2).unwrap(), &signature[..]);
+
+    let prevouts = [assert_tx.txout(&ctx)];
+    let result = execute_script_spend(&payout, &prevouts, 0);
+    assert!(result.success, "payout spend failed: {:?}", result.error);
+
+    // Signature of the committee over another transaction is rejected
+    let other_signature = committee_sign(&ctx, &committee, &seckeys, TapSighash::all_zeros());
+    let mut elements = payout.input[0].witness.to_vec();
+    elements[2] = other_signature.as_ref().to_vec();
+    let mut forged = payout.clone();
+    forged.input[0].witness = Witness::from_slice(&elements);
+
+    let result = execute_script_spend(&forged, &prevouts, 0);
+    assert!(!result.success);
+    assert_eq!(result.error, Some(ExecError::SchnorrSig));
+}
+
+#[test]
+fn test_presigned_disprove_spends_are_executable() {
+    type FibonacciScript = SquareFibonacciScript<64>;
+
+    let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+
+    let ctx = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let operator_xonly = SECKEY.public_key(&ctx).x_only_public_key().0;
+
+    let seckeys = (0..3).map(|_| SecretKey::new(&mut rng)).collect::<Vec<_>>();
+    let committee =
+        Committee::new(&ctx, seckeys.iter().map(|sk| sk.public_key(&ctx)).collect()).unwrap();
+
+    let (assert_tx, distorted_id) =
+        AssertTransaction::<FibonacciScript>::with_options_distorted_by(
+            input,
+            operator_xonly,
+            Amount::from_sat(70_000),
+            Options {
+                committee_pubkey: Some(committee.aggregated_pubkey()),
+                ..Default::default()
+            },
+            Distortion::Shard(1),
+            &KeyDerivation::for_program::<FibonacciScript>([1; 32], OutPoint::null()),
+        )
+        .unwrap();
+
+    let txout = TxOut {
+        value: Amount::from_sat(69_000),
+        script_pubkey: Script::new_p2tr(&ctx, operator_xonly, None),
+    };
+    let outpoint =
+        OutPoint::from_str("a85d89b4666fed622281d3589474aa1f87971b54bd5d9c1899ed2e8e0447cc06:0")
+            .unwrap();
+
+    let signatures = assert_tx
+        .disprove_sighashes(&ctx, txout.clone(), outpoint)
+        .unwrap()
+        .into_iter()
+        .map(|sighash| committee_sign(&ctx, &committee, &seckeys, sighash))
+        .collect::<Vec<_>>();
+    let disprove_txs = assert_tx
+        .disprove_transactions_presigned(&ctx, txout, outpoint, &signatures)
+        .unwrap();
+
+    let prevouts = [assert_tx.txout(&ctx)];
+    for (idx, disprove) in assert_tx.disprove_scripts.iter().enumerate() {
+        let result = execute_script_spend(&disprove_txs[disprove], &prevouts, 0);
+
+        // The committee signature passes for each leaf, so only the
+        // transitions to and from the distorted state are spendable.
+        assert_ne!(result.error, Some(ExecError::SchnorrSig), "disprove {idx}");
+        assert_eq!(
+            result.success,
+            idx == distorted_id || idx == distorted_id + 1,
+            "disprove {idx}: {:?}",
+            result.error
+        );
+    }
+
+    // Signature of the committee over another disprove leaf is rejected
+    let mut forged = disprove_txs[&assert_tx.disprove_scripts[distorted_id]].clone();
+    let mut elements = forged.input[0].witness.to_vec();
+    let committee_signature_idx = elements.len() - 3;
+    elements[committee_signature_idx] = signatures[0].as_ref().to_vec();
+    forged.input[0].witness = Witness::from_slice(&elements);
+
+    let result = execute_script_spend(&forged, &prevouts, 0);
+    assert!(!result.success);
+    assert_eq!(result.error, Some(ExecError::SchnorrSig));
+}
+
+#[test]
+fn test_states_committed_in_assert_witness() {
+    const STEPS: usize = 64;
+    type FibonacciScript = SquareFibonacciScript<STEPS>;
+
+    let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+    let ctx = Secp256k1::new();
+    let operator_xonly = SECKEY.public_key(&ctx).x_only_public_key().0;
+
+    let (disprove_scripts, signed_states) =
+        form_disprove_scripts_with_signed_states::<FibonacciScript>(
+            input.clone(),
+            &KeyDerivation::for_program::<FibonacciScript>([42; 32], OutPoint::null()),
+        )
+        .unwrap();
+    let commitment = StatesCommitment::new(operator_xonly, &signed_states);
+
+    let assert_tx = AssertTransaction::<FibonacciScript>::from_scripts(
+        operator_xonly,
+        PayoutScript::new(operator_xonly),
+        disprove_scripts.clone(),
+        Amount::from_sat(70_000),
+        *UNSPENDABLE_KEY,
+    );
+
+    let prevouts = commitment
+        .txouts(&ctx, assert_tx.internal_key, Amount::from_sat(1_000))
+        .into_iter()
+        .enumerate()
+        .map(|(vout, txout)| (OutPoint::new(Txid::all_zeros(), vout as u32), txout))
+        .collect::<Vec<_>>();
+    let tx = assert_tx
+        .spend_commitment_inputs_tx(&ctx, &commitment, &signed_states, &SECKEY, prevouts)
+        .unwrap();
+
+    let committed = commitment.parse(&tx).unwrap();
+    assert_eq!(committed.len(), signed_states.len());
+
+    let split_result = FibonacciScript::default_split(input.clone(), SplitType::default()).unwrap();
+    let states = std::iter::once(IntermediateState::from_inject_script(&input))
+        .chain(split_result.intermediate_states);
+    for (committed, state) in committed.iter().zip(states) {
+        let committed = committed.to_intermediate_state().to_bytes();
+        let expected = state.to_bytes();
+        assert_eq!(committed.stack, expected.stack);
+        assert_eq!(committed.altstack, expected.altstack);
+    }
+
+    for (idx, disprove_script) in disprove_scripts.iter().enumerate() {
+        assert_eq!(
+            disprove_witness(&committed[idx], &committed[idx + 1]),
+            disprove_script.script_witness,
+            "witness of disprove script {idx} differs from the committed one"
+        );
+    }
+}
+
+#[test]
+fn test_commitment_leaves_are_executable() {
+    const STACK: u32 = 30;
+    const ALTSTACK: u32 = 20;
+
+    let ctx = Secp256k1::new();
+    let operator_xonly = SECKEY.public_key(&ctx).x_only_public_key().0;
+
+    // Two states of 50 elements are committed in the inputs of 45, 45 and
+    // 10 elements
+    let state = IntermediateState::from_input_script(
+        &script! {},
+        &script! {
+            for i in 0..STACK {
+                { i }
+            }
+            for i in 0..ALTSTACK {
+                { i } OP_TOALTSTACK
+            }
+        },
+    );
+    let signed_states = [
+        SignedIntermediateState::sign(&state).unwrap(),
+        SignedIntermediateState::sign(&state).unwrap(),
+    ];
+    let commitment = StatesCommitment::new(operator_xonly, &signed_states);
+    assert_eq!(commitment.inputs_num(), 3);
+
+    let assert_tx = AssertTransaction::<SquareFibonacciScript<64>>::from_scripts(
+        operator_xonly,
+        PayoutScript::new(operator_xonly),
+        vec![],
+        Amount::from_sat(70_000),
+        *UNSPENDABLE_KEY,
+    );
+
+    let txouts = commitment.txouts(&ctx, assert_tx.internal_key, Amount::from_sat(1_000));
+    let prevouts = txouts
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(vout, txout)| (OutPoint::new(Txid::all_zeros(), vout as u32), txout))
+        .collect::<Vec<_>>();
+    let tx = assert_tx
+        .spend_commitment_inputs_tx(&ctx, &commitment, &signed_states, &SECKEY, prevouts)
+        .unwrap();
+
+    // The first input commits the maximum number of elements
+    assert_eq!(
+        tx.input[0].witness.len(),
+        MAX_ELEMENTS_PER_INPUT * SCRIPT_SIG_ELEMENTS + 3
+    );
+
+    // Each leaf is executed with the stack limit enforced
+    for (idx, script) in commitment.scripts().iter().enumerate() {
+        let witness = &tx.input[idx].witness;
+        assert_eq!(witness.nth(witness.len() - 2).unwrap(), script.as_bytes());
+
+        let result = execute_script_spend(&tx, &txouts, idx);
+        assert!(
+            result.success,
+            "commitment input {idx} is invalid: {:?}",
+            result.error
+        );
+    }
+}
+
+#[test]
+fn test_equivocation_script_spendable_with_double_signing() {
+    let state = IntermediateState::from_inject_script(&script! { 1 2 });
+    let signed = SignedIntermediateState::sign(&state).unwrap();
+    let element = signed.stack[0];
+
+    let equivocation_scripts = EquivocationScript::from_signed_states(&[signed.clone()]);
+    assert_eq!(equivocation_scripts.len(), signed.total_len());
+    let equivocation_script = &equivocation_scripts[0];
+
+    let other_message = Message::from_u32(element.stack_element + 1);
+    let other_signature = element.secret_key.sign(other_message);
+    let equivocation = Equivocation::detect(
+        &element.public_key,
+        (&element.encoding, &element.signature),
+        (&other_message, &other_signature),
+    )
+    .unwrap();
+
+    let result = execute_script(script! {
+        { equivocation_script.witness_script(&equivocation).unwrap() }
+        { equivocation_script.script_pubkey.clone() }
+    });
+    assert!(result.success, "Equivocation script should be spendable");
+
+    let result = execute_script(script! {
+        { element.signature.to_script_sig() }
+        { element.signature.to_script_sig() }
+        { equivocation_script.script_pubkey.clone() }
+    });
+    assert!(
+        !result.success,
+        "Same signature twice is not an equivocation"
+    );
+
+    let other_element = signed.stack[1];
+    assert!(equivocation_scripts[1]
+        .witness_script(&equivocation)
+        .is_none());
+    assert!(Equivocation::detect(
+        &other_element.public_key,
+        (&element.encoding, &element.signature),
+        (&other_message, &other_signature),
+    )
+    .is_none());
+
+    let ctx = Secp256k1::new();
+    let operator_xonly = SECKEY.public_key(&ctx).x_only_public_key().0;
+    let assert_tx = AssertTransaction::<U254MulScript>::from_scripts(
+        operator_xonly,
+        PayoutScript::new(operator_xonly),
+        vec![],
+        Amount::from_sat(70_000),
+        *UNSPENDABLE_KEY,
+    )
+    .with_equivocation_scripts(equivocation_scripts);
+
+    let txout = TxOut {
+        value: Amount::from_sat(69_000),
+        script_pubkey: Script::new_p2wpkh(&WPubkeyHash::all_zeros()),
+    };
+    let tx = assert_tx
+        .equivocation_transaction(&ctx, &equivocation, txout, OutPoint::null())
+        .unwrap();
+    // Two signatures, script and control block
+    assert_eq!(tx.input[0].witness.len(), 2 * SCRIPT_SIG_ELEMENTS + 2);
+}
+
 fn dump_hex_tx_to_file(tx: bitcoin::Transaction, path: impl AsRef<Path>) {
     let mut buf = Vec::new();
     tx.consensus_encode(&mut buf).unwrap();
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use bitcoin::{
+    absolute::LockTime,
+    key::{Keypair, Secp256k1},
+    secp256k1::{schnorr, All, SecretKey},
+    transaction::Version,
+    OutPoint, Sequence, TapSighash, TapSighashType, Transaction, TxOut, Txid,
+};
+use bitcoin_splitter::split::script::SplitableScript;
+
+use crate::{
+    assert::{
+        commitment::{CommittedState, StatesCommitment},
+        AssertTransaction,
+    },
+    disprove::{signing::SignedIntermediateState, DisproveScript},
+    treepp::*,
+};
+
+use super::{leaf_sighash, GraphConfig, Kickoff, LeafSpend};
+
+/// Index of the output with Payout and Disprove leaves.
+pub const ASSERT_VOUT: u32 = 0;
+
+/// Assert transaction of the graph, which spends the Kickoff connector A,
+/// and locks its value under [`AssertTransaction`] output.
+///
+/// The connector is followed by the commitment inputs, which publish the
+/// signed intermediate states in their witness (see
+/// [`crate::assert::commitment`]), so challengers can take them from the
+/// confirmed Assert, see [`Self::committed_states`].
+pub struct Assert<S: SplitableScript> {
+    /// The asserted program with disprove and payout scripts.
+    pub assert: AssertTransaction<S>,
+
+    /// Commitment of the signed intermediate states.
+    pub commitment: StatesCommitment,
+
+    tx: Transaction,
+    spends: Vec<LeafSpend>,
+}
+
+impl<S: SplitableScript> Assert<S> {
+    /// Construct Assert transaction. `assert` should be constructed with
+    /// [`GraphConfig::assert_options`], and `commitment_prevouts` are the
+    /// outputs of [`StatesCommitment::txouts`] with the graph internal key
+    /// in the same order.
+    pub fn new(
+        ctx: &Secp256k1<All>,
+        config: &GraphConfig,
+        kickoff: &Kickoff,
+        assert: AssertTransaction<S>,
+        commitment: StatesCommitment,
+        commitment_prevouts: Vec<(OutPoint, TxOut)>,
+    ) -> eyre::Result<Self> {
+        if assert.payout_script.committee_pubkey != Some(config.committee_pubkey) {
+            eyre::bail!("assert transaction should be presigned by the graph committee");
+        }
+        if assert.internal_key != config.internal_key {
+            eyre::bail!("assert transaction should use the graph internal key");
+        }
+        if assert.operator_pubkey != config.operator_pubkey {
+            eyre::bail!("assert transaction should be published by the graph operator");
+        }
+        if commitment.operator_pubkey() != config.operator_pubkey {
+            eyre::bail!("states commitment should be signed by the graph operator");
+        }
+        if commitment_prevouts.len() != commitment.inputs_num() {
+            eyre::bail!(
+                "expected {} commitment outputs, got {}",
+                commitment.inputs_num(),
+                commitment_prevouts.len()
+            );
+        }
+
+        let scripts = commitment.scripts();
+        let taptrees = commitment.taptrees(ctx, config.internal_key);
+        let mut spends = vec![kickoff.assert_spend()];
+        for ((script, taptree), (outpoint, txout)) in
+            scripts.into_iter().zip(taptrees).zip(commitment_prevouts)
+        {
+            if txout.script_pubkey != Script::new_p2tr_tweaked(taptree.output_key()) {
+                eyre::bail!("output {outpoint} doesn't lock the states commitment");
+            }
+
+            spends.push(LeafSpend::new(&taptree, outpoint, txout, script));
+        }
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: spends
+                .iter()
+                .map(|spend| spend.txin(Sequence::ZERO))
+                .collect(),
+            output: vec![assert.txout(ctx)],
+        };
+
+        Ok(Self {
+            assert,
+            commitment,
+            tx,
+            spends,
+        })
+    }
+
+    pub fn unsigned_tx(&self) -> &Transaction {
+        &self.tx
+    }
+
+    pub fn txid(&self) -> Txid {
+        self.tx.compute_txid()
+    }
+
+    pub fn outpoint(&self) -> OutPoint {
+        OutPoint::new(self.txid(), ASSERT_VOUT)
+    }
+
+    /// Sighash of the connector input, which is signed by both operator
+    /// and the committee. It commits to the commitment inputs, but not to
+    /// the states published in their witness.
+    pub fn sighash(&self) -> eyre::Result<TapSighash> {
+        leaf_sighash(&self.tx, &self.spends, 0, TapSighashType::Default)
+    }
+
+    /// Sign the connector and commitment inputs by operator and fill their
+    /// witnesses, publishing the signed `states` in the commitment ones.
+    pub fn finalize(
+        &self,
+        ctx: &Secp256k1<All>,
+        operator_seckey: &SecretKey,
+        committee_signature: &schnorr::Signature,
+        states: &[SignedIntermediateState],
+    ) -> eyre::Result<Transaction> {
+        let keypair = Keypair::from_secret_key(ctx, operator_seckey);
+        let signature = ctx.sign_schnorr(&self.sighash()?.into(), &keypair);
+
+        let mut tx = self.tx.clone();
+        tx.input[0].witness = self.spends[0].witness(&[&signature[..], &committee_signature[..]]);
+
+        for (idx, elements) in self
+            .commitment
+            .inputs_elements(states)?
+            .into_iter()
+            .enumerate()
+        {
+            let input = idx + 1;
+            let sighash = leaf_sighash(&self.tx, &self.spends, input, TapSighashType::Default)?;
+            let signature = ctx.sign_schnorr(&sighash.into(), &keypair);
+
+            let elements = elements
+                .iter()
+                .map(Vec::as_slice)
+                .chain([&signature[..]])
+                .collect::<Vec<_>>();
+            tx.input[input].witness = self.spends[input].witness(&elements);
+        }
+
+        Ok(tx)
+    }
+
+    /// Parse the signed intermediate states from the commitment inputs of
+    /// the confirmed Assert `tx`, see [`StatesCommitment::parse_inputs`].
+    pub fn committed_states(&self, tx: &Transaction) -> eyre::Result<Vec<CommittedState>> {
+        eyre::ensure!(
+            tx.compute_txid() == self.txid(),
+            "not the Assert transaction of the graph"
+        );
+
+        self.commitment.parse_inputs(&tx.input[1..])
+    }
+
+    /// Sighashes of Disprove transactions paying to `txout`, in the same
+    /// order as disprove scripts.
+    pub fn disprove_sighashes(
+        &self,
+        ctx: &Secp256k1<All>,
+        txout: TxOut,
+    ) -> eyre::Result<Vec<TapSighash>> {
+        self.assert.disprove_sighashes(ctx, txout, self.outpoint())
+    }
+
+    /// Disprove transactions paying to `txout` with committee signatures
+    /// over [`Self::disprove_sighashes`].
+    pub fn disprove_transactions(
+        &self,
+        ctx: &Secp256k1<All>,
+        txout: TxOut,
+        committee_signatures: &[schnorr::Signature],
+    ) -> eyre::Result<HashMap<DisproveScript, Transaction>> {
+        self.assert.disprove_transactions_presigned(
+            ctx,
+            txout,
+            self.outpoint(),
+            committee_signatures,
+        )
+    }
+
+    pub(crate) fn payout_spend(&self, ctx: &Secp256k1<All>) -> LeafSpend {
+        LeafSpend::new(
+            &self.assert.taptree(ctx),
+            self.outpoint(),
+            self.tx.output[ASSERT_VOUT as usize].clone(),
+            self.assert.payout_script.to_script(),
+        )
+    }
+}
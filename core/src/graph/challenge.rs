@@ -0,0 +1,88 @@
+use bitcoin::{
+    absolute::LockTime,
+    key::{Keypair, Secp256k1},
+    secp256k1::{All, SecretKey},
+    taproot,
+    transaction::Version,
+    Sequence, TapSighash, TapSighashType, Transaction, TxIn, TxOut,
+};
+
+use super::{leaf_sighash, Kickoff, LeafSpend};
+
+/// Sighash type of the operator signature, which lets challengers add their
+/// own inputs to fund the Challenge.
+pub const CHALLENGE_SIGHASH_TYPE: TapSighashType = TapSighashType::SinglePlusAnyoneCanPay;
+
+/// Transaction by which a challenger spends the Kickoff connector C, so
+/// Take1 becomes invalid and the operator has to publish Assert.
+///
+/// The operator presigns the connector input with
+/// [`CHALLENGE_SIGHASH_TYPE`], committing only to the first output paying
+/// the operator, so the Challenge can be crowdfunded by any number of
+/// challengers.
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    tx: Transaction,
+    spends: Vec<LeafSpend>,
+}
+
+impl Challenge {
+    /// Construct Challenge transaction, where `txout` is the payment to the
+    /// operator for the Assert publication.
+    pub fn new(kickoff: &Kickoff, txout: TxOut) -> Self {
+        let spends = vec![kickoff.challenge_spend()];
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![spends[0].txin(Sequence::ZERO)],
+            output: vec![txout],
+        };
+
+        Self { tx, spends }
+    }
+
+    pub fn unsigned_tx(&self) -> &Transaction {
+        &self.tx
+    }
+
+    /// Sighash of the connector input signed by the operator.
+    pub fn sighash(&self) -> eyre::Result<TapSighash> {
+        leaf_sighash(&self.tx, &self.spends, 0, CHALLENGE_SIGHASH_TYPE)
+    }
+
+    /// Presign the connector input by the operator.
+    pub fn presign(
+        &self,
+        ctx: &Secp256k1<All>,
+        operator_seckey: &SecretKey,
+    ) -> eyre::Result<taproot::Signature> {
+        let signature = ctx.sign_schnorr(
+            &self.sighash()?.into(),
+            &Keypair::from_secret_key(ctx, operator_seckey),
+        );
+
+        Ok(taproot::Signature {
+            signature,
+            sighash_type: CHALLENGE_SIGHASH_TYPE,
+        })
+    }
+
+    /// Fill the witness of connector input with operator's presignature,
+    /// and append challengers' (already signed) `funding` inputs and
+    /// `change` outputs.
+    pub fn finalize(
+        self,
+        operator_signature: &taproot::Signature,
+        funding: Vec<TxIn>,
+        change: Vec<TxOut>,
+    ) -> Transaction {
+        let mut tx = self.tx;
+
+        tx.input[0].witness = self.spends[0].witness(&[operator_signature.to_vec().as_slice()]);
+        tx.input.extend(funding);
+        tx.output.extend(change);
+
+        tx
+    }
+}
@@ -0,0 +1,193 @@
+use bitcoin::{
+    absolute::LockTime,
+    ecdsa,
+    key::Secp256k1,
+    secp256k1::{All, SecretKey},
+    sighash::SighashCache,
+    taproot::TaprootSpendInfo,
+    transaction::Version,
+    Amount, EcdsaSighashType, OutPoint, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+
+use crate::{committee::presigned_leaf, treepp::*};
+
+use super::{GraphConfig, LeafSpend};
+
+/// Index of the connector output spent by Assert or Take1.
+pub const CONNECTOR_A_VOUT: u32 = 0;
+
+/// Index of the connector output spent by Challenge or Take1.
+pub const CONNECTOR_C_VOUT: u32 = 1;
+
+/// Transaction by which the operator starts the withdrawal.
+///
+/// Has two connector outputs:
+/// - connector A, which is spent either by presigned Assert, or by Take1
+///   after the challenge timeout. Its value is used as the Assert stake.
+/// - connector C, which is spent either by Challenge, or by Take1, so after
+///   the Challenge is published Take1 becomes invalid.
+#[derive(Debug, Clone)]
+pub struct Kickoff {
+    config: GraphConfig,
+    tx: Transaction,
+    funding: TxOut,
+    connector_a: TaprootSpendInfo,
+    connector_c: TaprootSpendInfo,
+}
+
+impl Kickoff {
+    /// Construct Kickoff transaction spending operator's P2WPKH `funding`
+    /// output.
+    pub fn new(
+        ctx: &Secp256k1<All>,
+        config: &GraphConfig,
+        funding_outpoint: OutPoint,
+        funding: TxOut,
+        assert_amount: Amount,
+        challenge_amount: Amount,
+    ) -> Self {
+        let connector_a = config.taptree(
+            ctx,
+            [
+                (1, Self::assert_leaf(config)),
+                (1, Self::take1_leaf(config)),
+            ],
+        );
+        let connector_c = config.taptree(ctx, [(1, Self::challenge_leaf(config))]);
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: funding_outpoint,
+                script_sig: Script::new(),
+                sequence: Sequence::ZERO,
+                witness: Witness::new(),
+            }],
+            output: vec![
+                TxOut {
+                    value: assert_amount,
+                    script_pubkey: Script::new_p2tr_tweaked(connector_a.output_key()),
+                },
+                TxOut {
+                    value: challenge_amount,
+                    script_pubkey: Script::new_p2tr_tweaked(connector_c.output_key()),
+                },
+            ],
+        };
+
+        Self {
+            config: *config,
+            tx,
+            funding,
+            connector_a,
+            connector_c,
+        }
+    }
+
+    /// Leaf of connector A spent by Assert.
+    ///
+    /// ## Witness:
+    /// ```bitcoin_script
+    /// { operator_signature }
+    /// { committee_signature }
+    /// ```
+    pub fn assert_leaf(config: &GraphConfig) -> Script {
+        presigned_leaf(
+            &config.committee_pubkey,
+            script! {
+                { config.operator_pubkey.serialize().to_vec() }
+                OP_CHECKSIG
+            },
+        )
+    }
+
+    /// Leaf of connector A spent by Take1 after the challenge timeout.
+    ///
+    /// ## Witness:
+    /// ```bitcoin_script
+    /// { operator_signature }
+    /// ```
+    pub fn take1_leaf(config: &GraphConfig) -> Script {
+        script! {
+            { config.challenge_timeout.value() as u32 }
+            OP_CSV
+            OP_DROP
+            { config.operator_pubkey.serialize().to_vec() }
+            OP_CHECKSIG
+        }
+    }
+
+    /// Leaf of connector C spent by Challenge or Take1.
+    ///
+    /// ## Witness:
+    /// ```bitcoin_script
+    /// { operator_signature }
+    /// ```
+    pub fn challenge_leaf(config: &GraphConfig) -> Script {
+        script! {
+            { config.operator_pubkey.serialize().to_vec() }
+            OP_CHECKSIG
+        }
+    }
+
+    pub fn unsigned_tx(&self) -> &Transaction {
+        &self.tx
+    }
+
+    pub fn txid(&self) -> Txid {
+        self.tx.compute_txid()
+    }
+
+    pub(crate) fn assert_spend(&self) -> LeafSpend {
+        self.connector_a_spend(Self::assert_leaf(&self.config))
+    }
+
+    pub(crate) fn take1_spend(&self) -> LeafSpend {
+        self.connector_a_spend(Self::take1_leaf(&self.config))
+    }
+
+    pub(crate) fn challenge_spend(&self) -> LeafSpend {
+        LeafSpend::new(
+            &self.connector_c,
+            OutPoint::new(self.txid(), CONNECTOR_C_VOUT),
+            self.tx.output[CONNECTOR_C_VOUT as usize].clone(),
+            Self::challenge_leaf(&self.config),
+        )
+    }
+
+    fn connector_a_spend(&self, leaf: Script) -> LeafSpend {
+        LeafSpend::new(
+            &self.connector_a,
+            OutPoint::new(self.txid(), CONNECTOR_A_VOUT),
+            self.tx.output[CONNECTOR_A_VOUT as usize].clone(),
+            leaf,
+        )
+    }
+
+    /// Sign the funding input with operator's key.
+    pub fn sign(
+        &self,
+        ctx: &Secp256k1<All>,
+        operator_seckey: &SecretKey,
+    ) -> eyre::Result<Transaction> {
+        let mut tx = self.tx.clone();
+
+        let sighash = SighashCache::new(&tx).p2wpkh_signature_hash(
+            0,
+            &self.funding.script_pubkey,
+            self.funding.value,
+            EcdsaSighashType::All,
+        )?;
+
+        let signature = ctx.sign_ecdsa(&sighash.into(), operator_seckey);
+
+        let mut witness = Witness::new();
+        witness.push_ecdsa_signature(&ecdsa::Signature::sighash_all(signature));
+        witness.push(operator_seckey.public_key(ctx).serialize());
+
+        tx.input[0].witness = witness;
+
+        Ok(tx)
+    }
+}
@@ -0,0 +1,201 @@
+//! Module containing the full transaction graph of the BitVM2 protocol:
+//!
+//! ```text
+//!  PegIn ──────────────────────────┬─────────────┐
+//!                                  │             │
+//!  Kickoff ─┬─ (connector A) ──────┼─ Take1      │
+//!           │        │             │             │
+//!           │        └──── Assert ─┼─────────────┴─ Take2
+//!           │                │     │
+//!           │                └─────┼─ Disprove
+//!           │                      │
+//!           └─ (connector C) ──────┴─ Challenge
+//! ```
+//!
+//! Each transaction has its own typed builder, which is constructed from the
+//! builders of the transactions it spends, so txids of the whole graph are
+//! known before anything is published and can be presigned by the committee
+//! (see [`crate::committee`]).
+//!
+//! All taptrees and locktimes are taken from the single [`GraphConfig`].
+
+use bitcoin::{
+    key::Secp256k1,
+    relative::Height,
+    secp256k1::All,
+    sighash::{Prevouts, SighashCache},
+    taproot::{ControlBlock, LeafVersion, TaprootBuilder, TaprootSpendInfo},
+    OutPoint, Sequence, TapSighash, TapSighashType, Transaction, TxIn, TxOut, Witness,
+    XOnlyPublicKey,
+};
+
+use crate::{
+    assert::{self, payout_script::LOCKTIME},
+    treepp::*,
+    UNSPENDABLE_KEY,
+};
+
+pub mod assert_tx;
+pub mod challenge;
+pub mod kickoff;
+pub mod peg_in;
+pub mod take1;
+pub mod take2;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::{
+    assert_tx::Assert, challenge::Challenge, kickoff::Kickoff, peg_in::PegIn, take1::Take1,
+    take2::Take2,
+};
+
+/// Default number of blocks challengers have to challenge the Kickoff
+/// before the operator can use Take1. Assuming that mean block mining
+/// time is 10 minutes, that is one week.
+pub const CHALLENGE_TIMEOUT: u16 = 6 /* hour */ * 24 /* day */ * 7 /* week */;
+
+/// Configuration shared by all transactions of the graph.
+#[derive(Debug, Clone, Copy)]
+pub struct GraphConfig {
+    /// Public key of the operator.
+    pub operator_pubkey: XOnlyPublicKey,
+
+    /// Aggregated key of the committee, which presigns the graph.
+    pub committee_pubkey: XOnlyPublicKey,
+
+    /// Number of blocks after Kickoff, after which the operator can spend
+    /// the deposit with Take1, default is [`CHALLENGE_TIMEOUT`].
+    pub challenge_timeout: Height,
+
+    /// Number of blocks after Assert, after which the operator can spend
+    /// the deposit with Take2, default is [`LOCKTIME`].
+    pub payout_locktime: Height,
+
+    /// Internal key of all taptrees in the graph, default is
+    /// [`UNSPENDABLE_KEY`]. See [`crate::nums`].
+    pub internal_key: XOnlyPublicKey,
+}
+
+impl GraphConfig {
+    pub fn new(operator_pubkey: XOnlyPublicKey, committee_pubkey: XOnlyPublicKey) -> Self {
+        Self {
+            operator_pubkey,
+            committee_pubkey,
+            challenge_timeout: Height::from(CHALLENGE_TIMEOUT),
+            payout_locktime: Height::from(LOCKTIME),
+            internal_key: *UNSPENDABLE_KEY,
+        }
+    }
+
+    /// Options of the [`assert::AssertTransaction`] which is a part of this
+    /// graph.
+    pub fn assert_options(&self) -> assert::Options {
+        assert::Options {
+            payout_locktime: self.payout_locktime,
+            committee_pubkey: Some(self.committee_pubkey),
+            internal_key: self.internal_key,
+        }
+    }
+
+    /// Build taptree from leaves with their weights.
+    pub(crate) fn taptree(
+        &self,
+        ctx: &Secp256k1<All>,
+        leaves: impl IntoIterator<Item = (u32, Script)>,
+    ) -> TaprootSpendInfo {
+        TaprootBuilder::with_huffman_tree(leaves)
+            .expect("Number of leaves in graph outputs is small")
+            .finalize(ctx, self.internal_key)
+            .expect("Scripts and keys should be valid")
+    }
+}
+
+/// Output of the graph spent by one of its taptree leaves.
+#[derive(Debug, Clone)]
+pub struct LeafSpend {
+    /// Outpoint of the spent output.
+    pub outpoint: OutPoint,
+
+    /// The spent output.
+    pub txout: TxOut,
+
+    /// The leaf script by which the output is spent.
+    pub leaf: Script,
+
+    /// Control block of the leaf.
+    pub control_block: ControlBlock,
+}
+
+impl LeafSpend {
+    pub(crate) fn new(
+        taptree: &TaprootSpendInfo,
+        outpoint: OutPoint,
+        txout: TxOut,
+        leaf: Script,
+    ) -> Self {
+        let control_block = taptree
+            .control_block(&(leaf.clone(), LeafVersion::TapScript))
+            .expect("leaf should be in the taptree");
+
+        Self {
+            outpoint,
+            txout,
+            leaf,
+            control_block,
+        }
+    }
+
+    /// Unsigned input spending the output.
+    pub(crate) fn txin(&self, sequence: Sequence) -> TxIn {
+        TxIn {
+            previous_output: self.outpoint,
+            script_sig: Script::new(),
+            sequence,
+            witness: Witness::new(),
+        }
+    }
+
+    /// Form witness from `elements` (the first one is the deepest on the
+    /// stack), leaf script and control block.
+    pub(crate) fn witness(&self, elements: &[&[u8]]) -> Witness {
+        let mut witness = Witness::new();
+
+        for elem in elements {
+            witness.push(elem);
+        }
+        witness.push(self.leaf.as_bytes());
+        witness.push(self.control_block.serialize());
+
+        witness
+    }
+}
+
+/// Return sighash of the `input` of `tx`, which spends `spends[input]`.
+pub(crate) fn leaf_sighash(
+    tx: &Transaction,
+    spends: &[LeafSpend],
+    input: usize,
+    sighash_type: TapSighashType,
+) -> eyre::Result<TapSighash> {
+    let prevouts = spends
+        .iter()
+        .map(|spend| spend.txout.clone())
+        .collect::<Vec<_>>();
+
+    let prevouts = match sighash_type {
+        TapSighashType::AllPlusAnyoneCanPay
+        | TapSighashType::NonePlusAnyoneCanPay
+        | TapSighashType::SinglePlusAnyoneCanPay => Prevouts::One(input, prevouts[input].clone()),
+        _ => Prevouts::All(&prevouts),
+    };
+
+    SighashCache::new(tx)
+        .taproot_script_spend_signature_hash(
+            input,
+            &prevouts,
+            spends[input].leaf.tapscript_leaf_hash(),
+            sighash_type,
+        )
+        .map_err(Into::into)
+}
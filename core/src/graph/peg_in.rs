@@ -0,0 +1,92 @@
+use bitcoin::{
+    absolute::LockTime, key::Secp256k1, secp256k1::All, taproot::TaprootSpendInfo,
+    transaction::Version, Amount, OutPoint, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+
+use crate::treepp::*;
+
+use super::{GraphConfig, LeafSpend};
+
+/// Index of the deposit output in the PegIn transaction.
+pub const PEG_IN_VOUT: u32 = 0;
+
+/// Transaction by which the depositor locks funds under the committee key.
+///
+/// The deposit can be spent only by transactions presigned by the
+/// committee, which are Take1 and Take2.
+#[derive(Debug, Clone)]
+pub struct PegIn {
+    tx: Transaction,
+    taptree: TaprootSpendInfo,
+}
+
+impl PegIn {
+    /// Construct PegIn transaction spending depositor's `inputs`. Inputs are
+    /// left unsigned, as they are signed by the depositor's wallet.
+    pub fn new(
+        ctx: &Secp256k1<All>,
+        config: &GraphConfig,
+        amount: Amount,
+        inputs: Vec<OutPoint>,
+        change: Option<TxOut>,
+    ) -> Self {
+        let taptree = config.taptree(ctx, [(1, Self::deposit_leaf(config))]);
+
+        let deposit = TxOut {
+            value: amount,
+            script_pubkey: Script::new_p2tr_tweaked(taptree.output_key()),
+        };
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs
+                .into_iter()
+                .map(|previous_output| TxIn {
+                    previous_output,
+                    script_sig: Script::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: [deposit].into_iter().chain(change).collect(),
+        };
+
+        Self { tx, taptree }
+    }
+
+    /// Leaf of the deposit output.
+    ///
+    /// ## Witness:
+    /// ```bitcoin_script
+    /// { committee_signature }
+    /// ```
+    pub fn deposit_leaf(config: &GraphConfig) -> Script {
+        script! {
+            { config.committee_pubkey.serialize().to_vec() }
+            OP_CHECKSIG
+        }
+    }
+
+    /// Unsigned transaction, which should be signed by the depositor.
+    pub fn unsigned_tx(&self) -> &Transaction {
+        &self.tx
+    }
+
+    pub fn txid(&self) -> Txid {
+        self.tx.compute_txid()
+    }
+
+    pub fn deposit_txout(&self) -> &TxOut {
+        &self.tx.output[PEG_IN_VOUT as usize]
+    }
+
+    pub(crate) fn deposit_spend(&self, config: &GraphConfig) -> LeafSpend {
+        LeafSpend::new(
+            &self.taptree,
+            OutPoint::new(self.txid(), PEG_IN_VOUT),
+            self.deposit_txout().clone(),
+            Self::deposit_leaf(config),
+        )
+    }
+}
@@ -0,0 +1,85 @@
+use bitcoin::{
+    absolute::LockTime,
+    key::{Keypair, Secp256k1},
+    secp256k1::{schnorr, All, SecretKey},
+    transaction::Version,
+    Sequence, TapSighash, TapSighashType, Transaction, TxOut, Txid,
+};
+
+use super::{leaf_sighash, GraphConfig, Kickoff, LeafSpend, PegIn};
+
+const PEG_IN_INPUT: usize = 0;
+
+/// Transaction by which the operator takes the deposit if nobody challenged
+/// the Kickoff in time.
+///
+/// Spends the deposit, and both Kickoff connectors.
+#[derive(Debug, Clone)]
+pub struct Take1 {
+    tx: Transaction,
+    spends: Vec<LeafSpend>,
+}
+
+impl Take1 {
+    pub fn new(config: &GraphConfig, peg_in: &PegIn, kickoff: &Kickoff, txout: TxOut) -> Self {
+        let spends = vec![
+            peg_in.deposit_spend(config),
+            kickoff.take1_spend(),
+            kickoff.challenge_spend(),
+        ];
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![
+                spends[0].txin(Sequence::ZERO),
+                spends[1].txin(Sequence::from_height(config.challenge_timeout.value())),
+                spends[2].txin(Sequence::ZERO),
+            ],
+            output: vec![txout],
+        };
+
+        Self { tx, spends }
+    }
+
+    pub fn unsigned_tx(&self) -> &Transaction {
+        &self.tx
+    }
+
+    pub fn txid(&self) -> Txid {
+        self.tx.compute_txid()
+    }
+
+    /// Sighash of the deposit input, which is presigned by the committee.
+    pub fn peg_in_sighash(&self) -> eyre::Result<TapSighash> {
+        leaf_sighash(
+            &self.tx,
+            &self.spends,
+            PEG_IN_INPUT,
+            TapSighashType::Default,
+        )
+    }
+
+    /// Sign connector inputs by operator and fill the witness of all inputs.
+    pub fn finalize(
+        self,
+        ctx: &Secp256k1<All>,
+        operator_seckey: &SecretKey,
+        committee_signature: &schnorr::Signature,
+    ) -> eyre::Result<Transaction> {
+        let keypair = Keypair::from_secret_key(ctx, operator_seckey);
+        let mut tx = self.tx.clone();
+
+        tx.input[PEG_IN_INPUT].witness =
+            self.spends[PEG_IN_INPUT].witness(&[&committee_signature[..]]);
+
+        for input in 1..self.spends.len() {
+            let sighash = leaf_sighash(&self.tx, &self.spends, input, TapSighashType::Default)?;
+            let signature = ctx.sign_schnorr(&sighash.into(), &keypair);
+
+            tx.input[input].witness = self.spends[input].witness(&[&signature[..]]);
+        }
+
+        Ok(tx)
+    }
+}
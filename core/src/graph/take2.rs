@@ -0,0 +1,102 @@
+use bitcoin::{
+    absolute::LockTime,
+    key::{Keypair, Secp256k1},
+    secp256k1::{schnorr, All, SecretKey},
+    transaction::Version,
+    Sequence, TapSighash, TapSighashType, Transaction, TxOut, Txid,
+};
+use bitcoin_splitter::split::script::SplitableScript;
+
+use super::{leaf_sighash, Assert, GraphConfig, LeafSpend, PegIn};
+
+const PEG_IN_INPUT: usize = 0;
+const ASSERT_INPUT: usize = 1;
+
+/// Transaction by which the operator takes the deposit after the Assert
+/// wasn't disproved in time.
+///
+/// Spends the deposit, and the Assert output by the payout leaf.
+#[derive(Debug, Clone)]
+pub struct Take2 {
+    tx: Transaction,
+    spends: Vec<LeafSpend>,
+}
+
+impl Take2 {
+    pub fn new<S: SplitableScript>(
+        ctx: &Secp256k1<All>,
+        config: &GraphConfig,
+        peg_in: &PegIn,
+        assert: &Assert<S>,
+        txout: TxOut,
+    ) -> Self {
+        let spends = vec![peg_in.deposit_spend(config), assert.payout_spend(ctx)];
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![
+                spends[PEG_IN_INPUT].txin(Sequence::ZERO),
+                spends[ASSERT_INPUT].txin(Sequence::from_height(
+                    assert.assert.payout_script.locktime.value(),
+                )),
+            ],
+            output: vec![txout],
+        };
+
+        Self { tx, spends }
+    }
+
+    pub fn unsigned_tx(&self) -> &Transaction {
+        &self.tx
+    }
+
+    pub fn txid(&self) -> Txid {
+        self.tx.compute_txid()
+    }
+
+    /// Sighashes of the deposit and the Assert inputs, which are presigned
+    /// by the committee.
+    pub fn sighashes(&self) -> eyre::Result<[TapSighash; 2]> {
+        Ok([
+            leaf_sighash(
+                &self.tx,
+                &self.spends,
+                PEG_IN_INPUT,
+                TapSighashType::Default,
+            )?,
+            leaf_sighash(
+                &self.tx,
+                &self.spends,
+                ASSERT_INPUT,
+                TapSighashType::Default,
+            )?,
+        ])
+    }
+
+    /// Sign the Assert input by operator and fill the witness of all inputs
+    /// with committee signatures over [`Self::sighashes`].
+    pub fn finalize(
+        self,
+        ctx: &Secp256k1<All>,
+        operator_seckey: &SecretKey,
+        committee_signatures: &[schnorr::Signature; 2],
+    ) -> eyre::Result<Transaction> {
+        let keypair = Keypair::from_secret_key(ctx, operator_seckey);
+        let [_, assert_sighash] = self.sighashes()?;
+        let signature = ctx.sign_schnorr(&assert_sighash.into(), &keypair);
+        let operator_pubkey = keypair.x_only_public_key().0.serialize();
+
+        let mut tx = self.tx;
+
+        tx.input[PEG_IN_INPUT].witness =
+            self.spends[PEG_IN_INPUT].witness(&[&committee_signatures[0][..]]);
+        tx.input[ASSERT_INPUT].witness = self.spends[ASSERT_INPUT].witness(&[
+            &signature[..],
+            &operator_pubkey,
+            &committee_signatures[1][..],
+        ]);
+
+        Ok(tx)
+    }
+}
@@ -0,0 +1,540 @@
+use std::str::FromStr as _;
+
+use bitcoin::{
+    key::{rand::thread_rng, Secp256k1},
+    secp256k1::{schnorr, All, SecretKey},
+    Amount, OutPoint, Sequence, TapSighash, Transaction, TxIn, TxOut, Witness,
+};
+use bitcoin_scriptexec::ExecError;
+use bitcoin_splitter::split::{
+    core::SplitType,
+    script::{IOPair, SplitableScript},
+};
+use bitcoin_testscripts::{
+    int_mul_windowed::U254MulScript, square_fibonacci::SquareFibonacciScript,
+};
+
+use crate::{
+    assert::{
+        commitment::{disprove_witness, StatesCommitment},
+        payout_script::PayoutScript,
+        AssertTransaction,
+    },
+    committee::{verify_signature, Committee},
+    disprove::{
+        derivation::KeyDerivation, find_disprovable_shards, sign_states_derived,
+        signing::SignedIntermediateState, witness_elements, Distortion,
+    },
+    test_utils::{committee_sign, execute_script_spend},
+    treepp::*,
+};
+
+use super::{
+    assert_tx::ASSERT_VOUT,
+    kickoff::{CONNECTOR_A_VOUT, CONNECTOR_C_VOUT},
+    peg_in::PEG_IN_VOUT,
+    Assert, Challenge, GraphConfig, Kickoff, PegIn, Take1, Take2,
+};
+
+type FibonacciScript = SquareFibonacciScript<64>;
+
+/// Keys of the participants and the transactions which start the graph.
+struct Setup {
+    ctx: Secp256k1<All>,
+    operator_seckey: SecretKey,
+    seckeys: Vec<SecretKey>,
+    committee: Committee,
+    config: GraphConfig,
+    peg_in: PegIn,
+    kickoff: Kickoff,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let ctx = Secp256k1::new();
+        let mut rng = thread_rng();
+
+        let operator_seckey = SecretKey::new(&mut rng);
+        let operator_xonly = operator_seckey.public_key(&ctx).x_only_public_key().0;
+
+        let seckeys = (0..3).map(|_| SecretKey::new(&mut rng)).collect::<Vec<_>>();
+        let committee =
+            Committee::new(&ctx, seckeys.iter().map(|sk| sk.public_key(&ctx)).collect()).unwrap();
+
+        let config = GraphConfig::new(operator_xonly, committee.aggregated_pubkey());
+        let outpoint = OutPoint::from_str(
+            "a85d89b4666fed622281d3589474aa1f87971b54bd5d9c1899ed2e8e0447cc06:0",
+        )
+        .unwrap();
+
+        let peg_in = PegIn::new(
+            &ctx,
+            &config,
+            Amount::from_sat(100_000),
+            vec![outpoint],
+            None,
+        );
+        let kickoff = Kickoff::new(
+            &ctx,
+            &config,
+            OutPoint::new(outpoint.txid, 1),
+            TxOut {
+                value: Amount::from_sat(60_000),
+                script_pubkey: Script::new_p2tr(&ctx, operator_xonly, None),
+            },
+            Amount::from_sat(50_000),
+            Amount::from_sat(1_000),
+        );
+
+        Self {
+            ctx,
+            operator_seckey,
+            seckeys,
+            committee,
+            config,
+            peg_in,
+            kickoff,
+        }
+    }
+
+    fn operator_txout(&self, sats: u64) -> TxOut {
+        TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey: Script::new_p2tr(&self.ctx, self.config.operator_pubkey, None),
+        }
+    }
+
+    fn committee_sign(&self, sighash: TapSighash) -> schnorr::Signature {
+        committee_sign(&self.ctx, &self.committee, &self.seckeys, sighash)
+    }
+
+    /// Assert of the graph with disprove scripts of the honest execution,
+    /// and the signed states it commits.
+    fn assert(&self) -> (Assert<FibonacciScript>, Vec<SignedIntermediateState>) {
+        let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+        let assert_tx = AssertTransaction::<FibonacciScript>::with_options(
+            input,
+            self.config.operator_pubkey,
+            Amount::from_sat(49_000),
+            self.config.assert_options(),
+            &self.key_derivation(),
+        )
+        .unwrap();
+
+        let states = self.signed_states(None);
+        let (commitment, prevouts) = self.commitment(&states);
+
+        (
+            Assert::new(
+                &self.ctx,
+                &self.config,
+                &self.kickoff,
+                assert_tx,
+                commitment,
+                prevouts,
+            )
+            .unwrap(),
+            states,
+        )
+    }
+
+    /// Assert of the graph with the distorted state, returning the index of
+    /// the first disprovable shard and the signed states it commits.
+    fn assert_distorted(&self) -> (Assert<FibonacciScript>, usize, Vec<SignedIntermediateState>) {
+        let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+        let (assert_tx, distorted_id) =
+            AssertTransaction::<FibonacciScript>::with_options_distorted_by(
+                input,
+                self.config.operator_pubkey,
+                Amount::from_sat(49_000),
+                self.config.assert_options(),
+                Distortion::Shard(1),
+                &self.key_derivation(),
+            )
+            .unwrap();
+
+        let states = self.signed_states(Some(distorted_id));
+        let (commitment, prevouts) = self.commitment(&states);
+
+        (
+            Assert::new(
+                &self.ctx,
+                &self.config,
+                &self.kickoff,
+                assert_tx,
+                commitment,
+                prevouts,
+            )
+            .unwrap(),
+            distorted_id,
+            states,
+        )
+    }
+
+    /// Signed states of the execution on the valid input, where the output
+    /// state of the shard `distorted_id` is distorted, if provided.
+    fn signed_states(&self, distorted_id: Option<usize>) -> Vec<SignedIntermediateState> {
+        let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+        let split_result =
+            FibonacciScript::default_split(input.clone(), SplitType::default()).unwrap();
+        let split_result = match distorted_id {
+            Some(idx) => split_result.distort_at(idx),
+            None => split_result,
+        };
+
+        sign_states_derived(&input, &split_result, &self.key_derivation()).unwrap()
+    }
+
+    /// Commitment of `states` and the outputs locking it, which are funded
+    /// by the same transaction as the Kickoff.
+    fn commitment(
+        &self,
+        states: &[SignedIntermediateState],
+    ) -> (StatesCommitment, Vec<(OutPoint, TxOut)>) {
+        let commitment = StatesCommitment::new(self.config.operator_pubkey, states);
+        let funding_txid = self.kickoff.unsigned_tx().input[0].previous_output.txid;
+        let prevouts = commitment
+            .txouts(&self.ctx, self.config.internal_key, Amount::from_sat(1_000))
+            .into_iter()
+            .enumerate()
+            .map(|(idx, txout)| (OutPoint::new(funding_txid, 2 + idx as u32), txout))
+            .collect();
+
+        (commitment, prevouts)
+    }
+
+    /// Outputs spent by the inputs of Assert committing `states`.
+    fn assert_prevouts(&self, states: &[SignedIntermediateState]) -> Vec<TxOut> {
+        std::iter::once(self.connector_txout(CONNECTOR_A_VOUT))
+            .chain(
+                self.commitment(states)
+                    .1
+                    .into_iter()
+                    .map(|(_, txout)| txout),
+            )
+            .collect()
+    }
+
+    /// Key derivation of the Assert spending the Kickoff connector A.
+    fn key_derivation(&self) -> KeyDerivation {
+        KeyDerivation::for_program::<FibonacciScript>(
+            [42; 32],
+            OutPoint::new(self.kickoff.txid(), CONNECTOR_A_VOUT),
+        )
+    }
+
+    fn connector_txout(&self, vout: u32) -> TxOut {
+        self.kickoff.unsigned_tx().output[vout as usize].clone()
+    }
+}
+
+/// Assert that each input of `tx` spending `prevouts` is executed
+/// successfully.
+fn assert_inputs_are_valid(tx: &Transaction, prevouts: &[TxOut]) {
+    for input_idx in 0..tx.input.len() {
+        let result = execute_script_spend(tx, prevouts, input_idx);
+        assert!(
+            result.success,
+            "input {input_idx} is invalid: {:?}",
+            result.error
+        );
+    }
+}
+
+#[test]
+fn test_graph_txids_are_consistent() {
+    let setup = Setup::new();
+    let Setup {
+        ctx,
+        operator_seckey,
+        config,
+        peg_in,
+        kickoff,
+        ..
+    } = &setup;
+    let committee_pubkey = config.committee_pubkey;
+
+    let take1 = Take1::new(config, peg_in, kickoff, setup.operator_txout(150_000));
+    let inputs = &take1.unsigned_tx().input;
+    assert_eq!(
+        inputs[0].previous_output,
+        OutPoint::new(peg_in.txid(), PEG_IN_VOUT)
+    );
+    assert_eq!(
+        inputs[1].previous_output,
+        OutPoint::new(kickoff.txid(), CONNECTOR_A_VOUT)
+    );
+    assert_eq!(
+        inputs[1].sequence,
+        Sequence::from_height(config.challenge_timeout.value())
+    );
+    assert_eq!(
+        inputs[2].previous_output,
+        OutPoint::new(kickoff.txid(), CONNECTOR_C_VOUT)
+    );
+
+    let take1_sighash = take1.peg_in_sighash().unwrap();
+    let signature = setup.committee_sign(take1_sighash);
+    let take1_txid = take1.txid();
+    let take1 = take1.finalize(ctx, operator_seckey, &signature).unwrap();
+    assert_eq!(take1.compute_txid(), take1_txid, "witness changed txid");
+
+    let challenge = Challenge::new(kickoff, setup.operator_txout(10_000));
+    let operator_signature = challenge.presign(ctx, operator_seckey).unwrap();
+    let challenge = challenge.finalize(&operator_signature, vec![], vec![]);
+    assert_eq!(
+        challenge.input[0].previous_output,
+        OutPoint::new(kickoff.txid(), CONNECTOR_C_VOUT)
+    );
+
+    let (assert, states) = setup.assert();
+    assert!(!assert.assert.disprove_scripts.is_empty());
+    assert_eq!(
+        assert.unsigned_tx().input[0].previous_output,
+        OutPoint::new(kickoff.txid(), CONNECTOR_A_VOUT)
+    );
+    assert_eq!(
+        assert.unsigned_tx().input.len(),
+        1 + assert.commitment.inputs_num()
+    );
+
+    let sighash = assert.sighash().unwrap();
+    let signature = setup.committee_sign(sighash);
+    let signed_assert = assert
+        .finalize(ctx, operator_seckey, &signature, &states)
+        .unwrap();
+    assert_eq!(signed_assert.compute_txid(), assert.txid());
+
+    let take2 = Take2::new(ctx, config, peg_in, &assert, setup.operator_txout(148_000));
+    let inputs = &take2.unsigned_tx().input;
+    assert_eq!(
+        inputs[0].previous_output,
+        OutPoint::new(peg_in.txid(), PEG_IN_VOUT)
+    );
+    assert_eq!(inputs[1].previous_output, assert.outpoint());
+
+    let sighashes = take2.sighashes().unwrap();
+    let signatures = sighashes.map(|sighash| setup.committee_sign(sighash));
+    for (sighash, signature) in sighashes.iter().zip(signatures.iter()) {
+        assert!(verify_signature(
+            ctx,
+            &committee_pubkey,
+            *sighash,
+            signature
+        ));
+    }
+    let take2_txid = take2.txid();
+    let take2 = take2.finalize(ctx, operator_seckey, &signatures).unwrap();
+    assert_eq!(take2.compute_txid(), take2_txid);
+}
+
+#[test]
+fn test_take1_spends_are_valid() {
+    let setup = Setup::new();
+
+    let take1 = Take1::new(
+        &setup.config,
+        &setup.peg_in,
+        &setup.kickoff,
+        setup.operator_txout(150_000),
+    );
+    let signature = setup.committee_sign(take1.peg_in_sighash().unwrap());
+    let take1 = take1
+        .finalize(&setup.ctx, &setup.operator_seckey, &signature)
+        .unwrap();
+
+    let prevouts = [
+        setup.peg_in.deposit_txout().clone(),
+        setup.connector_txout(CONNECTOR_A_VOUT),
+        setup.connector_txout(CONNECTOR_C_VOUT),
+    ];
+    assert_inputs_are_valid(&take1, &prevouts);
+
+    // Connector A can't be spent by Take1 before the challenge timeout
+    let mut early = take1.clone();
+    early.input[1].sequence = Sequence::from_height(setup.config.challenge_timeout.value() - 1);
+    let result = execute_script_spend(&early, &prevouts, 1);
+    assert!(!result.success);
+    assert_eq!(result.error, Some(ExecError::UnsatisfiedLocktime));
+}
+
+#[test]
+fn test_challenge_spend_is_valid_with_added_inputs() {
+    let setup = Setup::new();
+
+    let challenge = Challenge::new(&setup.kickoff, setup.operator_txout(10_000));
+    let operator_signature = challenge
+        .presign(&setup.ctx, &setup.operator_seckey)
+        .unwrap();
+
+    // Challengers add their inputs and outputs after the operator signed
+    let funding = TxIn {
+        previous_output: OutPoint::new(setup.peg_in.txid(), 1),
+        script_sig: Script::new(),
+        sequence: Sequence::ZERO,
+        witness: Witness::new(),
+    };
+    let funding_txout = setup.operator_txout(20_000);
+    let challenge = challenge.finalize(
+        &operator_signature,
+        vec![funding],
+        vec![setup.operator_txout(9_000)],
+    );
+
+    let prevouts = [setup.connector_txout(CONNECTOR_C_VOUT), funding_txout];
+    let result = execute_script_spend(&challenge, &prevouts, 0);
+    assert!(result.success, "challenge is invalid: {:?}", result.error);
+
+    // The payment to the operator is committed by the signature
+    let mut underpaid = challenge.clone();
+    underpaid.output[0].value = Amount::from_sat(1_000);
+    let result = execute_script_spend(&underpaid, &prevouts, 0);
+    assert!(!result.success);
+    assert_eq!(result.error, Some(ExecError::SchnorrSig));
+}
+
+#[test]
+fn test_assert_take2_and_disprove_spends_are_valid() {
+    let setup = Setup::new();
+    let (assert, distorted_id, states) = setup.assert_distorted();
+
+    let signature = setup.committee_sign(assert.sighash().unwrap());
+    let signed_assert = assert
+        .finalize(&setup.ctx, &setup.operator_seckey, &signature, &states)
+        .unwrap();
+    assert_inputs_are_valid(&signed_assert, &setup.assert_prevouts(&states));
+
+    let assert_txout = assert.unsigned_tx().output[ASSERT_VOUT as usize].clone();
+
+    let take2 = Take2::new(
+        &setup.ctx,
+        &setup.config,
+        &setup.peg_in,
+        &assert,
+        setup.operator_txout(148_000),
+    );
+    let signatures = take2
+        .sighashes()
+        .unwrap()
+        .map(|sighash| setup.committee_sign(sighash));
+    let take2 = take2
+        .finalize(&setup.ctx, &setup.operator_seckey, &signatures)
+        .unwrap();
+
+    let prevouts = [setup.peg_in.deposit_txout().clone(), assert_txout.clone()];
+    assert_inputs_are_valid(&take2, &prevouts);
+
+    // The Assert output can't be spent by Take2 before the payout locktime
+    let mut early = take2.clone();
+    early.input[1].sequence = Sequence::from_height(setup.config.payout_locktime.value() - 1);
+    let result = execute_script_spend(&early, &prevouts, 1);
+    assert!(!result.success);
+    assert_eq!(result.error, Some(ExecError::UnsatisfiedLocktime));
+
+    let txout = setup.operator_txout(48_000);
+    let signatures = assert
+        .disprove_sighashes(&setup.ctx, txout.clone())
+        .unwrap()
+        .into_iter()
+        .map(|sighash| setup.committee_sign(sighash))
+        .collect::<Vec<_>>();
+    let disprove_txs = assert
+        .disprove_transactions(&setup.ctx, txout, &signatures)
+        .unwrap();
+
+    let disprove = &disprove_txs[&assert.assert.disprove_scripts[distorted_id]];
+    assert_eq!(disprove.input[0].previous_output, assert.outpoint());
+    assert_inputs_are_valid(disprove, &[assert_txout]);
+}
+
+#[test]
+fn test_kickoff_assert_disprove_flow() {
+    let setup = Setup::new();
+    let (assert, distorted_id, states) = setup.assert_distorted();
+
+    // The operator publishes Assert spending the Kickoff connector A and
+    // the commitment outputs
+    let kickoff = setup.kickoff.unsigned_tx();
+    assert_eq!(
+        assert.unsigned_tx().input[0].previous_output,
+        OutPoint::new(kickoff.compute_txid(), CONNECTOR_A_VOUT)
+    );
+    let signature = setup.committee_sign(assert.sighash().unwrap());
+    let signed_assert = assert
+        .finalize(&setup.ctx, &setup.operator_seckey, &signature, &states)
+        .unwrap();
+    assert_eq!(signed_assert.compute_txid(), assert.txid());
+    assert_inputs_are_valid(&signed_assert, &setup.assert_prevouts(&states));
+
+    // The challenger takes the states from the confirmed Assert and finds
+    // the incorrect transition
+    let committed = assert.committed_states(&signed_assert).unwrap();
+    assert_eq!(committed.len(), assert.assert.disprove_scripts.len() + 1);
+
+    let IOPair { input, .. } = FibonacciScript::generate_valid_io_pair();
+    let disprovable =
+        find_disprovable_shards::<FibonacciScript>(input, &assert.assert.disprove_scripts).unwrap();
+    assert_eq!(disprovable.first(), Some(&distorted_id));
+
+    // The witness of the disprove script is formed from the published
+    // states, and spends the Assert output
+    let disprove_script = &assert.assert.disprove_scripts[distorted_id];
+    let witness = disprove_witness(&committed[distorted_id], &committed[distorted_id + 1]);
+    assert_eq!(
+        witness_elements(&witness),
+        disprove_script.witness_elements()
+    );
+
+    let txout = setup.operator_txout(48_000);
+    let signatures = assert
+        .disprove_sighashes(&setup.ctx, txout.clone())
+        .unwrap()
+        .into_iter()
+        .map(|sighash| setup.committee_sign(sighash))
+        .collect::<Vec<_>>();
+    let disprove_txs = assert
+        .disprove_transactions(&setup.ctx, txout, &signatures)
+        .unwrap();
+
+    let assert_txout = signed_assert.output[ASSERT_VOUT as usize].clone();
+    assert_inputs_are_valid(&disprove_txs[disprove_script], &[assert_txout]);
+}
+
+#[test]
+fn test_assert_should_use_graph_committee() {
+    let ctx = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let operator_xonly = SecretKey::new(&mut rng)
+        .public_key(&ctx)
+        .x_only_public_key()
+        .0;
+    let committee_pubkey = SecretKey::new(&mut rng)
+        .public_key(&ctx)
+        .x_only_public_key()
+        .0;
+
+    let config = GraphConfig::new(operator_xonly, committee_pubkey);
+    let kickoff = Kickoff::new(
+        &ctx,
+        &config,
+        OutPoint::null(),
+        TxOut {
+            value: Amount::from_sat(60_000),
+            script_pubkey: Script::new_p2tr(&ctx, operator_xonly, None),
+        },
+        Amount::from_sat(50_000),
+        Amount::from_sat(1_000),
+    );
+
+    let assert_tx = AssertTransaction::<U254MulScript>::from_scripts(
+        operator_xonly,
+        PayoutScript::new(operator_xonly),
+        vec![],
+        Amount::from_sat(49_000),
+        config.internal_key,
+    );
+
+    let commitment = StatesCommitment::new(operator_xonly, &[]);
+    assert!(Assert::new(&ctx, &config, &kickoff, assert_tx, commitment, vec![]).is_err());
+}
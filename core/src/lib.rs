@@ -2,7 +2,14 @@ use bitcoin::XOnlyPublicKey;
 use once_cell::sync::Lazy;
 
 pub mod assert;
+pub mod bundle;
+pub mod committee;
 pub mod disprove;
+pub mod graph;
+pub mod nums;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 #[allow(dead_code)]
 // Re-export what is needed to write treepp scripts
@@ -14,13 +21,9 @@ pub mod treepp {
     pub use bitcoin::ScriptBuf as Script;
 }
 
-// FIXME(Velnbur): Use really non spendable key. For example checkout:
-// 1. https://github.com/nomic-io/nomic/blob/5ba8b661e6d9ffb6b9eb39c13247cccefa5342a9/src/babylon/mod.rs#L451
-pub static UNSPENDABLE_KEY: Lazy<XOnlyPublicKey> = Lazy::new(|| {
-    "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0"
-        .parse()
-        .unwrap()
-});
+/// Default internal key of taptrees, which is the BIP-341 `H` point without
+/// randomness. See [`nums::NumsKey`] for per-instance keys.
+pub static UNSPENDABLE_KEY: Lazy<XOnlyPublicKey> = Lazy::new(|| nums::NumsKey::h().xonly());
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,133 @@
+//! Provably unspendable ("nothing up my sleeve") taproot internal keys.
+//!
+//! The base point is `H` from
+//! [BIP-341](https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#constructing-and-spending-taproot-outputs),
+//! which x coordinate is the SHA-256 of the uncompressed encoding of the
+//! generator `G`, so nobody knows its discrete logarithm.
+//!
+//! Using the same `H` for all outputs lets anyone recognize that the key
+//! path is disabled, so each instance can be tweaked as `H + r*G` with
+//! random `r`. Knowing the discrete logarithm of `H + r*G` is equivalent to
+//! knowing the one of `H`, so by revealing `r` to a third party it can
+//! audit that the key is unspendable (see [`NumsKey::verify`]).
+
+use bitcoin::{
+    hashes::{sha256, Hash},
+    key::{
+        rand::{CryptoRng, Rng},
+        Parity, Secp256k1,
+    },
+    secp256k1::{
+        constants::{GENERATOR_X, GENERATOR_Y},
+        PublicKey, SecretKey, Signing, Verification,
+    },
+    XOnlyPublicKey,
+};
+
+/// Provably unspendable internal key, optionally tweaked with randomness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumsKey {
+    key: XOnlyPublicKey,
+    randomness: Option<SecretKey>,
+}
+
+impl NumsKey {
+    /// The `H` point itself without randomness.
+    pub fn h() -> Self {
+        Self {
+            key: h_point().x_only_public_key().0,
+            randomness: None,
+        }
+    }
+
+    /// Generate key `H + r*G` with random `r`.
+    pub fn random<C: Signing, R: Rng + CryptoRng>(ctx: &Secp256k1<C>, rng: &mut R) -> Self {
+        Self::from_randomness(ctx, SecretKey::new(rng))
+    }
+
+    /// Construct key `H + r*G` from known randomness `r`.
+    pub fn from_randomness<C: Signing>(ctx: &Secp256k1<C>, randomness: SecretKey) -> Self {
+        let key = h_point()
+            .combine(&randomness.public_key(ctx))
+            .expect("H has unknown discrete logarithm, so H + r*G is never infinity");
+
+        Self {
+            key: key.x_only_public_key().0,
+            randomness: Some(randomness),
+        }
+    }
+
+    /// The internal key to use in taptree.
+    pub fn xonly(&self) -> XOnlyPublicKey {
+        self.key
+    }
+
+    /// Randomness `r` the key was tweaked with, which should be revealed
+    /// to the auditors of the key. `None` if the key is `H`.
+    pub fn randomness(&self) -> Option<SecretKey> {
+        self.randomness
+    }
+
+    /// Check that `key` was derived from `H` with provided `randomness`,
+    /// so nobody knows its discrete logarithm.
+    pub fn verify<C: Signing + Verification>(
+        ctx: &Secp256k1<C>,
+        key: &XOnlyPublicKey,
+        randomness: Option<SecretKey>,
+    ) -> bool {
+        let expected = match randomness {
+            Some(randomness) => Self::from_randomness(ctx, randomness),
+            None => Self::h(),
+        };
+
+        expected.key == *key
+    }
+}
+
+/// Return the `H` point, which is `lift_x(SHA256(G))`, where `G` is in
+/// uncompressed encoding.
+fn h_point() -> PublicKey {
+    let mut generator = [0u8; 65];
+    generator[0] = 0x04;
+    generator[1..33].copy_from_slice(&GENERATOR_X);
+    generator[33..].copy_from_slice(&GENERATOR_Y);
+
+    let x = sha256::Hash::hash(&generator);
+
+    XOnlyPublicKey::from_slice(x.as_byte_array())
+        .expect("SHA256(G) is a valid x coordinate")
+        .public_key(Parity::Even)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::key::rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn test_h_is_bip341_point() {
+        let expected: XOnlyPublicKey =
+            "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0"
+                .parse()
+                .unwrap();
+
+        assert_eq!(NumsKey::h().xonly(), expected);
+    }
+
+    #[test]
+    fn test_random_key_is_verifiable() {
+        let ctx = Secp256k1::new();
+        let mut rng = thread_rng();
+
+        let key = NumsKey::random(&ctx, &mut rng);
+        assert_ne!(key.xonly(), NumsKey::h().xonly());
+        assert!(NumsKey::verify(&ctx, &key.xonly(), key.randomness()));
+        assert!(!NumsKey::verify(&ctx, &key.xonly(), None));
+        assert!(!NumsKey::verify(
+            &ctx,
+            &key.xonly(),
+            Some(SecretKey::new(&mut rng))
+        ));
+    }
+}
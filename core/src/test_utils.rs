@@ -0,0 +1,86 @@
+//! Helpers shared by the tests of the crate.
+
+use bitcoin::{
+    key::{rand::thread_rng, Secp256k1},
+    secp256k1::{schnorr, All, SecretKey},
+    taproot::{ControlBlock, LeafVersion, TapLeafHash},
+    TapSighash, Transaction, TxOut, XOnlyPublicKey,
+};
+use bitcoin_scriptexec::{Exec, ExecCtx, ExecutionResult, Options, TxTemplate};
+
+use crate::{
+    committee::{musig2::SecNonce, Committee},
+    treepp::*,
+};
+
+/// Sign `sighash` by all the members of the `committee` with `seckeys`.
+pub(crate) fn committee_sign(
+    ctx: &Secp256k1<All>,
+    committee: &Committee,
+    seckeys: &[SecretKey],
+    sighash: TapSighash,
+) -> schnorr::Signature {
+    let mut rng = thread_rng();
+
+    let (secnonces, pubnonces): (Vec<_>, Vec<_>) = seckeys
+        .iter()
+        .map(|_| SecNonce::generate(ctx, &mut rng))
+        .unzip();
+    let session = committee.signing_session(ctx, &pubnonces, sighash).unwrap();
+    let partials = seckeys
+        .iter()
+        .zip(secnonces)
+        .map(|(sk, secnonce)| session.partial_sign(ctx, sk, secnonce).unwrap())
+        .collect::<Vec<_>>();
+
+    session.aggregate(&partials).unwrap()
+}
+
+/// Execute the taproot script path spend of the input `input_idx` of `tx`
+/// with the interpreter, where `prevouts` are the outputs spent by all the
+/// inputs of `tx`. The default options are used, so the stack limit is
+/// enforced.
+///
+/// Panics if the witness of the input is not a script path spend of the
+/// leaf committed in the spent output.
+pub(crate) fn execute_script_spend(
+    tx: &Transaction,
+    prevouts: &[TxOut],
+    input_idx: usize,
+) -> ExecutionResult {
+    let mut stack = tx.input[input_idx].witness.to_vec();
+    let control_block = stack.pop().expect("witness is empty");
+    let control_block = ControlBlock::decode(&control_block).expect("invalid control block");
+    let script = Script::from_bytes(stack.pop().expect("witness has no leaf script"));
+
+    let script_pubkey = &prevouts[input_idx].script_pubkey;
+    assert!(script_pubkey.is_p2tr(), "spent output is not P2TR");
+    let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..]).unwrap();
+    assert!(
+        control_block.verify_taproot_commitment(
+            &Secp256k1::verification_only(),
+            output_key,
+            &script
+        ),
+        "leaf script is not committed in the spent output"
+    );
+
+    let leaf_hash = TapLeafHash::from_script(&script, LeafVersion::TapScript);
+    let mut exec = Exec::new(
+        ExecCtx::Tapscript,
+        Options::default(),
+        TxTemplate {
+            tx: tx.clone(),
+            prevouts: prevouts.to_vec(),
+            input_idx,
+            taproot_annex_scriptleaf: Some((leaf_hash, None)),
+        },
+        script,
+        stack,
+    )
+    .expect("error when creating the execution body");
+
+    while exec.exec_next().is_ok() {}
+
+    exec.result().expect("execution is finished").clone()
+}
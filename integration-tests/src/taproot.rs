@@ -19,6 +19,7 @@ use bitcoincore_rpc::{
 };
 use bitvm2_core::{
     assert::{AssertTransaction, Options},
+    disprove::derivation::KeyDerivation,
     treepp::*,
 };
 use once_cell::sync::Lazy;
@@ -152,8 +153,13 @@ where
         Amount::from_sat(APPROX_TXOUT_AMOUNT - 1_000),
         Options {
             payout_locktime: Height::from(1),
+            ..Default::default()
         },
-    );
+        &KeyDerivation::for_program::<S>(
+            [1; 32],
+            OutPoint::new(funding_txid, funding_txout_idx as u32),
+        ),
+    )?;
 
     let atx = assert_tx.clone().spend_p2wpkh_input_tx(
         &ctx,
@@ -213,9 +219,10 @@ where
             Amount::from_sat(APPROX_TXOUT_AMOUNT * 9 / 10),
             Options {
                 payout_locktime: Height::from(1),
+                ..Default::default()
             },
             [1; 32],
-        );
+        )?;
 
     let atx = assert_tx.clone().spend_p2wpkh_input_tx(
         &ctx,
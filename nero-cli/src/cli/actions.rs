@@ -1,16 +1,15 @@
-use std::{collections::BTreeMap, fs, path::PathBuf, str::FromStr as _};
+use std::{fs, path::PathBuf, str::FromStr as _};
 
 use bitcoin::{
     address::NetworkUnchecked,
     consensus::{Decodable, Encodable},
     hashes::Hash,
     io::Cursor,
-    key::rand::{rngs::SmallRng, thread_rng},
+    key::rand::thread_rng,
     secp256k1::SecretKey,
     Address, Amount, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid, XOnlyPublicKey,
 };
 use bitcoin_splitter::split::script::{IOPair, SplitableScript};
-use bitcoin_testscripts::square_fibonacci::SquareFibonacciScript;
 use bitcoincore_rpc::{
     bitcoin::{
         consensus::{Decodable as _, Encodable as _},
@@ -19,13 +18,17 @@ use bitcoincore_rpc::{
     RpcApi,
 };
 use bitvm2_core::{
-    assert::{payout_script::PayoutScript, AssertTransaction, Options},
-    disprove::DisproveScript,
+    assert::{AssertTransaction, Options},
+    bundle::AssertBundle,
+    disprove::{derivation::KeyDerivation, find_disprovable_shards, Distortion},
 };
-use clap::Args;
+use clap::{Args, ValueEnum as _};
+use eyre::OptionExt as _;
 
 use crate::context::Context;
 
+use super::programs::{with_program, Program};
+
 #[derive(Args, Debug, Clone)]
 pub struct AssertTxArgs {
     #[arg(long)]
@@ -34,21 +37,67 @@ pub struct AssertTxArgs {
     pub amount: Amount,
     #[arg(long)]
     pub pubkey: XOnlyPublicKey,
+    /// Outpoint of the Kickoff connector spent by this Assert. The Winternitz
+    /// keys are derived for it, so it must be unique for each Assert.
+    #[arg(long)]
+    pub kickoff: OutPoint,
+    /// Testing mode: distort the output state of the shard with this index.
+    #[arg(long, conflicts_with = "distort_seed")]
+    pub distort_shard: Option<usize>,
+    /// Testing mode: distort the output state of the shard chosen randomly
+    /// from this seed.
     #[arg(long)]
-    pub distort: bool,
+    pub distort_seed: Option<u64>,
+    /// Path to write the bundle with Assert scripts to, binary unless
+    /// extension is `json`.
+    #[arg(long, default_value = DEFAULT_BUNDLE_FILE)]
+    pub bundle: PathBuf,
+    #[arg(long, value_enum, default_value_t)]
+    pub program: Program,
 }
 
+const DEFAULT_BUNDLE_FILE: &str = "assert.json";
+
 pub fn assert_tx(ctx: Context, args: AssertTxArgs) -> eyre::Result<()> {
+    with_program!(args.program, S => assert_tx_for::<S>(ctx, args))
+}
+
+fn assert_tx_for<S: SplitableScript>(ctx: Context, args: AssertTxArgs) -> eyre::Result<()> {
     let input_script: ScriptBuf = hex::decode(fs::read_to_string(args.input)?)?.into();
 
     let opts = Options::default();
+    let derivation = KeyDerivation::for_program::<S>(ctx.master_seed()?, args.kickoff);
+
+    let distortion = match (args.distort_shard, args.distort_seed) {
+        (Some(idx), _) => Some(Distortion::Shard(idx)),
+        (None, Some(seed)) => Some(Distortion::Seed(seed)),
+        (None, None) => None,
+    };
 
-    // FIXME(Velnbur): make this optionally valid
-    let (assert, invalid_chunk_idx) =
-        AssertTransaction::<SquareFibonacciScript<1024>>::with_options_distorted::<
-            [u8; 32],
-            SmallRng,
-        >(input_script, args.pubkey, args.amount, opts, [1; 32]);
+    let (assert, invalid_chunk_idx) = match distortion {
+        Some(distortion) => {
+            let (assert, idx) = AssertTransaction::<S>::with_options_distorted_by(
+                input_script,
+                args.pubkey,
+                args.amount,
+                opts,
+                distortion,
+                &derivation,
+            )?;
+
+            (assert, Some(idx))
+        }
+        None => (
+            AssertTransaction::<S>::with_options(
+                input_script,
+                args.pubkey,
+                args.amount,
+                opts,
+                &derivation,
+            )?,
+            None,
+        ),
+    };
 
     let assert_output_address = Address::from_script(
         &assert.txout(ctx.secp_ctx()).script_pubkey,
@@ -77,33 +126,11 @@ pub fn assert_tx(ctx: Context, args: AssertTxArgs) -> eyre::Result<()> {
         .unwrap();
 
     println!("{assert_txid}:{output}");
-    if args.distort {
+    if let Some(invalid_chunk_idx) = invalid_chunk_idx {
         println!("{invalid_chunk_idx}");
     }
 
-    fs::write(
-        "payout.txt",
-        assert.payout_script.to_script().to_hex_string(),
-    )?;
-
-    let base_path = PathBuf::from("disproves");
-    if base_path.exists() {
-        // TODO(Velnbur): we should ask about that later
-        fs::remove_dir_all(&base_path)?;
-    }
-
-    for (idx, disprove_script) in assert.disprove_scripts.iter().enumerate() {
-        let disprove_base_path = base_path.join(format!("{:06}", idx));
-        fs::create_dir_all(&disprove_base_path)?;
-        fs::write(
-            disprove_base_path.join("script_pubkey.txt"),
-            disprove_script.script_pubkey.to_hex_string(),
-        )?;
-        fs::write(
-            disprove_base_path.join("witness.txt"),
-            disprove_script.script_witness.to_hex_string(),
-        )?;
-    }
+    AssertBundle::new(&assert).write_to_file(&args.bundle)?;
 
     Ok(())
 }
@@ -127,37 +154,19 @@ pub struct PayoutSpendArgs {
     address: Address<NetworkUnchecked>,
     #[arg(long)]
     seckey: SecretKey,
+    /// Path to the bundle written by `assert-tx`.
+    #[arg(long, default_value = DEFAULT_BUNDLE_FILE)]
+    bundle: PathBuf,
+    #[arg(long, value_enum, default_value_t)]
+    program: Program,
 }
 
 pub fn spend_payout(ctx: Context, args: PayoutSpendArgs) -> eyre::Result<()> {
-    let base_path = PathBuf::from("disproves");
-
-    let dirs = fs::read_dir(base_path)?;
-    let mut disprove_scripts = BTreeMap::new();
-
-    for dir in dirs.filter_map(Result::ok) {
-        let dir_name = dir.file_name().into_string().unwrap();
-
-        let Ok(disprove_script_num) = dir_name.parse::<usize>() else {
-            continue;
-        };
-
-        let script_pubkey: ScriptBuf =
-            hex::decode(fs::read_to_string(dir.path().join("script_pubkey.txt"))?)?.into();
-        let script_witness: ScriptBuf =
-            hex::decode(fs::read_to_string(dir.path().join("witness.txt"))?)?.into();
-
-        disprove_scripts.insert(
-            disprove_script_num,
-            DisproveScript {
-                script_witness,
-                script_pubkey,
-            },
-        );
-    }
+    with_program!(args.program, S => spend_payout_for::<S>(ctx, args))
+}
 
-    let disprove_scripts = disprove_scripts.values().cloned().collect::<Vec<_>>();
-    // let payout_script: ScriptBuf = hex::decode(fs::read_to_string("input.txt")?)?.into();
+fn spend_payout_for<S: SplitableScript>(ctx: Context, args: PayoutSpendArgs) -> eyre::Result<()> {
+    let assert = AssertBundle::read_from_file(&args.bundle)?.into_assert::<S>()?;
 
     let assert_tx = ctx.client().get_raw_transaction(&args.assert.txid, None)?;
 
@@ -167,16 +176,6 @@ pub fn spend_payout(ctx: Context, args: PayoutSpendArgs) -> eyre::Result<()> {
         let mut cursor = Cursor::new(buf);
         TxOut::consensus_decode(&mut cursor)?
     };
-    let operator_pubkey = args.seckey.public_key(ctx.secp_ctx()).x_only_public_key().0;
-    let payout = PayoutScript::new(operator_pubkey);
-
-    let assert = AssertTransaction::<SquareFibonacciScript<1024>>::from_scripts(
-        operator_pubkey,
-        payout,
-        disprove_scripts,
-        assert_txout.value,
-    );
-
     let tx = assert.payout_transaction(
         ctx.secp_ctx(),
         TxOut {
@@ -210,39 +209,30 @@ pub struct DisproveSpendArgs {
     assert: bitcoincore_rpc::bitcoin::OutPoint,
     #[arg(long)]
     address: Address<NetworkUnchecked>,
+    /// Index of the disprove script to spend. If not set, the first
+    /// spendable one is found from the committed states.
     #[arg(long)]
-    disprove: usize,
+    disprove: Option<usize>,
+    /// Path to the program input, required if `--disprove` is not set.
+    #[arg(long, required_unless_present = "disprove")]
+    input: Option<PathBuf>,
+    /// Path to the bundle written by `assert-tx`.
+    #[arg(long, default_value = DEFAULT_BUNDLE_FILE)]
+    bundle: PathBuf,
+    #[arg(long, value_enum, default_value_t)]
+    program: Program,
 }
 
 pub fn spend_disprove(ctx: Context, args: DisproveSpendArgs) -> eyre::Result<()> {
-    let base_path = PathBuf::from("disproves");
-
-    let dirs = fs::read_dir(base_path)?;
-    let mut disprove_scripts = BTreeMap::new();
-
-    for dir in dirs.filter_map(Result::ok) {
-        let dir_name = dir.file_name().into_string().unwrap();
-
-        let Ok(disprove_script_num) = dir_name.parse::<usize>() else {
-            continue;
-        };
-
-        let script_pubkey: ScriptBuf =
-            hex::decode(fs::read_to_string(dir.path().join("script_pubkey.txt"))?)?.into();
-        let script_witness: ScriptBuf =
-            hex::decode(fs::read_to_string(dir.path().join("witness.txt"))?)?.into();
-
-        disprove_scripts.insert(
-            disprove_script_num,
-            DisproveScript {
-                script_witness,
-                script_pubkey,
-            },
-        );
-    }
+    with_program!(args.program, S => spend_disprove_for::<S>(ctx, args))
+}
 
-    let disprove_scripts = disprove_scripts.values().cloned().collect::<Vec<_>>();
-    let payout_script: ScriptBuf = hex::decode(fs::read_to_string("payout.txt")?)?.into();
+fn spend_disprove_for<S: SplitableScript>(
+    ctx: Context,
+    args: DisproveSpendArgs,
+) -> eyre::Result<()> {
+    let assert = AssertBundle::read_from_file(&args.bundle)?.into_assert::<S>()?;
+    let disprove_scripts = &assert.disprove_scripts;
 
     let assert_tx = ctx.client().get_raw_transaction(&args.assert.txid, None)?;
 
@@ -253,10 +243,23 @@ pub fn spend_disprove(ctx: Context, args: DisproveSpendArgs) -> eyre::Result<()>
         TxOut::consensus_decode(&mut cursor)?
     };
 
-    let disprove_script = &disprove_scripts[args.disprove];
-    let tx = &AssertTransaction::<SquareFibonacciScript<1024>>::form_disprove_transactions(
-        payout_script,
-        &disprove_scripts,
+    let disprove_idx = match args.disprove {
+        Some(idx) => idx,
+        None => {
+            let input_path = args
+                .input
+                .ok_or_eyre("either --disprove or --input should be provided")?;
+            let input: ScriptBuf = hex::decode(fs::read_to_string(input_path)?)?.into();
+
+            let disprovable = find_disprovable_shards::<S>(input, disprove_scripts)?;
+
+            *disprovable
+                .first()
+                .ok_or_eyre("all committed states are correct, nothing to disprove")?
+        }
+    };
+    let disprove_script = &disprove_scripts[disprove_idx];
+    let tx = &assert.disprove_transactions(
         ctx.secp_ctx(),
         TxOut {
             script_pubkey: args.address.assume_checked().script_pubkey(),
@@ -288,12 +291,21 @@ const DEFAULT_OUTPUT_FILE: &str = "input.txt";
 pub struct GenerateInputArgs {
     #[arg(long)]
     output: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t)]
+    program: Program,
 }
 
-pub fn generate_input(_ctx: Context, args: GenerateInputArgs) -> eyre::Result<()> {
+pub fn generate_input(ctx: Context, args: GenerateInputArgs) -> eyre::Result<()> {
+    with_program!(args.program, S => generate_input_for::<S>(ctx, args))
+}
+
+fn generate_input_for<S: SplitableScript>(
+    _ctx: Context,
+    args: GenerateInputArgs,
+) -> eyre::Result<()> {
     let output = args.output.unwrap_or_else(|| DEFAULT_OUTPUT_FILE.into());
 
-    let IOPair { input, .. } = SquareFibonacciScript::<1024>::generate_valid_io_pair();
+    let IOPair { input, .. } = S::generate_valid_io_pair();
 
     println!("{}", input.to_asm_string());
 
@@ -302,6 +314,21 @@ pub fn generate_input(_ctx: Context, args: GenerateInputArgs) -> eyre::Result<()
     Ok(())
 }
 
+pub fn list_programs() -> eyre::Result<()> {
+    for program in Program::value_variants() {
+        let value = program
+            .to_possible_value()
+            .expect("no programs are skipped");
+
+        match value.get_help() {
+            Some(help) => println!("{:<20} {help}", value.get_name()),
+            None => println!("{}", value.get_name()),
+        }
+    }
+
+    Ok(())
+}
+
 pub fn generate_keys(ctx: Context) -> eyre::Result<()> {
     let (seckey, pubkey) = ctx.secp_ctx().generate_keypair(&mut thread_rng());
 
@@ -9,6 +9,7 @@ use crate::{config::Config, context::Context};
 use self::actions::{AssertTxArgs, DisproveSpendArgs, GenerateInputArgs, PayoutSpendArgs};
 
 mod actions;
+mod programs;
 
 #[derive(Parser)]
 pub struct Cli {
@@ -41,6 +42,7 @@ impl Cli {
             Command::SpendPayout(args) => actions::spend_payout(context, args),
             Command::SpendDisprove(args) => actions::spend_disprove(context, args),
             Command::GenerateKeys => actions::generate_keys(context),
+            Command::ListPrograms => actions::list_programs(),
         }
     }
 }
@@ -52,4 +54,6 @@ pub enum Command {
     SpendPayout(PayoutSpendArgs),
     SpendDisprove(DisproveSpendArgs),
     GenerateKeys,
+    /// List programs available for `--program`.
+    ListPrograms,
 }
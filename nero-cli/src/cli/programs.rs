@@ -0,0 +1,64 @@
+//! Registry of programs from `bitcoin-testscripts` the CLI can assert.
+
+use clap::ValueEnum;
+
+/// Program which execution is asserted.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Program {
+    /// Widening multiplication of two 254-bit integers.
+    #[value(name = "u254-mul")]
+    U254Mul,
+    /// Multiplication of two 254-bit integers, optimized for splitting.
+    #[value(name = "friendly-u254-mul")]
+    FriendlyU254Mul,
+    /// Karatsuba multiplication of two 261-bit integers.
+    #[value(name = "u261-mul-karatsuba")]
+    U261MulKaratsuba,
+    /// SHA-256 of 32 bytes.
+    #[value(name = "sha256-32")]
+    Sha256_32,
+    /// 64 steps of the square Fibonacci sequence.
+    #[value(name = "square-fib:64")]
+    SquareFib64,
+    /// 1024 steps of the square Fibonacci sequence.
+    #[default]
+    #[value(name = "square-fib:1024")]
+    SquareFib1024,
+}
+
+/// Evaluate `$body` with `$S` being the type alias of the `SplitableScript`
+/// implementation of `$program`.
+macro_rules! with_program {
+    ($program:expr, $S:ident => $body:expr) => {{
+        use $crate::cli::programs::Program;
+
+        match $program {
+            Program::U254Mul => {
+                type $S = bitcoin_testscripts::int_mul_windowed::U254MulScript;
+                $body
+            }
+            Program::FriendlyU254Mul => {
+                type $S = bitcoin_testscripts::friendly::bigint_mul::FriendlyU254MulScript;
+                $body
+            }
+            Program::U261MulKaratsuba => {
+                type $S = bitcoin_testscripts::u261_mul_karatsuba::U261MulKaratsubaScript;
+                $body
+            }
+            Program::Sha256_32 => {
+                type $S = bitcoin_testscripts::sha256::SHA256Script<32>;
+                $body
+            }
+            Program::SquareFib64 => {
+                type $S = bitcoin_testscripts::square_fibonacci::SquareFibonacciScript<64>;
+                $body
+            }
+            Program::SquareFib1024 => {
+                type $S = bitcoin_testscripts::square_fibonacci::SquareFibonacciScript<1024>;
+                $body
+            }
+        }
+    }};
+}
+
+pub(crate) use with_program;
@@ -7,6 +7,10 @@ pub struct Config {
     pub bitcoin_password: String,
     pub bitcoin_username: String,
     pub bitcoin_url: String,
+    /// Hex-encoded 32-byte seed the Winternitz keys of the Assert
+    /// transactions are derived from. Required only to create Asserts.
+    #[serde(default)]
+    pub master_seed: Option<String>,
 }
 
 impl Config {
@@ -22,6 +26,18 @@ impl Config {
         toml::from_str(&src).map_err(Into::into)
     }
 
+    /// Parsed [`Self::master_seed`], if it is set.
+    pub fn master_seed(&self) -> eyre::Result<Option<[u8; 32]>> {
+        let Some(seed) = &self.master_seed else {
+            return Ok(None);
+        };
+        let seed = hex::decode(seed)?;
+
+        seed.try_into()
+            .map(Some)
+            .map_err(|_| eyre::eyre!("master seed must be 32 bytes long"))
+    }
+
     pub fn bitcoinrpc_auth(&self) -> bitcoincore_rpc::Auth {
         bitcoincore_rpc::Auth::UserPass(
             self.bitcoin_username.clone(),
@@ -6,14 +6,21 @@ pub struct Context {
     client: bitcoincore_rpc::Client,
 
     secp: Secp256k1<All>,
+
+    master_seed: Option<[u8; 32]>,
 }
 
 impl Context {
     pub fn from_config(config: Config) -> eyre::Result<Self> {
         let client = bitcoincore_rpc::Client::new(&config.bitcoin_url, config.bitcoinrpc_auth())?;
         let secp = Secp256k1::new();
+        let master_seed = config.master_seed()?;
 
-        Ok(Self { client, secp })
+        Ok(Self {
+            client,
+            secp,
+            master_seed,
+        })
     }
 
     pub fn client(&self) -> &bitcoincore_rpc::Client {
@@ -23,4 +30,12 @@ impl Context {
     pub fn secp_ctx(&self) -> &Secp256k1<All> {
         &self.secp
     }
+
+    pub fn master_seed(&self) -> eyre::Result<[u8; 32]> {
+        self.master_seed.ok_or_else(|| {
+            eyre::eyre!(
+                "`master_seed` is not set in the config, generate one with `openssl rand -hex 32`"
+            )
+        })
+    }
 }